@@ -4,6 +4,7 @@ struct VertexInput {
 	@location(0) pos: vec3<f32>,
 	@location(1) tex_coords: vec2<f32>,
 	@location(2) light: f32,
+	@location(3) waving: f32,
 }
 
 struct VertexOutput {
@@ -15,12 +16,43 @@ struct VertexOutput {
 @group(1) @binding(0) var<uniform> view_proj: mat4x4<f32>;
 @group(2) @binding(0) var<uniform> model: mat4x4<f32>;
 
+struct MapUniform {
+	time: f32,
+}
+
+@group(0) @binding(2) var<uniform> map: MapUniform;
+
+// displacement for waving nodes, see upstream's nodes_shader
+// all frequencies are multiples of 0.1 so the time can wrap around (see WAVE_PERIOD)
+fn wave(pos: vec3<f32>, waving: f32) -> vec3<f32> {
+	let t = map.time;
+	var out = pos;
+
+	if waving == 1.0 || waving == 2.0 {
+		let disp_x = sin(t * 1.9 + pos.x * 0.6 + pos.z * 0.2) * 0.04 + sin(t * 3.1 + pos.y * 0.4) * 0.02;
+		let disp_z = sin(t * 1.4 + pos.z * 0.6 + pos.x * 0.3) * 0.04 + sin(t * 2.7 + pos.y * 0.5) * 0.02;
+
+		out.x = out.x + disp_x;
+		out.z = out.z + disp_z;
+
+		if waving == 2.0 {
+			out.y = out.y + disp_z * 0.1;
+		}
+	} else if waving == 3.0 {
+		// only move downwards so liquid surfaces never poke through the nodes above
+		out.y = out.y + (sin(t * 2.0 + pos.x * 0.5) + sin(t * 1.4 + pos.z * 0.6) - 2.0) * 0.04;
+	}
+
+	return out;
+}
+
 @vertex
 fn vs_main(
 	in: VertexInput,
 ) -> VertexOutput {
 	var out: VertexOutput;
-	out.pos = view_proj * model * vec4<f32>(in.pos, 1.0);
+	let world_pos = model * vec4<f32>(in.pos, 1.0);
+	out.pos = view_proj * vec4<f32>(wave(world_pos.xyz, in.waving), 1.0);
 	out.tex_coords = in.tex_coords;
 	out.light = in.light;
 	return out;
@@ -42,3 +74,68 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
 	color = vec4<f32>(color.rgb * in.light, color.a);
 	return color;
 }
+
+// Shadowed variants, only used when shadow mapping is enabled
+
+struct Shadow {
+	light_view_proj: mat4x4<f32>,
+	// x: strength, y: texel size, z: depth bias
+	params: vec4<f32>,
+}
+
+@group(3) @binding(0) var<uniform> shadow: Shadow;
+@group(3) @binding(1) var shadow_map: texture_depth_2d;
+@group(3) @binding(2) var shadow_sampler: sampler_comparison;
+
+struct ShadowedVertexOutput {
+	@builtin(position) pos: vec4<f32>,
+	@location(0) tex_coords: vec2<f32>,
+	@location(1) light: f32,
+	@location(2) light_pos: vec4<f32>,
+}
+
+@vertex
+fn vs_shadowed(
+	in: VertexInput,
+) -> ShadowedVertexOutput {
+	var out: ShadowedVertexOutput;
+	let world_pos = vec4<f32>(wave((model * vec4<f32>(in.pos, 1.0)).xyz, in.waving), 1.0);
+	out.pos = view_proj * world_pos;
+	out.tex_coords = in.tex_coords;
+	out.light = in.light;
+	out.light_pos = shadow.light_view_proj * world_pos;
+	return out;
+}
+
+// 3x3 PCF, returns the fraction of samples in shadow
+fn shadow_factor(light_pos: vec4<f32>) -> f32 {
+	let proj = light_pos.xyz / light_pos.w;
+	let uv = proj.xy * vec2<f32>(0.5, -0.5) + 0.5;
+
+	if uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0 || proj.z > 1.0 {
+		return 0.0;
+	}
+
+	var lit = 0.0;
+	for (var x = -1; x <= 1; x = x + 1) {
+		for (var y = -1; y <= 1; y = y + 1) {
+			let offset = vec2<f32>(f32(x), f32(y)) * shadow.params.y;
+			lit = lit + textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, proj.z - shadow.params.z);
+		}
+	}
+
+	return 1.0 - lit / 9.0;
+}
+
+@fragment
+fn fs_shadowed(in: ShadowedVertexOutput) -> @location(0) vec4<f32> {
+	var color = textureSample(atlas_texture, atlas_sampler, in.tex_coords);
+
+	if color.a < 0.1 {
+		discard;
+	}
+
+	let shade = 1.0 - shadow_factor(in.light_pos) * shadow.params.x;
+	color = vec4<f32>(color.rgb * in.light * shade, color.a);
+	return color;
+}
@@ -0,0 +1,152 @@
+struct PostUniform {
+	exposure: f32,
+	saturation: f32,
+	gamma: f32,
+	bloom_intensity: f32,
+	bloom_threshold: f32,
+	tone_mapping: f32,
+	texel_size: vec2<f32>,
+}
+
+@group(0) @binding(0) var<uniform> post: PostUniform;
+
+@group(1) @binding(0) var input_texture: texture_2d<f32>;
+@group(1) @binding(1) var input_sampler: sampler;
+
+@group(2) @binding(0) var bloom_texture: texture_2d<f32>;
+@group(2) @binding(1) var bloom_sampler: sampler;
+
+struct VertexOutput {
+	@builtin(position) pos: vec4<f32>,
+	@location(0) tex_coords: vec2<f32>,
+}
+
+@vertex
+fn vs_main(
+	@builtin(vertex_index) index: u32,
+) -> VertexOutput {
+	// fullscreen triangle
+	var out: VertexOutput;
+	let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+	out.pos = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+	out.tex_coords = vec2<f32>(uv.x, 1.0 - uv.y);
+	return out;
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+	return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// Bloom
+
+@fragment
+fn fs_bright(in: VertexOutput) -> @location(0) vec4<f32> {
+	let color = textureSample(input_texture, input_sampler, in.tex_coords).rgb * post.exposure;
+	let brightness = luminance(color);
+	let factor = max(brightness - post.bloom_threshold, 0.0) / max(brightness, 0.0001);
+	return vec4<f32>(color * factor, 1.0);
+}
+
+fn blur(tex_coords: vec2<f32>, dir: vec2<f32>) -> vec4<f32> {
+	// 9-tap gaussian using linear sampling
+	var offsets = array<f32, 3>(0.0, 1.3846153846, 3.2307692308);
+	var weights = array<f32, 3>(0.2270270270, 0.3162162162, 0.0702702703);
+
+	var color = textureSample(input_texture, input_sampler, tex_coords).rgb * weights[0];
+	for (var i = 1; i < 3; i = i + 1) {
+		let offset = dir * offsets[i] * post.texel_size * 2.0;
+		color = color + textureSample(input_texture, input_sampler, tex_coords + offset).rgb * weights[i];
+		color = color + textureSample(input_texture, input_sampler, tex_coords - offset).rgb * weights[i];
+	}
+
+	return vec4<f32>(color, 1.0);
+}
+
+@fragment
+fn fs_blur_h(in: VertexOutput) -> @location(0) vec4<f32> {
+	return blur(in.tex_coords, vec2<f32>(1.0, 0.0));
+}
+
+@fragment
+fn fs_blur_v(in: VertexOutput) -> @location(0) vec4<f32> {
+	return blur(in.tex_coords, vec2<f32>(0.0, 1.0));
+}
+
+// Composition
+
+// filmic tone mapping curve by John Hable, as used by upstream
+fn hable(x: vec3<f32>) -> vec3<f32> {
+	let a = 0.15;
+	let b = 0.50;
+	let c = 0.10;
+	let d = 0.20;
+	let e = 0.02;
+	let f = 0.30;
+	return ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f;
+}
+
+fn tone_map(color: vec3<f32>) -> vec3<f32> {
+	let white = 11.2;
+	return hable(color * 2.0) / hable(vec3<f32>(white));
+}
+
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+	var color = textureSample(input_texture, input_sampler, in.tex_coords).rgb * post.exposure;
+	color = color + textureSample(bloom_texture, bloom_sampler, in.tex_coords).rgb * post.bloom_intensity;
+
+	if post.tone_mapping > 0.5 {
+		color = tone_map(color);
+	}
+
+	color = mix(vec3<f32>(luminance(color)), color, post.saturation);
+	color = pow(max(color, vec3<f32>(0.0)), vec3<f32>(1.0 / post.gamma));
+
+	return vec4<f32>(color, 1.0);
+}
+
+// FXAA, simplified version of the console variant by Timothy Lottes
+
+@fragment
+fn fs_fxaa(in: VertexOutput) -> @location(0) vec4<f32> {
+	let t = post.texel_size;
+	let uv = in.tex_coords;
+
+	let rgb_nw = textureSample(input_texture, input_sampler, uv + vec2<f32>(-1.0, -1.0) * t).rgb;
+	let rgb_ne = textureSample(input_texture, input_sampler, uv + vec2<f32>(1.0, -1.0) * t).rgb;
+	let rgb_sw = textureSample(input_texture, input_sampler, uv + vec2<f32>(-1.0, 1.0) * t).rgb;
+	let rgb_se = textureSample(input_texture, input_sampler, uv + vec2<f32>(1.0, 1.0) * t).rgb;
+	let rgb_m = textureSample(input_texture, input_sampler, uv).rgb;
+
+	let luma_nw = luminance(rgb_nw);
+	let luma_ne = luminance(rgb_ne);
+	let luma_sw = luminance(rgb_sw);
+	let luma_se = luminance(rgb_se);
+	let luma_m = luminance(rgb_m);
+
+	let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+	let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+	var dir = vec2<f32>(
+		-((luma_nw + luma_ne) - (luma_sw + luma_se)),
+		(luma_nw + luma_sw) - (luma_ne + luma_se),
+	);
+
+	let dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * 0.125, 1.0 / 128.0);
+	let rcp_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+	dir = clamp(dir * rcp_dir_min, vec2<f32>(-8.0), vec2<f32>(8.0)) * t;
+
+	let rgb_a = 0.5 * (
+		textureSample(input_texture, input_sampler, uv + dir * (1.0 / 3.0 - 0.5)).rgb +
+		textureSample(input_texture, input_sampler, uv + dir * (2.0 / 3.0 - 0.5)).rgb);
+	let rgb_b = rgb_a * 0.5 + 0.25 * (
+		textureSample(input_texture, input_sampler, uv + dir * -0.5).rgb +
+		textureSample(input_texture, input_sampler, uv + dir * 0.5).rgb);
+
+	let luma_b = luminance(rgb_b);
+	if luma_b < luma_min || luma_b > luma_max {
+		return vec4<f32>(rgb_a, 1.0);
+	}
+
+	return vec4<f32>(rgb_b, 1.0);
+}
@@ -0,0 +1,37 @@
+struct VertexInput {
+	@location(0) pos: vec3<f32>,
+	@location(1) tex_coords: vec2<f32>,
+	@location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+	@builtin(position) pos: vec4<f32>,
+	@location(0) tex_coords: vec2<f32>,
+	@location(1) color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> view_proj: mat4x4<f32>;
+
+@vertex
+fn vs_main(
+	in: VertexInput,
+) -> VertexOutput {
+	var out: VertexOutput;
+	out.pos = view_proj * vec4<f32>(in.pos, 1.0);
+	out.tex_coords = in.tex_coords;
+	out.color = in.color;
+	return out;
+}
+
+@group(1) @binding(0) var face_texture: texture_2d<f32>;
+@group(1) @binding(1) var face_sampler: sampler;
+
+@fragment
+fn fs_line(in: VertexOutput) -> @location(0) vec4<f32> {
+	return in.color;
+}
+
+@fragment
+fn fs_face(in: VertexOutput) -> @location(0) vec4<f32> {
+	return textureSample(face_texture, face_sampler, in.tex_coords) * in.color;
+}
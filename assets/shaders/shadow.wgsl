@@ -0,0 +1,68 @@
+// Vertex shader
+
+struct VertexInput {
+	@location(0) pos: vec3<f32>,
+	@location(1) tex_coords: vec2<f32>,
+	@location(3) waving: f32,
+}
+
+struct VertexOutput {
+	@builtin(position) pos: vec4<f32>,
+	@location(0) tex_coords: vec2<f32>,
+}
+
+@group(1) @binding(0) var<uniform> light_view_proj: mat4x4<f32>;
+@group(2) @binding(0) var<uniform> model: mat4x4<f32>;
+
+struct MapUniform {
+	time: f32,
+}
+
+@group(0) @binding(2) var<uniform> map: MapUniform;
+
+// displacement for waving nodes, see upstream's nodes_shader
+// all frequencies are multiples of 0.1 so the time can wrap around (see WAVE_PERIOD)
+fn wave(pos: vec3<f32>, waving: f32) -> vec3<f32> {
+	let t = map.time;
+	var out = pos;
+
+	if waving == 1.0 || waving == 2.0 {
+		let disp_x = sin(t * 1.9 + pos.x * 0.6 + pos.z * 0.2) * 0.04 + sin(t * 3.1 + pos.y * 0.4) * 0.02;
+		let disp_z = sin(t * 1.4 + pos.z * 0.6 + pos.x * 0.3) * 0.04 + sin(t * 2.7 + pos.y * 0.5) * 0.02;
+
+		out.x = out.x + disp_x;
+		out.z = out.z + disp_z;
+
+		if waving == 2.0 {
+			out.y = out.y + disp_z * 0.1;
+		}
+	} else if waving == 3.0 {
+		// only move downwards so liquid surfaces never poke through the nodes above
+		out.y = out.y + (sin(t * 2.0 + pos.x * 0.5) + sin(t * 1.4 + pos.z * 0.6) - 2.0) * 0.04;
+	}
+
+	return out;
+}
+
+@vertex
+fn vs_main(
+	in: VertexInput,
+) -> VertexOutput {
+	var out: VertexOutput;
+	let world_pos = model * vec4<f32>(in.pos, 1.0);
+	out.pos = light_view_proj * vec4<f32>(wave(world_pos.xyz, in.waving), 1.0);
+	out.tex_coords = in.tex_coords;
+	return out;
+}
+
+// Fragment shader
+
+@group(0) @binding(0) var atlas_texture: texture_2d<f32>;
+@group(0) @binding(1) var atlas_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) {
+	if textureSample(atlas_texture, atlas_sampler, in.tex_coords).a < 0.1 {
+		discard;
+	}
+}
@@ -0,0 +1,108 @@
+struct SkyUniform {
+	view_proj: mat4x4<f32>,
+	inv_view_proj: mat4x4<f32>,
+	sky_color: vec4<f32>,
+	horizon_color: vec4<f32>,
+	cloud_color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> sky: SkyUniform;
+
+// Sky dome
+
+struct DomeOutput {
+	@builtin(position) pos: vec4<f32>,
+	@location(0) ndc: vec2<f32>,
+}
+
+@vertex
+fn vs_dome(
+	@builtin(vertex_index) index: u32,
+) -> DomeOutput {
+	// fullscreen triangle
+	var out: DomeOutput;
+	out.ndc = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u)) * 2.0 - 1.0;
+	out.pos = vec4<f32>(out.ndc, 1.0, 1.0);
+	return out;
+}
+
+@fragment
+fn fs_dome(in: DomeOutput) -> @location(0) vec4<f32> {
+	let world = sky.inv_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+	let dir = normalize(world.xyz / world.w);
+
+	var color = mix(sky.horizon_color.rgb, sky.sky_color.rgb, sqrt(clamp(dir.y * 2.5, 0.0, 1.0)));
+	if dir.y < 0.0 {
+		color = color * (1.0 + max(dir.y, -0.5));
+	}
+
+	return vec4<f32>(color, 1.0);
+}
+
+// Skybox, sun, moon and stars
+
+struct BodyInput {
+	@location(0) pos: vec3<f32>,
+	@location(1) tex_coords: vec2<f32>,
+	@location(2) color: vec4<f32>,
+}
+
+struct BodyOutput {
+	@builtin(position) pos: vec4<f32>,
+	@location(0) tex_coords: vec2<f32>,
+	@location(1) color: vec4<f32>,
+}
+
+@vertex
+fn vs_body(
+	in: BodyInput,
+) -> BodyOutput {
+	var out: BodyOutput;
+	out.pos = (sky.view_proj * vec4<f32>(in.pos, 1.0)).xyww;
+	out.tex_coords = in.tex_coords;
+	out.color = in.color;
+	return out;
+}
+
+@group(1) @binding(0) var body_texture: texture_2d<f32>;
+@group(1) @binding(1) var body_sampler: sampler;
+
+@fragment
+fn fs_body(in: BodyOutput) -> @location(0) vec4<f32> {
+	let color = textureSample(body_texture, body_sampler, in.tex_coords) * in.color;
+
+	if color.a < 0.01 {
+		discard;
+	}
+
+	return color;
+}
+
+// Clouds
+
+struct CloudInput {
+	@location(0) pos: vec3<f32>,
+	@location(1) shade: f32,
+}
+
+struct CloudOutput {
+	@builtin(position) pos: vec4<f32>,
+	@location(0) shade: f32,
+}
+
+@group(1) @binding(0) var<uniform> view_proj: mat4x4<f32>;
+
+@vertex
+fn vs_cloud(
+	in: CloudInput,
+) -> CloudOutput {
+	var out: CloudOutput;
+	out.pos = view_proj * vec4<f32>(in.pos, 1.0);
+	out.shade = in.shade;
+	return out;
+}
+
+@fragment
+fn fs_cloud(in: CloudOutput) -> @location(0) vec4<f32> {
+	return vec4<f32>(sky.cloud_color.rgb * in.shade, sky.cloud_color.a);
+}
@@ -0,0 +1,15 @@
+@group(0) @binding(0) var<uniform> color: vec4<f32>;
+
+@vertex
+fn vs_main(
+	@builtin(vertex_index) index: u32,
+) -> @builtin(position) vec4<f32> {
+	// fullscreen triangle
+	let ndc = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u)) * 2.0 - 1.0;
+	return vec4<f32>(ndc, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+	return color;
+}
@@ -0,0 +1,34 @@
+struct VertexInput {
+	@location(0) pos: vec2<f32>,
+	@location(1) tex_coords: vec2<f32>,
+	@location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+	@builtin(position) pos: vec4<f32>,
+	@location(0) tex_coords: vec2<f32>,
+	@location(1) color: vec4<f32>,
+}
+
+// size of the screen in pixels
+@group(0) @binding(0) var<uniform> screen: vec2<f32>;
+
+@vertex
+fn vs_main(
+	in: VertexInput,
+) -> VertexOutput {
+	var out: VertexOutput;
+	// pixels from the top left corner to NDC
+	out.pos = vec4<f32>(in.pos.x / screen.x * 2.0 - 1.0, 1.0 - in.pos.y / screen.y * 2.0, 0.0, 1.0);
+	out.tex_coords = in.tex_coords;
+	out.color = in.color;
+	return out;
+}
+
+@group(1) @binding(0) var ui_texture: texture_2d<f32>;
+@group(1) @binding(1) var ui_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+	return textureSample(ui_texture, ui_sampler, in.tex_coords) * in.color;
+}
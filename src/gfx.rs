@@ -1,20 +1,285 @@
-use crate::{GfxEvent::*, NetEvent};
-use std::time::Instant;
+use crate::{
+    inventory::{self, InvAction, InvLocation, InvSlot},
+    net::{NodeMeta, PlayerState},
+    GfxEvent::*,
+    NetEvent,
+};
+use formspec::{Form, FormContext, FormEvent};
+use gilrs::Button;
+use input::{Action, Input};
+use pause_menu::MenuResult;
+use std::{collections::HashMap, time::Instant};
 use tokio::sync::mpsc;
 use winit::{
-    event::{DeviceEvent::*, Event::*, WindowEvent::*},
+    event::{
+        DeviceEvent::*, ElementState, Event::*, MouseScrollDelta, VirtualKeyCode, WindowEvent::*,
+    },
     event_loop::ControlFlow::ExitWithCode,
     platform::run_return::EventLoopExtRunReturn,
     window::CursorGrabMode,
 };
 
+mod bobbing;
 mod camera;
+mod crosshair;
 mod debug_menu;
 mod font;
+mod formspec;
+mod gamepad;
 mod gpu;
+mod hotbar;
+mod hud;
+mod input;
+mod interact;
+mod items;
 mod map;
 mod media;
+mod pause_menu;
+mod player;
+mod player_model;
+mod post;
+mod raycast;
+mod selection;
+mod settings;
+mod sky;
+mod tint;
+mod ui;
 mod util;
+mod wield;
+
+pub use player::{MovementParams, PhysicsOverride};
+
+/// handles actions that only affect the camera and player,
+/// returns pressed actions that need the rest of the game state
+fn handle_action(
+    action: Action,
+    pressed: bool,
+    camera: &mut camera::Camera,
+    player: &mut player::LocalPlayer,
+    debug_menu: &mut debug_menu::DebugMenu,
+) -> Option<Action> {
+    use player::MoveMode;
+
+    let input = &mut camera.input;
+
+    let held = match action {
+        Action::Forward => Some(&mut input.forward),
+        Action::Backward => Some(&mut input.backward),
+        Action::Left => Some(&mut input.left),
+        Action::Right => Some(&mut input.right),
+        Action::Jump => Some(&mut input.jump),
+        Action::Sneak => Some(&mut input.sneak),
+        Action::Aux1 => Some(&mut input.aux1),
+        Action::Dig => Some(&mut input.dig),
+        Action::Place => Some(&mut input.place),
+        Action::Zoom => Some(&mut input.zoom),
+        _ => None,
+    };
+
+    if let Some(held) = held {
+        *held = pressed;
+        return None;
+    }
+
+    if !pressed {
+        return None;
+    }
+
+    let mode = match action {
+        Action::ToggleFly => MoveMode::Fly,
+        Action::ToggleFast => MoveMode::Fast,
+        Action::ToggleNoclip => MoveMode::Noclip,
+        Action::TogglePitchMove => MoveMode::PitchMove,
+        Action::ToggleDebug => {
+            debug_menu.enabled = !debug_menu.enabled;
+            return None;
+        }
+        Action::ChangeCamera => {
+            camera.mode = camera.mode.next();
+            return None;
+        }
+        Action::ToggleCinematic => {
+            camera.cinematic = !camera.cinematic;
+            println!(
+                "Cinematic mode {}",
+                if camera.cinematic {
+                    "enabled"
+                } else {
+                    "disabled"
+                }
+            );
+            return None;
+        }
+        _ => return Some(action),
+    };
+
+    println!("{}", player.toggle(mode));
+    None
+}
+
+/// tells the server what happened in a form, closing it if asked to
+fn handle_form_events(
+    events: Vec<FormEvent>,
+    form: &mut Option<Form>,
+    inventory: &mut inventory::Inventory,
+    items: &items::ItemRegistry,
+    net_events: &mpsc::UnboundedSender<NetEvent>,
+) {
+    for event in events {
+        match event {
+            FormEvent::Fields(fields) => {
+                let (name, context) = match form {
+                    Some(form) => (form.name.clone(), form.context.clone()),
+                    None => continue,
+                };
+
+                if fields.contains_key("quit") {
+                    *form = None;
+                }
+
+                let event = match context {
+                    InvLocation::NodeMeta(pos) => NetEvent::NodeMetaFields(pos, name, fields),
+                    _ => NetEvent::InvFields(name, fields),
+                };
+                net_events.send(event).ok();
+            }
+            FormEvent::InvAction(action) => {
+                // moves within the player's inventory show up before the server confirms them
+                action.apply(inventory, |name| {
+                    items.get(name).map_or(99, |def| def.stack_max)
+                });
+                net_events.send(NetEvent::InvAction(action)).ok();
+            }
+        }
+    }
+}
+
+/// position and formspec of the node a place interaction would show a form for instead,
+/// sneaking places against the node like upstream
+fn node_formspec<'a>(
+    node_metas: &'a HashMap<cgmath::Point3<i16>, NodeMeta>,
+    sneak: bool,
+    interaction: &interact::Interaction,
+) -> Option<(cgmath::Point3<i16>, &'a str)> {
+    let pos = match interaction {
+        interact::Interaction::Place(pointed) if !sneak => pointed.under.cast::<i16>()?,
+        _ => return None,
+    };
+
+    let formspec = node_metas.get(&pos)?.formspec();
+    (!formspec.is_empty()).then_some((pos, formspec))
+}
+
+/// keeps a form shown for a node up to date with the node's meta,
+/// returns whether the form was closed because the formspec is gone
+fn refresh_node_form(
+    form: &mut Option<Form>,
+    node_metas: &HashMap<cgmath::Point3<i16>, NodeMeta>,
+    prepend: &str,
+    screen: (f32, f32),
+) -> bool {
+    let pos = match form.as_ref().map(|form| &form.context) {
+        Some(InvLocation::NodeMeta(pos)) => *pos,
+        _ => return false,
+    };
+
+    let formspec = node_metas.get(&pos).map_or("", |meta| meta.formspec());
+    if formspec.is_empty() {
+        *form = None;
+        return true;
+    }
+
+    if let Some(form) = form.as_mut().filter(|form| form.formspec != formspec) {
+        form.update(formspec, prepend, screen);
+    }
+    false
+}
+
+/// name of the item in the given slot of the player's main list, empty for the hand
+fn wielded_name(inventory: &inventory::Inventory, index: u16) -> String {
+    inventory
+        .stack("main", index as usize)
+        .filter(|stack| !stack.is_empty())
+        .map_or(String::new(), |stack| stack.name.clone())
+}
+
+/// switches the wielded item, which changes how it is drawn and how it digs
+fn wield_item(
+    name: &str,
+    gpu: &gpu::Gpu,
+    media: &media::MediaMgr,
+    items: &items::ItemRegistry,
+    map: &map::MapRender,
+    wield: &mut wield::WieldItem,
+    interact: &mut interact::Interact,
+) {
+    let name = items.resolve(name);
+    let def = items.get(name);
+
+    let node = def
+        .filter(|def| def.item_type == mt_net::ItemType::Node)
+        .and_then(|_| map.node_by_name(name))
+        .map(|(_, node)| node);
+
+    wield.set_item(gpu, media, items, name, node);
+    interact.tool_caps = items.tool_caps(name).cloned();
+    interact.place_prediction = def.map_or(String::new(), |def| def.place_predict.clone());
+    interact.usable = def.map_or(false, |def| def.usable);
+}
+
+/// info text about the pointed node
+fn pointed_info(
+    map: &map::MapRender,
+    items: &items::ItemRegistry,
+    pointed: &raycast::PointedNode,
+) -> Option<String> {
+    use player::NodeMap;
+
+    map.view()
+        .node(pointed.under)
+        .map(|def| crosshair::info_text(items, def))
+}
+
+/// applies the expected outcome of an interaction before the server confirms it
+fn predict_interaction(
+    map: &mut map::MapRender,
+    player: &player::LocalPlayer,
+    place_prediction: &str,
+    sneak: bool,
+    interaction: &interact::Interaction,
+) {
+    use player::NodeMap;
+
+    let (pos, name) = match interaction {
+        interact::Interaction::Dug(pointed) => match map.view().node(pointed.under) {
+            Some(def) => (pointed.under, def.dig_predict.clone()),
+            None => return,
+        },
+        interact::Interaction::Place(pointed) if !place_prediction.is_empty() => {
+            // the server calls on_rightclick instead of placing, unless sneaking
+            let rightclickable = map
+                .view()
+                .node(pointed.under)
+                .map_or(false, |def| def.rightclickable);
+
+            match interact::place_target(&map.view(), pointed) {
+                Some(pos) if sneak || !rightclickable => (pos, place_prediction.to_string()),
+                _ => return,
+            }
+        }
+        _ => return,
+    };
+
+    let id = match map.node_by_name(&name) {
+        // don't trap the player inside the node
+        Some((_, def)) if def.walkable && interact::node_intersects(pos, &player.aabb()) => return,
+        Some((id, _)) => id,
+        None => return,
+    };
+
+    // rotation is left to the server, it resends the block anyway
+    map.set_node(pos, id, 0);
+}
 
 pub async fn run(
     mut event_loop: winit::event_loop::EventLoop<crate::GfxEvent>,
@@ -26,12 +291,40 @@ pub async fn run(
 
     window.set_cursor_visible(false);
 
-    let mut gpu = gpu::Gpu::new(&window).await;
+    let mut settings = settings::Settings::load();
+    let mut gpu = gpu::Gpu::new(&window, &settings).await;
     let mut map: Option<map::MapRender> = None;
+    let mut sky: Option<sky::Sky> = None;
+    // kept for when sky and map are created, servers don't resend them
+    let mut sky_state = sky::SkyState::default();
+    let mut shadow_intensity = 0.0;
+    let mut player_model: Option<player_model::PlayerModel> = None;
+    let mut selection: Option<selection::Selection> = None;
+    let mut wield: Option<wield::WieldItem> = None;
     let mut font = font::Font::new(&gpu);
     let mut debug_menu = debug_menu::DebugMenu::default();
+    let mut pause_menu = pause_menu::PauseMenu::default();
+    let mut gamepad = gamepad::Gamepad::new(&settings.gamepad);
     let mut media = media::MediaMgr::new();
-    let mut camera = camera::Camera::new(&gpu);
+    let mut camera = camera::Camera::new(&gpu, &settings);
+    let mut tint = tint::ScreenTint::new(&gpu);
+    let mut player = player::LocalPlayer::new();
+    let mut bobbing = bobbing::ViewBobbing::new(&settings.bobbing);
+    let mut pointed: Option<raycast::PointedNode> = None;
+    let mut interact = interact::Interact::default();
+    let mut items = items::ItemRegistry::default();
+    let mut inventory = inventory::Inventory::default();
+    let mut detached_invs = HashMap::new();
+    let mut node_metas = HashMap::new();
+    let mut hud = hud::Hud::default();
+    let mut crosshair = crosshair::Crosshair::new(&settings.crosshair);
+    let mut ui = ui::Ui::new(&gpu);
+    let mut wielded = String::new();
+    let mut pending_actions = Vec::new();
+    let mut form: Option<Form> = None;
+    let mut inventory_formspec = String::new();
+    let mut formspec_prepend = String::new();
+    let mut shift = false;
 
     let mut nodedefs = None;
     let mut last_frame = Instant::now();
@@ -62,27 +355,327 @@ pub async fn run(
             last_frame = now;
 
             debug_menu.fps = fps_counter.tick();
-            camera.update(&gpu, dt);
+
+            for (button, pressed) in gamepad.poll() {
+                if game_paused || (form.is_none() && button == Button::Start) {
+                    let key = match (pressed, gamepad::menu_key(button)) {
+                        // only escape cancels binding a key, other buttons would bind their key
+                        (true, Some(key))
+                            if !pause_menu.capturing() || key == VirtualKeyCode::Escape =>
+                        {
+                            key
+                        }
+                        _ => continue,
+                    };
+
+                    if !game_paused {
+                        game_paused = true;
+                    } else {
+                        match pause_menu.input(Input::Key(key), None, &mut settings) {
+                            MenuResult::Resume => game_paused = false,
+                            MenuResult::SettingsChanged => {
+                                settings.save();
+                                camera.apply_settings(&settings);
+                                crosshair.apply_settings(&settings.crosshair);
+                                if let Some(selection) = &mut selection {
+                                    selection.apply_settings(&settings.selection);
+                                }
+                            }
+                            MenuResult::None => {}
+                        }
+                    }
+
+                    window.set_cursor_visible(game_paused || form.is_some());
+                    update_cursor_mode(game_paused || form.is_some());
+                    camera.input = Default::default();
+                } else if let Some(open) = &mut form {
+                    // start and the inventory button close forms
+                    if pressed
+                        && (button == Button::Start
+                            || gamepad.state.action(button) == Some(Action::Inventory))
+                    {
+                        let events = vec![open.close()];
+                        handle_form_events(events, &mut form, &mut inventory, &items, &net_events);
+                        window.set_cursor_visible(false);
+                        update_cursor_mode(false);
+                    }
+                } else if let Some(action) = gamepad.state.action(button) {
+                    pending_actions.extend(handle_action(
+                        action,
+                        pressed,
+                        &mut camera,
+                        &mut player,
+                        &mut debug_menu,
+                    ));
+                }
+            }
+
+            if !game_paused && form.is_none() {
+                let movement = gamepad.state.movement();
+                camera.input.move_x = movement.x;
+                camera.input.move_y = movement.y;
+
+                let look = gamepad.state.look(dt.as_secs_f32());
+                camera.input.look_x += look.x;
+                camera.input.look_y += look.y;
+            }
+
+            camera.rotate(dt.as_secs_f32());
+            if let Some(map) = &map {
+                player.update(&map.view(), &camera, dt.as_secs_f32());
+            }
+            let right = camera.move_dirs(false).1;
+            let eye = bobbing.update(&mut player, &camera.input, right, dt.as_secs_f32());
+            match &map {
+                Some(map) => camera.follow(eye, &map.view()),
+                None => camera.pos = eye,
+            }
+            camera.update(&gpu, dt.as_secs_f32());
+            if let Some(model) = &player_model {
+                model.update(&gpu, player.visual_pos(), camera.move_dirs(false).0);
+            }
+            if let Some(map) = &map {
+                // the third person camera looks past the player, so point from the eyes
+                let origin = match camera.mode {
+                    camera::CameraMode::FirstPerson => camera.pos,
+                    _ => eye,
+                };
+                pointed =
+                    raycast::raycast(&map.view(), origin, camera.look_dir(), raycast::HAND_RANGE);
+            }
+            let mut interactions = Vec::new();
+            let mut node_form = None;
+            if let Some(map) = &mut map {
+                interactions = interact.update(
+                    &map.view(),
+                    &camera.input,
+                    pointed.as_ref(),
+                    dt.as_secs_f32(),
+                );
+
+                // nodes with a formspec in their meta show it instead of being placed against,
+                // the server is only told if the node has on_rightclick
+                node_form = interactions.iter().find_map(|interaction| {
+                    let (pos, formspec) =
+                        node_formspec(&node_metas, camera.input.sneak, interaction)?;
+                    let rightclickable = map
+                        .view()
+                        .node(pos.cast()?)
+                        .map_or(false, |def| def.rightclickable);
+                    Some((pos, formspec.to_string(), rightclickable))
+                });
+                if let Some((_, _, rightclickable)) = node_form {
+                    interactions.retain(|interaction| {
+                        rightclickable || !matches!(interaction, interact::Interaction::Place(_))
+                    });
+                }
+
+                for interaction in &interactions {
+                    predict_interaction(
+                        map,
+                        &player,
+                        &interact.place_prediction,
+                        camera.input.sneak,
+                        interaction,
+                    );
+                }
+            }
+            if let Some((pos, formspec, _)) = node_form {
+                let mut shown = Form::new(
+                    String::new(),
+                    InvLocation::NodeMeta(pos),
+                    &formspec,
+                    &formspec_prepend,
+                    ui.screen_size(),
+                    settings.hud_scale,
+                );
+                shown.shift = shift;
+                form = Some(shown);
+
+                camera.input = Default::default();
+                window.set_cursor_visible(true);
+                update_cursor_mode(true);
+            }
+            if let Some(selection) = &mut selection {
+                selection.update(&gpu, pointed.as_ref(), interact.dig_progress());
+            }
+            if let Some(wield) = &mut wield {
+                let placed = interactions
+                    .iter()
+                    .any(|i| matches!(i, interact::Interaction::Place(_)));
+                if camera.input.dig || placed {
+                    wield.swing();
+                }
+                wield.update(&gpu, dt.as_secs_f32());
+            }
+            if let Some(map) = &map {
+                let color = player::node_at(&map.view(), camera.pos)
+                    .map_or([0.0; 4], |def| util::color_to_rgba(def.post_effect_color));
+                tint.set_color(&gpu, color);
+            }
             if let Some(map) = &mut map {
                 map.update(&gpu);
             }
+            if let Some(sky) = &mut sky {
+                sky.update(&gpu, &camera, dt);
+
+                if let Some(map) = &mut map {
+                    map.update_shadows(&gpu, &camera, sky.light_dir());
+                }
+            }
+
+            for action in pending_actions.drain(..) {
+                let selected = match action {
+                    Action::Drop => {
+                        // like upstream, sneaking drops the whole stack
+                        let action = InvAction::Drop {
+                            count: if camera.input.sneak { 0 } else { 1 },
+                            from: InvSlot {
+                                inv: InvLocation::CurrentPlayer,
+                                list: "main".into(),
+                                index: hud.hotbar.selected,
+                            },
+                        };
+                        net_events.send(NetEvent::InvAction(action)).ok();
+                        false
+                    }
+                    Action::Inventory if !inventory_formspec.is_empty() => {
+                        let mut inv = Form::new(
+                            String::new(),
+                            InvLocation::CurrentPlayer,
+                            &inventory_formspec,
+                            &formspec_prepend,
+                            ui.screen_size(),
+                            settings.hud_scale,
+                        );
+                        inv.shift = shift;
+                        form = Some(inv);
+
+                        camera.input = Default::default();
+                        window.set_cursor_visible(true);
+                        update_cursor_mode(true);
+                        false
+                    }
+                    Action::HotbarNext => hud.hotbar.scroll(1, &inventory),
+                    Action::HotbarPrev => hud.hotbar.scroll(-1, &inventory),
+                    action => action
+                        .hotbar_slot()
+                        .map_or(false, |slot| hud.hotbar.select(slot, &inventory)),
+                };
+
+                if selected {
+                    net_events
+                        .send(NetEvent::PlayerItem(hud.hotbar.selected))
+                        .ok();
+                }
+            }
+
+            let name = wielded_name(&inventory, hud.hotbar.selected);
+            if name != wielded {
+                wielded = name;
+
+                if let (Some(map), Some(wield)) = (&map, &mut wield) {
+                    wield_item(&wielded, &gpu, &media, &items, map, wield, &mut interact);
+                }
+            }
 
             net_events
-                .send(NetEvent::PlayerPos(camera.pos, camera.rot.y, camera.rot.z))
+                .send(NetEvent::PlayerPos(PlayerState {
+                    pos: player.pos,
+                    vel: player.vel,
+                    yaw: camera.rot.y,
+                    pitch: camera.rot.z,
+                    keys: camera.input.keys(),
+                    fov: camera.fov,
+                }))
                 .ok();
 
+            for interaction in interactions {
+                let (action, pointed) = interaction.to_packet();
+                net_events.send(NetEvent::Interact(action, pointed)).ok();
+            }
+
             let mut render = || {
                 let size = (gpu.config.width as f32, gpu.config.height as f32);
                 let mut frame = gpu::Frame::new(&mut gpu)?;
 
+                if let Some(map) = &map {
+                    map.render_shadows(&mut frame.encoder);
+                }
+
                 {
                     let mut pass = frame.pass();
+                    if let Some(sky) = &sky {
+                        sky.render(&mut pass);
+                    }
                     if let Some(map) = &mut map {
                         map.render(&camera, &mut debug_menu, &mut pass);
                     }
+                    if let Some(model) = &player_model {
+                        if camera.mode != camera::CameraMode::FirstPerson {
+                            model.render(&camera, &mut pass);
+                        }
+                    }
+                    if let Some(selection) = &selection {
+                        selection.render(&camera, &mut pass);
+                    }
+                    if let Some(sky) = &sky {
+                        sky.render_clouds(&camera, &mut pass);
+                    }
+                    if let Some(wield) = &wield {
+                        if camera.mode == camera::CameraMode::FirstPerson
+                            && hud.shows(mt_net::HudFlag::WieldedItem)
+                        {
+                            wield.render(&mut pass);
+                        }
+                    }
+                    tint.render(&mut pass);
                 }
 
+                frame.post_process();
+
+                if let Some(map) = &map {
+                    let ctx = hud::HudContext {
+                        camera: &camera,
+                        items: &items,
+                        map,
+                        inventory: &inventory,
+                        scale: settings.hud_scale,
+                    };
+                    hud.render(&ctx, &mut ui, &mut font);
+
+                    // like upstream, there's nothing to aim at when looking at the player
+                    if hud.shows(mt_net::HudFlag::Crosshair)
+                        && camera.mode != camera::CameraMode::ThirdPersonFront
+                    {
+                        crosshair.render(&mut ui, settings.hud_scale);
+                    }
+                    if hud.shows(mt_net::HudFlag::Basic) {
+                        let info = pointed
+                            .as_ref()
+                            .and_then(|pointed| pointed_info(map, &items, pointed));
+                        if let Some(info) = info {
+                            crosshair.render_info(&mut ui, settings.hud_scale, &info);
+                        }
+                    }
+
+                    if let Some(form) = &form {
+                        let ctx = FormContext {
+                            items: &items,
+                            map,
+                            inventory: &inventory,
+                            detached_invs: &detached_invs,
+                            node_metas: &node_metas,
+                        };
+                        form.render(&ctx, &mut ui, &mut font);
+                    }
+                }
+                ui.submit(&mut frame, &media, &mut font);
+
                 debug_menu.render(size, &camera, &mut font);
+                if game_paused {
+                    pause_menu.render(size, &settings, &mut font);
+                }
                 font.submit(&mut frame);
 
                 frame.finish();
@@ -112,46 +705,185 @@ pub async fn run(
             } => {
                 gpu.resize(size);
                 camera.resize(size);
+                ui.resize(size);
+                if let Some(form) = &mut form {
+                    form.resize((size.width as f32, size.height as f32));
+                }
+            }
+            ModifiersChanged(modifiers) => {
+                shift = modifiers.shift();
+                pause_menu.shift = shift;
+                if let Some(form) = &mut form {
+                    form.shift = shift;
+                }
+            }
+            ReceivedCharacter(c) => {
+                if let (false, Some(form)) = (game_paused, &mut form) {
+                    form.character(c);
+                }
+            }
+            CursorMoved { position, .. } => {
+                if let Some(form) = &mut form {
+                    form.cursor_moved(position.x as f32, position.y as f32);
+                }
             }
             KeyboardInput {
                 input:
                     winit::event::KeyboardInput {
-                        virtual_keycode: Some(key),
-                        state: key_state,
+                        virtual_keycode: key,
+                        scancode,
+                        state,
                         ..
                     },
                 ..
             } => {
-                use winit::event::{ElementState, VirtualKeyCode as Key};
+                let pressed = state == ElementState::Pressed;
 
-                if key == Key::Escape && key_state == ElementState::Pressed {
-                    game_paused = !game_paused;
-                    window.set_cursor_visible(game_paused);
-                    update_cursor_mode(game_paused);
+                // open forms take all keys, the inventory key also closes the inventory
+                if let (false, Some(open)) = (game_paused, &mut form) {
+                    if pressed {
+                        let events = match key {
+                            _ if !open.typing()
+                                && settings.keys.key_action(key, scancode)
+                                    == Some(Action::Inventory) =>
+                            {
+                                vec![open.close()]
+                            }
+                            Some(key) => open.key(key),
+                            None => Vec::new(),
+                        };
 
-                    if game_paused {
-                        camera.input = Default::default();
+                        handle_form_events(events, &mut form, &mut inventory, &items, &net_events);
+                        if form.is_none() {
+                            window.set_cursor_visible(false);
+                            update_cursor_mode(false);
+                        }
                     }
+
+                    return;
                 }
 
-                if game_paused {
+                if game_paused || key == Some(VirtualKeyCode::Escape) {
+                    if pressed {
+                        if !game_paused {
+                            game_paused = true;
+                        } else {
+                            let input = input::key_input(key, scancode);
+                            match pause_menu.input(input, Some(scancode), &mut settings) {
+                                MenuResult::Resume => game_paused = false,
+                                MenuResult::SettingsChanged => {
+                                    settings.save();
+                                    camera.apply_settings(&settings);
+                                    crosshair.apply_settings(&settings.crosshair);
+                                    if let Some(selection) = &mut selection {
+                                        selection.apply_settings(&settings.selection);
+                                    }
+                                }
+                                MenuResult::None => {}
+                            }
+                        }
+
+                        window.set_cursor_visible(game_paused || form.is_some());
+                        update_cursor_mode(game_paused || form.is_some());
+                        camera.input = Default::default();
+                    }
+
                     return;
                 }
 
-                if key == Key::F3 && key_state == ElementState::Pressed {
-                    debug_menu.enabled = !debug_menu.enabled;
+                if let Some(action) = settings.keys.key_action(key, scancode) {
+                    pending_actions.extend(handle_action(
+                        action,
+                        pressed,
+                        &mut camera,
+                        &mut player,
+                        &mut debug_menu,
+                    ));
                 }
+            }
+            MouseInput { state, button, .. } => {
+                let input = Input::Mouse(button);
+                let pressed = state == ElementState::Pressed;
 
-                if !game_paused {
-                    *(match key {
-                        Key::W => &mut camera.input.forward,
-                        Key::A => &mut camera.input.left,
-                        Key::S => &mut camera.input.backward,
-                        Key::D => &mut camera.input.right,
-                        Key::Space => &mut camera.input.jump,
-                        Key::LShift => &mut camera.input.sneak,
-                        _ => return,
-                    }) = key_state == ElementState::Pressed;
+                if game_paused {
+                    if pressed
+                        && pause_menu.input(input, None, &mut settings)
+                            == MenuResult::SettingsChanged
+                    {
+                        settings.save();
+                        camera.apply_settings(&settings);
+                        crosshair.apply_settings(&settings.crosshair);
+                        if let Some(selection) = &mut selection {
+                            selection.apply_settings(&settings.selection);
+                        }
+                    }
+                } else if let Some(open) = &mut form {
+                    if let Some(map) = &map {
+                        let ctx = FormContext {
+                            items: &items,
+                            map,
+                            inventory: &inventory,
+                            detached_invs: &detached_invs,
+                            node_metas: &node_metas,
+                        };
+                        let events = open.mouse_input(button, pressed, &ctx);
+
+                        handle_form_events(events, &mut form, &mut inventory, &items, &net_events);
+                        if form.is_none() {
+                            window.set_cursor_visible(false);
+                            update_cursor_mode(false);
+                        }
+                    }
+                } else if let Some(action) = settings.keys.action(input) {
+                    pending_actions.extend(handle_action(
+                        action,
+                        pressed,
+                        &mut camera,
+                        &mut player,
+                        &mut debug_menu,
+                    ));
+                }
+            }
+            MouseWheel { delta, .. } => {
+                let y = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(pos) => pos.y as f32,
+                };
+
+                let input = match y {
+                    y if y > 0.0 => Input::ScrollUp,
+                    y if y < 0.0 => Input::ScrollDown,
+                    _ => return,
+                };
+
+                if game_paused {
+                    if pause_menu.input(input, None, &mut settings) == MenuResult::SettingsChanged {
+                        settings.save();
+                        camera.apply_settings(&settings);
+                        crosshair.apply_settings(&settings.crosshair);
+                        if let Some(selection) = &mut selection {
+                            selection.apply_settings(&settings.selection);
+                        }
+                    }
+                } else if let Some(open) = &mut form {
+                    let events = open.scroll(if input == Input::ScrollUp { 1 } else { -1 });
+                    handle_form_events(events, &mut form, &mut inventory, &items, &net_events);
+                } else if let Some(action) = settings.keys.action(input) {
+                    // scrolling has no release, so it only triggers actions
+                    pending_actions.extend(handle_action(
+                        action,
+                        true,
+                        &mut camera,
+                        &mut player,
+                        &mut debug_menu,
+                    ));
+                    pending_actions.extend(handle_action(
+                        action,
+                        false,
+                        &mut camera,
+                        &mut player,
+                        &mut debug_menu,
+                    ));
                 }
             }
             _ => {}
@@ -160,9 +892,15 @@ pub async fn run(
             event: MouseMotion { delta },
             ..
         } => {
-            if !game_paused {
-                camera.input.mouse_x += delta.0 as f32;
-                camera.input.mouse_y += delta.1 as f32;
+            if !game_paused && form.is_none() {
+                let scale = if camera.mouse.dpi_scaling {
+                    window.scale_factor()
+                } else {
+                    1.0
+                };
+
+                camera.input.mouse_x += (delta.0 / scale) as f32;
+                camera.input.mouse_y += (delta.1 / scale) as f32;
 
                 window
                     .set_cursor_position(winit::dpi::PhysicalPosition::new(
@@ -175,32 +913,199 @@ pub async fn run(
         UserEvent(event) => match event {
             Close => *flow = ExitWithCode(0),
             NodeDefs(defs) => nodedefs = Some(defs),
+            Inventory(inv) => inventory = inv,
+            DetachedInventory(name, inv) => match inv {
+                Some(inv) => {
+                    detached_invs.insert(name, inv);
+                }
+                None => {
+                    detached_invs.remove(&name);
+                }
+            },
+            HudAdd(id, elem) => hud.add(id, *elem),
+            HudChange(id, change) => hud.change(id, change),
+            HudRemove(id) => hud.remove(id),
+            HudFlags(flags, mask) => hud.set_flags(flags, mask),
+            HudParam(param) => hud.set_param(param),
+            ShowFormspec(name, spec) => {
+                if !spec.is_empty() {
+                    let mut shown = Form::new(
+                        name,
+                        InvLocation::CurrentPlayer,
+                        &spec,
+                        &formspec_prepend,
+                        ui.screen_size(),
+                        settings.hud_scale,
+                    );
+                    shown.shift = shift;
+                    form = Some(shown);
+                    camera.input = Default::default();
+                } else if form.as_ref().map_or(false, |form| form.name == name) {
+                    // an empty formspec closes the form of that name
+                    form = None;
+                }
+
+                window.set_cursor_visible(game_paused || form.is_some());
+                update_cursor_mode(game_paused || form.is_some());
+            }
+            InventoryFormspec(spec) => {
+                let inventory_form = |form: &&mut Form| {
+                    form.name.is_empty() && form.context == InvLocation::CurrentPlayer
+                };
+                if let Some(form) = form.as_mut().filter(inventory_form) {
+                    form.update(&spec, &formspec_prepend, ui.screen_size());
+                }
+                inventory_formspec = spec;
+            }
+            FormspecPrepend(prepend) => formspec_prepend = prepend,
+            Hp(hp) => hud.hp = hp,
+            Breath(breath) => hud.breath = breath,
+            ItemDefs(defs, aliases) => {
+                items = items::ItemRegistry::new(defs, aliases);
+
+                if let (Some(map), Some(wield)) = (&map, &mut wield) {
+                    wield_item(&wielded, &gpu, &media, &items, map, wield, &mut interact);
+                }
+            }
             MapBlock(pos, blk) => {
                 if let Some(map) = &mut map {
                     map.add_block(pos, blk);
                 }
             }
+            BlockMetas(blkpos, metas) => {
+                // the block's metas replace all old ones inside it
+                node_metas
+                    .retain(|pos: &cgmath::Point3<i16>, _| pos.map(|c| c.div_euclid(16)) != blkpos);
+                node_metas.extend(metas);
+
+                if refresh_node_form(&mut form, &node_metas, &formspec_prepend, ui.screen_size()) {
+                    window.set_cursor_visible(game_paused);
+                    update_cursor_mode(game_paused);
+                }
+            }
+            NodeMetas(metas) => {
+                node_metas.extend(metas);
+
+                if refresh_node_form(&mut form, &node_metas, &formspec_prepend, ui.screen_size()) {
+                    window.set_cursor_visible(game_paused);
+                    update_cursor_mode(game_paused);
+                }
+            }
             Media(files, finished) => {
                 media.add_server_media(files);
 
                 if finished {
+                    let mut new_sky = sky::Sky::new(&gpu, &camera, &media);
+                    new_sky.apply(&gpu, &media, &sky_state);
+                    sky = Some(new_sky);
+                    player_model = Some(player_model::PlayerModel::new(&gpu, &camera, &media));
+                    crosshair.set_media(&media);
+                    selection = Some(selection::Selection::new(
+                        &gpu,
+                        &camera,
+                        &media,
+                        &settings.selection,
+                    ));
                     map = Some(map::MapRender::new(
                         &mut gpu,
                         &camera,
                         &media,
                         nodedefs.take().unwrap_or_default(),
+                        &settings.map,
                     ));
+                    if let Some(map) = &mut map {
+                        map.set_shadow_intensity(shadow_intensity);
+                    }
+
+                    if let Some(map) = &map {
+                        let mut item = wield::WieldItem::new(&gpu, &media);
+                        wield_item(
+                            &wielded,
+                            &gpu,
+                            &media,
+                            &items,
+                            map,
+                            &mut item,
+                            &mut interact,
+                        );
+                        wield = Some(item);
+                    }
 
                     net_events.send(NetEvent::Ready).ok();
                 }
             }
             PlayerPos(pos, pitch, yaw) => {
-                camera.pos = pos;
-                camera.rot.y = yaw;
-                camera.rot.z = pitch;
+                player.set_server_pos(pos);
+                camera.set_rotation(yaw, pitch);
+            }
+            Movement(movement) => {
+                player.physics.movement = movement;
+            }
+            Physics(overrides) => {
+                player.physics.overrides = overrides;
+            }
+            Fov {
+                fov,
+                multiplier,
+                transition_time,
+            } => camera.set_server_fov(fov, multiplier, transition_time),
+            ZoomFov(fov) => camera.zoom_fov = cgmath::Deg(fov),
+            EyeOffset(first, third) => {
+                // eye offsets are given in tenths of a node
+                camera.eye_offset_first = first / 10.0;
+                camera.eye_offset_third = third / 10.0;
+            }
+            Privs(privs) => {
+                player.set_privs(privs);
+            }
+            SkyParams(params) => {
+                if let Some(sky) = &mut sky {
+                    sky.set_sky(&gpu, &media, &params);
+                }
+                sky_state.sky = Some(params);
+            }
+            SunParams(params) => {
+                if let Some(sky) = &mut sky {
+                    sky.set_sun(&gpu, &media, &params);
+                }
+                sky_state.sun = Some(params);
             }
-            MovementSpeed(speed) => {
-                camera.speed = speed;
+            MoonParams(params) => {
+                if let Some(sky) = &mut sky {
+                    sky.set_moon(&gpu, &media, &params);
+                }
+                sky_state.moon = Some(params);
+            }
+            StarParams(params) => {
+                if let Some(sky) = &mut sky {
+                    sky.set_stars(&params);
+                }
+                sky_state.stars = Some(params);
+            }
+            CloudParams(params) => {
+                if let Some(sky) = &mut sky {
+                    sky.set_clouds(&params);
+                }
+                sky_state.clouds = Some(params);
+            }
+            TimeOfDay(time, speed) => {
+                if let Some(sky) = &mut sky {
+                    sky.set_time(time, speed);
+                }
+                sky_state.time = Some((time, speed));
+            }
+            Lighting {
+                shadow_intensity: intensity,
+                exposure,
+                saturation,
+            } => {
+                if let Some(map) = &mut map {
+                    map.set_shadow_intensity(intensity);
+                }
+                shadow_intensity = intensity;
+                if let Some(post) = &mut gpu.post {
+                    post.set_lighting(exposure, saturation);
+                }
             }
         },
         _ => {}
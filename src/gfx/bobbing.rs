@@ -0,0 +1,112 @@
+use super::{
+    camera::CameraInput,
+    player::{LocalPlayer, EYE_HEIGHT},
+};
+use cgmath::{prelude::*, Point3, Vector3};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+// distance covered by a single step, one bob up and down
+const STEP_LENGTH: f32 = 1.2;
+const BOB_HEIGHT: f32 = 0.05;
+const BOB_SWAY: f32 = 0.03;
+// how fast bobbing fades in and out, per second
+const BOB_FADE: f32 = 5.0;
+const SNEAK_LOWERING: f32 = 0.15;
+// rate at which the eye height approaches its target, per second
+const EYE_HEIGHT_RATE: f32 = 10.0;
+const FALL_DIP_TIME: f32 = 0.4;
+const MAX_FALL_DIP: f32 = 0.5;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct BobbingSettings {
+    /// strength of the view bobbing while walking, 0 disables it
+    pub view_bobbing: f32,
+    /// camera dip per node per second of landing speed, 0 disables it
+    pub fall_bobbing: f32,
+}
+
+impl Default for BobbingSettings {
+    fn default() -> Self {
+        Self {
+            view_bobbing: 1.0,
+            fall_bobbing: 0.03,
+        }
+    }
+}
+
+/// purely visual camera movement on top of the player's position
+pub struct ViewBobbing {
+    pub settings: BobbingSettings,
+    phase: f32,
+    amount: f32,
+    eye_height: f32,
+    fall_dip: f32,
+    fall_time: f32,
+}
+
+impl ViewBobbing {
+    pub fn new(settings: &BobbingSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+            phase: 0.0,
+            amount: 0.0,
+            eye_height: EYE_HEIGHT,
+            fall_dip: 0.0,
+            fall_time: 0.0,
+        }
+    }
+
+    /// the camera's eye position for this frame, right is the horizontal right direction
+    pub fn update(
+        &mut self,
+        player: &mut LocalPlayer,
+        input: &CameraInput,
+        right: Vector3<f32>,
+        dt: f32,
+    ) -> Point3<f32> {
+        let walk_speed = player.physics.movement.walk_speed;
+        let speed = Vector3::new(player.vel.x, 0.0, player.vel.z).magnitude();
+        let walking = player.touching_ground && !player.fly;
+
+        self.phase = (self.phase + speed * dt / STEP_LENGTH * PI) % (2.0 * PI);
+
+        let target = if walking {
+            (speed / walk_speed.max(0.1)).min(1.0)
+        } else {
+            0.0
+        };
+        self.amount += (target - self.amount).clamp(-BOB_FADE * dt, BOB_FADE * dt);
+
+        let target_height = if walking && input.sneak {
+            EYE_HEIGHT - SNEAK_LOWERING
+        } else {
+            EYE_HEIGHT
+        };
+        self.eye_height +=
+            (target_height - self.eye_height) * (1.0 - (-EYE_HEIGHT_RATE * dt).exp());
+
+        if let Some(speed) = player.take_landing() {
+            let dip = (speed * self.settings.fall_bobbing).min(MAX_FALL_DIP);
+
+            if dip > 0.0 {
+                self.fall_dip = dip;
+                self.fall_time = FALL_DIP_TIME;
+            }
+        }
+
+        self.fall_time = (self.fall_time - dt).max(0.0);
+        let fall = if self.fall_time > 0.0 {
+            self.fall_dip * (self.fall_time / FALL_DIP_TIME * PI).sin()
+        } else {
+            0.0
+        };
+
+        let bob = self.amount * self.settings.view_bobbing;
+        let offset = Vector3::unit_y() * (self.phase.sin().abs() * BOB_HEIGHT * bob - fall)
+            + right * (self.phase.cos() * BOB_SWAY * bob);
+
+        player.visual_pos() + Vector3::unit_y() * self.eye_height + offset
+    }
+}
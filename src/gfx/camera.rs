@@ -1,7 +1,80 @@
-use super::{gpu::Gpu, util::MatrixUniform};
+use super::{
+    gpu::Gpu,
+    player::{node_at, NodeMap},
+    settings::Settings,
+    util::MatrixUniform,
+};
 use cgmath::{prelude::*, Deg, Euler, Matrix3, Matrix4, Point3, Rad, Vector3};
 use collision::Frustum;
-use std::time::Duration;
+use mt_net::{enumset::EnumSet, Key};
+use serde::{Deserialize, Serialize};
+
+// distance of the third person camera to the player's eyes
+const THIRD_PERSON_DISTANCE: f32 = 2.0;
+// minimum distance kept between the third person camera and walls
+const CAMERA_MARGIN: f32 = 0.2;
+// duration of FOV changes not caused by the server, in seconds
+const FOV_TRANSITION: f32 = 0.15;
+
+/// FOV override sent by the server in the Fov packet
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ServerFov {
+    fov: f32,
+    multiplier: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FovTransition {
+    from: Deg<f32>,
+    to: Deg<f32>,
+    elapsed: f32,
+    duration: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CameraMode {
+    #[default]
+    FirstPerson,
+    ThirdPersonBack,
+    ThirdPersonFront,
+}
+
+impl CameraMode {
+    pub fn next(self) -> Self {
+        match self {
+            CameraMode::FirstPerson => CameraMode::ThirdPersonBack,
+            CameraMode::ThirdPersonBack => CameraMode::ThirdPersonFront,
+            CameraMode::ThirdPersonFront => CameraMode::FirstPerson,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MouseSettings {
+    /// camera rotation in degrees per pixel of mouse movement
+    pub sensitivity: f32,
+    pub invert_y: bool,
+    /// measure mouse movement in logical instead of physical pixels,
+    /// so sensitivity doesn't depend on the display's DPI
+    pub dpi_scaling: bool,
+    /// from 0 (off) to 1, the fraction of a rotation that is left after 1/60 second
+    pub smoothing: f32,
+    /// smoothing used in cinematic mode instead
+    pub cinematic_smoothing: f32,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self {
+            sensitivity: 0.05,
+            invert_y: false,
+            dpi_scaling: true,
+            smoothing: 0.0,
+            cinematic_smoothing: 0.7,
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct CameraInput {
@@ -11,14 +84,63 @@ pub struct CameraInput {
     pub right: bool,
     pub jump: bool,
     pub sneak: bool,
+    pub aux1: bool,
+    pub dig: bool,
+    pub place: bool,
+    pub zoom: bool,
     pub mouse_x: f32,
     pub mouse_y: f32,
+    /// analog movement, x to the right and y forward, added to the movement keys
+    pub move_x: f32,
+    pub move_y: f32,
+    /// analog camera rotation in degrees, in the same directions as mouse movement
+    pub look_x: f32,
+    pub look_y: f32,
+}
+
+impl CameraInput {
+    /// pressed keys as reported to the server
+    pub fn keys(&self) -> EnumSet<Key> {
+        [
+            (self.forward, Key::Forward),
+            (self.backward, Key::Backward),
+            (self.left, Key::Left),
+            (self.right, Key::Right),
+            (self.jump, Key::Jump),
+            (self.aux1, Key::Special),
+            (self.sneak, Key::Sneak),
+            (self.dig, Key::Dig),
+            (self.place, Key::Place),
+            (self.zoom, Key::Zoom),
+        ]
+        .into_iter()
+        .filter(|(pressed, _)| *pressed)
+        .map(|(_, key)| key)
+        .collect()
+    }
 }
 
 pub struct Camera {
     pub pos: Point3<f32>,
     pub rot: Euler<Deg<f32>>,
-    pub speed: f32,
+    /// rotation rot approaches when smoothing is enabled
+    target_rot: Euler<Deg<f32>>,
+    pub mouse: MouseSettings,
+    pub cinematic: bool,
+    pub mode: CameraMode,
+    /// offsets from the player's eyes set by the server, x to the right and z forward
+    pub eye_offset_first: Vector3<f32>,
+    pub eye_offset_third: Vector3<f32>,
+    /// FOV set by the user
+    pub base_fov: Deg<f32>,
+    /// FOV while zooming, set by the server, 0 disables zooming
+    pub zoom_fov: Deg<f32>,
+    server_fov: Option<ServerFov>,
+    /// duration of the next FOV transition, set by the server
+    next_transition: Option<f32>,
+    transition: FovTransition,
+    aspect: f32,
+    /// current FOV, including zoom and transitions
     pub fov: Rad<f32>,
     pub view: Matrix4<f32>,
     pub proj: Matrix4<f32>,
@@ -44,19 +166,36 @@ where
 }
 
 impl Camera {
-    pub fn new(gpu: &Gpu) -> Self {
+    pub fn new(gpu: &Gpu, settings: &Settings) -> Self {
         let layout = MatrixUniform::layout(&gpu.device, "camera");
         let uniform = MatrixUniform::new(&gpu.device, &layout, Matrix4::identity(), "camera", true);
+        let rot = Euler {
+            x: Deg(0.0),
+            y: Deg(0.0),
+            z: Deg(0.0),
+        };
 
         Self {
             pos: Point3::new(0.0, 0.0, 0.0),
-            rot: Euler {
-                x: Deg(0.0),
-                y: Deg(0.0),
-                z: Deg(0.0),
+            rot,
+            target_rot: rot,
+            mouse: settings.mouse.clone(),
+            cinematic: false,
+            mode: CameraMode::FirstPerson,
+            eye_offset_first: Vector3::zero(),
+            eye_offset_third: Vector3::zero(),
+            base_fov: Deg(settings.fov),
+            zoom_fov: Deg(0.0),
+            server_fov: None,
+            next_transition: None,
+            transition: FovTransition {
+                from: Deg(settings.fov),
+                to: Deg(settings.fov),
+                elapsed: 0.0,
+                duration: 0.0,
             },
-            speed: 0.0,
-            fov: Deg(90.0).into(),
+            aspect: 1.0,
+            fov: Deg(settings.fov).into(),
             proj: Matrix4::identity(),
             view: Matrix4::identity(),
             frustum: Frustum::from_matrix4(Matrix4::identity()).unwrap(),
@@ -66,77 +205,180 @@ impl Camera {
         }
     }
 
-    pub fn update(&mut self, gpu: &Gpu, dt: Duration) {
-        let sensitivity = Deg(0.05);
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.mouse = settings.mouse.clone();
+        self.base_fov = Deg(settings.fov);
+    }
+
+    /// handles the server's Fov packet, fov 0 resets to the user's FOV
+    pub fn set_server_fov(&mut self, fov: f32, multiplier: bool, transition_time: f32) {
+        let old = self.target_fov();
+        self.server_fov = (fov > 0.0).then_some(ServerFov { fov, multiplier });
+
+        // only applies to this change, not to a later one like zooming
+        self.next_transition = (self.target_fov() != old).then_some(transition_time);
+    }
+
+    fn target_fov(&self) -> Deg<f32> {
+        if self.input.zoom && self.zoom_fov.0 > 0.0 {
+            return self.zoom_fov;
+        }
 
-        self.rot.y += sensitivity * self.input.mouse_x;
-        self.rot.z += sensitivity * self.input.mouse_y;
-        self.rot.z.0 = self.rot.z.0.min(89.9).max(-89.9);
+        let fov = match self.server_fov {
+            Some(ServerFov {
+                fov,
+                multiplier: true,
+            }) => self.base_fov.0 * fov,
+            Some(ServerFov { fov, .. }) => fov,
+            None => self.base_fov.0,
+        };
+
+        Deg(fov.clamp(1.0, 179.0))
+    }
+
+    fn update_fov(&mut self, dt: f32) {
+        let target = self.target_fov();
+
+        if target != self.transition.to {
+            self.transition = FovTransition {
+                from: self.fov.into(),
+                to: target,
+                elapsed: 0.0,
+                duration: self.next_transition.take().unwrap_or(FOV_TRANSITION),
+            };
+        }
+
+        let tr = &mut self.transition;
+        tr.elapsed += dt;
+
+        let t = if tr.duration > 0.0 {
+            (tr.elapsed / tr.duration).min(1.0)
+        } else {
+            1.0
+        };
+        let fov: Rad<f32> = (tr.from + (tr.to - tr.from) * t).into();
+
+        if fov != self.fov {
+            self.fov = fov;
+            self.update_projection();
+        }
+    }
+
+    fn update_projection(&mut self) {
+        self.proj = cgmath::perspective(self.fov, self.aspect, 0.1, 100000.0);
+        self.frustum = Frustum::from_matrix4(self.proj).unwrap();
+    }
+
+    /// sets the rotation immediately, skipping smoothing
+    pub fn set_rotation(&mut self, yaw: Deg<f32>, pitch: Deg<f32>) {
+        self.rot.y = yaw;
+        self.rot.z = pitch;
+        self.target_rot = self.rot;
+    }
+
+    /// applies mouse and gamepad input to the rotation
+    pub fn rotate(&mut self, dt: f32) {
+        let mouse = &self.mouse;
+        let invert = if mouse.invert_y { -1.0 } else { 1.0 };
+
+        self.target_rot.y += Deg(mouse.sensitivity * self.input.mouse_x + self.input.look_x);
+        self.target_rot.z +=
+            Deg(invert * (mouse.sensitivity * self.input.mouse_y + self.input.look_y));
+        self.target_rot.z.0 = self.target_rot.z.0.min(89.9).max(-89.9);
+
+        let smoothing = if self.cinematic {
+            mouse.cinematic_smoothing
+        } else {
+            mouse.smoothing
+        }
+        .clamp(0.0, 0.99);
+
+        let t = 1.0 - smoothing.powf(dt * 60.0);
+        self.rot.y += (self.target_rot.y - self.rot.y) * t;
+        self.rot.z += (self.target_rot.z - self.rot.z) * t;
 
         self.input.mouse_x = 0.0;
         self.input.mouse_y = 0.0;
+        self.input.look_x = 0.0;
+        self.input.look_y = 0.0;
+    }
 
-        let rot = Matrix3::from(self.rot.to_native());
+    /// places the camera relative to the player's eyes depending on the camera mode,
+    /// keeping the third person camera out of walls
+    pub fn follow(&mut self, eye: Point3<f32>, map: &impl NodeMap) {
+        let (forward, right) = self.move_dirs(false);
+        let offset = |o: Vector3<f32>| right * o.x + Vector3::unit_y() * o.y + forward * o.z;
 
-        let forward = rot * Vector3::unit_x();
-        let up = rot * Vector3::unit_y();
+        if self.mode == CameraMode::FirstPerson {
+            self.pos = eye + offset(self.eye_offset_first);
+            return;
+        }
 
-        {
-            let mut forward = forward;
-            let mut up = up;
-            let mut right = forward.cross(up);
+        let eye = eye + offset(self.eye_offset_third);
+        let dir = match self.mode {
+            CameraMode::ThirdPersonBack => -self.look_dir(),
+            _ => self.look_dir(),
+        };
 
-            let pitch_move = false;
+        let blocked = |dist: f32| {
+            node_at(map, eye + dir * (dist + CAMERA_MARGIN)).map_or(false, |def| def.walkable)
+        };
 
-            if !pitch_move {
-                forward.y = 0.0;
-                right.y = 0.0;
-                up = Vector3::unit_y();
+        let mut dist = 0.0;
+        while dist < THIRD_PERSON_DISTANCE {
+            let next = (dist + 0.1).min(THIRD_PERSON_DISTANCE);
+            if blocked(next) {
+                break;
             }
+            dist = next;
+        }
 
-            let mut hdir = Vector3::zero();
-            let mut vdir = Vector3::zero();
+        self.pos = eye + dir * dist;
+    }
 
-            if self.input.forward {
-                hdir += forward;
-            }
-            if self.input.backward {
-                hdir -= forward;
-            }
-            if self.input.right {
-                hdir += right;
-            }
-            if self.input.left {
-                hdir -= right;
-            }
-            if self.input.jump {
-                vdir += up;
-            }
-            if self.input.sneak {
-                vdir -= up;
-            }
+    pub fn update(&mut self, gpu: &Gpu, dt: f32) {
+        self.update_fov(dt);
 
-            self.pos += self.speed
-                * dt.as_secs_f32()
-                * (vdir
-                    + if hdir.is_zero() {
-                        hdir
-                    } else {
-                        hdir.normalize()
-                    });
+        let mut view_rot = self.rot;
+
+        // look back at the player
+        if self.mode == CameraMode::ThirdPersonFront {
+            view_rot.y += Deg(180.0);
+            view_rot.z = -view_rot.z;
         }
 
+        let rot = Matrix3::from(view_rot.to_native());
+
+        let forward = rot * Vector3::unit_x();
+        let up = rot * Vector3::unit_y();
+
         self.view = Matrix4::look_at_dir(self.pos, forward, up);
         self.uniform.set(&gpu.queue, self.proj * self.view);
     }
 
+    /// the direction the player is looking in, regardless of camera mode
+    pub fn look_dir(&self) -> Vector3<f32> {
+        Matrix3::from(self.rot.to_native()) * Vector3::unit_x()
+    }
+
+    /// forward and right vectors used for movement,
+    /// horizontal unless pitch_move is enabled
+    pub fn move_dirs(&self, pitch_move: bool) -> (Vector3<f32>, Vector3<f32>) {
+        let rot = Matrix3::from(self.rot.to_native());
+
+        let mut forward = rot * Vector3::unit_x();
+        let mut right = forward.cross(rot * Vector3::unit_y());
+
+        if !pitch_move {
+            forward.y = 0.0;
+            right.y = 0.0;
+        }
+
+        (forward.normalize(), right.normalize())
+    }
+
     pub fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
-        self.proj = cgmath::perspective(
-            self.fov,
-            size.width as f32 / size.height as f32,
-            0.1,
-            100000.0,
-        );
-        self.frustum = Frustum::from_matrix4(self.proj).unwrap();
+        self.aspect = size.width as f32 / size.height.max(1) as f32;
+        self.update_projection();
     }
 }
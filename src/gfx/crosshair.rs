@@ -0,0 +1,126 @@
+use super::{
+    hud::strip_escapes,
+    items::ItemRegistry,
+    media::MediaMgr,
+    ui::{Rect, Ui},
+};
+use mt_net::NodeDef;
+use serde::{Deserialize, Serialize};
+use wgpu_glyph::{Section, Text};
+
+// length of the default crosshair's lines in pixels at a HUD scale of 1
+const CROSSHAIR_SIZE: f32 = 20.0;
+const INFO_TEXT_SIZE: f32 = 16.0;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct CrosshairSettings {
+    pub enabled: bool,
+    pub color: [u8; 3],
+    pub alpha: u8,
+    /// show the description and name of the pointed node
+    pub info_text: bool,
+}
+
+impl Default for CrosshairSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            color: [255, 255, 255],
+            alpha: 255,
+            info_text: true,
+        }
+    }
+}
+
+/// description of a node followed by its name, like upstream's pointed node info
+pub fn info_text(items: &ItemRegistry, def: &NodeDef) -> String {
+    let description = items
+        .get(&def.name)
+        .map(|item| strip_escapes(&item.description))
+        .unwrap_or_default();
+
+    // only the first line, the rest is usually a longer explanation
+    match description.lines().next() {
+        Some(line) if !line.is_empty() => format!("{line} ({})", def.name),
+        _ => def.name.clone(),
+    }
+}
+
+pub struct Crosshair {
+    settings: CrosshairSettings,
+    /// crosshair.png sent by the server, drawn instead of the default lines
+    texture: Option<String>,
+}
+
+impl Crosshair {
+    pub fn new(settings: &CrosshairSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+            texture: None,
+        }
+    }
+
+    pub fn apply_settings(&mut self, settings: &CrosshairSettings) {
+        self.settings = settings.clone();
+    }
+
+    /// looks for a crosshair texture once media is loaded
+    pub fn set_media(&mut self, media: &MediaMgr) {
+        self.texture = media
+            .get("crosshair.png")
+            .map(|_| "crosshair.png".to_string());
+    }
+
+    pub fn render(&self, ui: &mut Ui, scale: f32) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        let (w, h) = ui.screen_size();
+        let center = ((w / 2.0).floor(), (h / 2.0).floor());
+
+        let [r, g, b] = self.settings.color.map(|c| c as f32 / 255.0);
+        let color = [r, g, b, self.settings.alpha as f32 / 255.0];
+
+        match &self.texture {
+            Some(texture) => {
+                let size = match ui.texture_size(texture) {
+                    Some((w, h)) => (w as f32 * scale, h as f32 * scale),
+                    None => return,
+                };
+
+                let rect = Rect::new(
+                    center.0 - (size.0 / 2.0).floor(),
+                    center.1 - (size.1 / 2.0).floor(),
+                    size.0,
+                    size.1,
+                );
+                ui.image(texture, rect, color);
+            }
+            None => {
+                let len = CROSSHAIR_SIZE * scale;
+                let width = scale.round().max(1.0);
+
+                ui.fill(Rect::new(center.0 - len / 2.0, center.1, len, width), color);
+                ui.fill(Rect::new(center.0, center.1 - len / 2.0, width, len), color);
+            }
+        }
+    }
+
+    /// draws the info text of the pointed node at the same place upstream does
+    pub fn render_info(&self, ui: &mut Ui, scale: f32, text: &str) {
+        if !self.settings.info_text || text.is_empty() {
+            return;
+        }
+
+        ui.text(Section {
+            screen_position: (100.0 * scale, 200.0 * scale),
+            bounds: ui.screen_size(),
+            text: vec![Text::new(text)
+                .with_color([1.0, 1.0, 1.0, 1.0])
+                .with_scale(INFO_TEXT_SIZE * scale)],
+            ..Section::default()
+        });
+    }
+}
@@ -1,4 +1,5 @@
 use super::gpu::{Frame, Gpu};
+use wgpu_glyph::GlyphCruncher;
 
 pub struct Font {
     glyph_brush: wgpu_glyph::GlyphBrush<()>,
@@ -23,18 +24,46 @@ impl Font {
         self.glyph_brush.queue(section);
     }
 
-    pub fn submit(&mut self, frame: &mut Frame) {
+    /// size of a section's text in pixels
+    pub fn text_size(&mut self, section: wgpu_glyph::Section) -> (f32, f32) {
         self.glyph_brush
-            .draw_queued(
-                &frame.gpu.device,
+            .glyph_bounds(section)
+            .map_or((0.0, 0.0), |bounds| (bounds.width(), bounds.height()))
+    }
+
+    /// draws the queued text, only inside the pixel rectangle clip if given
+    pub fn draw(&mut self, frame: &mut Frame, clip: Option<[u32; 4]>) {
+        let gpu = &*frame.gpu;
+        let (width, height) = (gpu.config.width, gpu.config.height);
+
+        match clip {
+            Some([x, y, w, h]) => self.glyph_brush.draw_queued_with_transform_and_scissoring(
+                &gpu.device,
                 &mut self.staging_belt,
                 &mut frame.encoder,
                 &frame.view,
-                frame.gpu.config.width,
-                frame.gpu.config.height,
-            )
-            .unwrap();
+                wgpu_glyph::orthographic_projection(width, height),
+                wgpu_glyph::Region {
+                    x,
+                    y,
+                    width: w,
+                    height: h,
+                },
+            ),
+            None => self.glyph_brush.draw_queued(
+                &gpu.device,
+                &mut self.staging_belt,
+                &mut frame.encoder,
+                &frame.view,
+                width,
+                height,
+            ),
+        }
+        .unwrap();
+    }
 
+    pub fn submit(&mut self, frame: &mut Frame) {
+        self.draw(frame, None);
         self.staging_belt.finish();
     }
 
@@ -0,0 +1,1653 @@
+mod layout;
+mod parse;
+
+use super::{
+    font::Font,
+    hotbar,
+    hud::strip_escapes,
+    items::ItemRegistry,
+    map::MapRender,
+    ui::{Rect, Ui},
+};
+use crate::{
+    inventory::{InvAction, InvLocation, InvSlot, Inventory, ItemStack},
+    net::NodeMeta,
+};
+use cgmath::Point3;
+use layout::{Layout, Widget};
+use parse::{Element, FieldKind, Formspec, ScrollbarOptions, TooltipTarget};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use wgpu_glyph::{HorizontalAlign, Layout as TextLayout, Section, Text, VerticalAlign};
+use winit::event::{MouseButton, VirtualKeyCode as Key};
+
+// forms are drawn above the HUD
+const Z: i32 = 100;
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+const WHITE: [f32; 4] = [1.0; 4];
+const BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 140.0 / 255.0];
+const SLOT: [f32; 4] = [0.5, 0.5, 0.5, 0.5];
+const SLOT_HOVER: [f32; 4] = [0.55, 0.55, 0.55, 0.55];
+const BUTTON: [f32; 4] = [0.25, 0.25, 0.25, 0.9];
+const BUTTON_HOVER: [f32; 4] = [0.35, 0.35, 0.35, 0.9];
+const BUTTON_PRESSED: [f32; 4] = [0.15, 0.15, 0.15, 0.9];
+const FIELD: [f32; 4] = [0.05, 0.05, 0.05, 0.8];
+const SELECTED: [f32; 4] = [0.2, 0.4, 0.8, 0.8];
+
+/// colors of list slots, set by listcolors
+#[derive(Debug, Clone, Copy)]
+struct SlotColors {
+    normal: [f32; 4],
+    hover: [f32; 4],
+    border: Option<[f32; 4]>,
+}
+
+/// what the player sees when looking at an inventory list
+pub struct FormContext<'a> {
+    pub items: &'a ItemRegistry,
+    pub map: &'a MapRender,
+    pub inventory: &'a Inventory,
+    pub detached_invs: &'a HashMap<String, Inventory>,
+    pub node_metas: &'a HashMap<Point3<i16>, NodeMeta>,
+}
+
+impl FormContext<'_> {
+    fn inventory(&self, location: &InvLocation) -> Option<&Inventory> {
+        match location {
+            InvLocation::CurrentPlayer => Some(self.inventory),
+            InvLocation::Detached(name) => self.detached_invs.get(name),
+            InvLocation::NodeMeta(pos) => self.node_metas.get(pos).map(|meta| &meta.inv),
+            // the client doesn't know other players' inventories
+            _ => None,
+        }
+    }
+
+    fn stack(&self, slot: &InvSlot) -> Option<&ItemStack> {
+        self.inventory(&slot.inv)?
+            .stack(&slot.list, slot.index as usize)
+    }
+}
+
+/// something the server has to be told about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormEvent {
+    /// values of the form's fields, "quit" is set if the form was closed
+    Fields(HashMap<String, String>),
+    InvAction(InvAction),
+}
+
+/// interactive state of an element
+#[derive(Debug, Clone, PartialEq)]
+enum State {
+    None,
+    /// the cursor is counted in characters
+    Field {
+        text: String,
+        cursor: usize,
+    },
+    Checkbox(bool),
+    Dropdown {
+        selected: usize,
+        open: bool,
+    },
+    TextList {
+        selected: Option<usize>,
+        scroll: usize,
+    },
+    Tab(usize),
+    Scrollbar(i32),
+}
+
+impl State {
+    fn new(element: &Element) -> Self {
+        match element {
+            Element::Field { default, .. } => State::Field {
+                text: default.clone(),
+                cursor: default.chars().count(),
+            },
+            Element::Checkbox { selected, .. } => State::Checkbox(*selected),
+            Element::Dropdown {
+                selected, items, ..
+            } => State::Dropdown {
+                selected: (selected - 1).min(items.len().saturating_sub(1)),
+                open: false,
+            },
+            Element::TextList { selected, .. } => State::TextList {
+                selected: selected.checked_sub(1),
+                scroll: 0,
+            },
+            Element::TabHeader { current, .. } => State::Tab(current - 1),
+            Element::Scrollbar { value, options, .. } => {
+                State::Scrollbar((*value).clamp(options.min, options.max.max(options.min)))
+            }
+            _ => State::None,
+        }
+    }
+}
+
+/// name of an element that sends fields
+fn element_name(element: &Element) -> Option<&str> {
+    match element {
+        Element::Field { name, .. }
+        | Element::Button { name, .. }
+        | Element::TextList { name, .. }
+        | Element::TabHeader { name, .. }
+        | Element::Dropdown { name, .. }
+        | Element::Checkbox { name, .. }
+        | Element::Scrollbar { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+/// the text of a textlist row and its color, rows may start with a #RRGGBB color
+fn row_text(row: &str) -> (&str, [f32; 4]) {
+    if let Some(rest) = row.strip_prefix("##") {
+        return (rest, WHITE);
+    }
+
+    match row.get(..7).and_then(parse::parse_color) {
+        Some(color) if row.starts_with('#') => (&row[7..], color),
+        _ => (row, WHITE),
+    }
+}
+
+/// hypertext shown as plain text without its tags
+fn strip_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_tag = false;
+
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+
+    out
+}
+
+/// byte index of the character at index i
+fn byte_index(text: &str, i: usize) -> usize {
+    text.char_indices().nth(i).map_or(text.len(), |(i, _)| i)
+}
+
+/// nine-slice borders given like upstream's middle rect, in pixels from each side
+fn borders(middle: [f32; 4], size: (u32, u32)) -> [f32; 4] {
+    let [left, top, right, bottom] = middle;
+
+    // upstream's positive x2 and y2 are positions from the left and top, parsed as negative
+    let from_end = |border: f32, size: u32| {
+        if border < 0.0 {
+            size as f32 + border
+        } else {
+            border
+        }
+    };
+
+    [left, top, from_end(right, size.0), from_end(bottom, size.1)]
+}
+
+fn outline(ui: &mut Ui, rect: Rect, color: [f32; 4]) {
+    ui.fill(Rect::new(rect.x, rect.y, rect.w, 1.0), color);
+    ui.fill(Rect::new(rect.x, rect.y + rect.h - 1.0, rect.w, 1.0), color);
+    ui.fill(Rect::new(rect.x, rect.y, 1.0, rect.h), color);
+    ui.fill(Rect::new(rect.x + rect.w - 1.0, rect.y, 1.0, rect.h), color);
+}
+
+/// the parts of a scrollbar: the track between the arrows and the thumb
+fn scrollbar_parts(
+    rect: Rect,
+    horizontal: bool,
+    options: &ScrollbarOptions,
+    value: i32,
+) -> (Rect, Rect) {
+    let (start, len, thick) = if horizontal {
+        (rect.x, rect.w, rect.h)
+    } else {
+        (rect.y, rect.h, rect.w)
+    };
+
+    let arrow = if options.arrows {
+        thick.min(len / 3.0)
+    } else {
+        0.0
+    };
+    let (track_start, track_len) = (start + arrow, len - arrow * 2.0);
+
+    let range = (options.max - options.min).max(0) as f32;
+    let thumb_size = options.thumb_size.max(1) as f32;
+    let thumb_len = if range > 0.0 {
+        (track_len * thumb_size / (range + thumb_size))
+            .max(thick / 2.0)
+            .min(track_len)
+    } else {
+        track_len
+    };
+    let thumb_start = if range > 0.0 {
+        track_start + (value - options.min) as f32 / range * (track_len - thumb_len)
+    } else {
+        track_start
+    };
+
+    if horizontal {
+        (
+            Rect::new(track_start, rect.y, track_len, rect.h),
+            Rect::new(thumb_start, rect.y, thumb_len, rect.h),
+        )
+    } else {
+        (
+            Rect::new(rect.x, track_start, rect.w, track_len),
+            Rect::new(rect.x, thumb_start, rect.w, thumb_len),
+        )
+    }
+}
+
+/// the value of a scrollbar whose thumb is centered at pos along its axis
+fn scrollbar_value(
+    rect: Rect,
+    horizontal: bool,
+    options: &ScrollbarOptions,
+    pos: (f32, f32),
+) -> i32 {
+    let (track, thumb) = scrollbar_parts(rect, horizontal, options, options.min);
+    let (pos, start, len, thumb) = if horizontal {
+        (pos.0, track.x, track.w, thumb.w)
+    } else {
+        (pos.1, track.y, track.h, thumb.h)
+    };
+
+    let range = (options.max - options.min).max(0) as f32;
+    let part = ((pos - start - thumb / 2.0) / (len - thumb).max(1.0)).clamp(0.0, 1.0);
+    options.min + (part * range).round() as i32
+}
+
+/// a formspec shown on screen
+pub struct Form {
+    pub name: String,
+    /// the node the form was opened for, or the current player
+    pub context: InvLocation,
+    /// the formspec as sent by the server
+    pub formspec: String,
+    spec: Formspec,
+    layout: Layout,
+    states: Vec<State>,
+    /// index of the focused field
+    focus: Option<usize>,
+    mouse: (f32, f32),
+    /// widget of the button or scrollbar thumb held down
+    pressed: Option<usize>,
+    /// the slot an item is taken from, and how many of it, 0 for all
+    held: Option<(InvSlot, u16)>,
+    /// the textlist row clicked last, to detect double clicks
+    last_click: Option<(usize, usize, Instant)>,
+    pub shift: bool,
+    scale: f32,
+    opened: Instant,
+}
+
+impl Form {
+    pub fn new(
+        name: String,
+        context: InvLocation,
+        formspec: &str,
+        prepend: &str,
+        screen: (f32, f32),
+        scale: f32,
+    ) -> Self {
+        let mut spec = parse::parse(formspec, &context);
+        spec.prepend(&parse::parse(prepend, &context));
+
+        let states = spec.elements.iter().map(State::new).collect();
+        let layout = Layout::new(&spec, screen, scale);
+
+        // the named field, or the first one
+        let focus = spec
+            .elements
+            .iter()
+            .rev()
+            .find_map(|element| match element {
+                Element::SetFocus(name) => Some(name),
+                _ => None,
+            })
+            .and_then(|focus| {
+                spec.elements.iter().position(|element| {
+                    matches!(element, Element::Field { .. })
+                        && element_name(element) == Some(focus.as_str())
+                })
+            })
+            .or_else(|| {
+                spec.elements
+                    .iter()
+                    .position(|element| matches!(element, Element::Field { .. }))
+            });
+
+        Self {
+            name,
+            context,
+            formspec: formspec.to_string(),
+            spec,
+            layout,
+            states,
+            focus,
+            mouse: (0.0, 0.0),
+            pressed: None,
+            held: None,
+            last_click: None,
+            shift: false,
+            scale,
+            opened: Instant::now(),
+        }
+    }
+
+    /// replaces the formspec, keeping what was typed into fields of the same name
+    pub fn update(&mut self, formspec: &str, prepend: &str, screen: (f32, f32)) {
+        let mut form = Form::new(
+            self.name.clone(),
+            self.context.clone(),
+            formspec,
+            prepend,
+            screen,
+            self.scale,
+        );
+
+        for (i, element) in form.spec.elements.iter().enumerate() {
+            let name = match (element, element_name(element)) {
+                (Element::Field { .. }, Some(name)) => name,
+                _ => continue,
+            };
+
+            let old = self.spec.elements.iter().position(|old| {
+                matches!(old, Element::Field { .. }) && element_name(old) == Some(name)
+            });
+
+            if let Some(old) = old {
+                form.states[i] = self.states[old].clone();
+
+                if self.focus == Some(old) {
+                    form.focus = Some(i);
+                }
+            }
+        }
+
+        form.mouse = self.mouse;
+        form.held = self.held.take();
+        form.shift = self.shift;
+        *self = form;
+    }
+
+    pub fn resize(&mut self, screen: (f32, f32)) {
+        self.layout = Layout::new(&self.spec, screen, self.scale);
+    }
+
+    /// whether keys are typed into a field instead of triggering actions
+    pub fn typing(&self) -> bool {
+        self.focus.is_some()
+    }
+
+    fn scrollbar_state(&self, name: &str) -> i32 {
+        self.spec
+            .elements
+            .iter()
+            .zip(&self.states)
+            .find_map(|(element, state)| match (element, state) {
+                (Element::Scrollbar { name: n, .. }, State::Scrollbar(value)) if n == name => {
+                    Some(*value)
+                }
+                _ => None,
+            })
+            .unwrap_or(0)
+    }
+
+    /// how far the widgets of a scroll area and its parents are moved
+    fn scroll_offset(&self, area: Option<usize>) -> (f32, f32) {
+        let mut offset = (0.0, 0.0);
+        let mut area = area;
+
+        while let Some(i) = area {
+            let scroll = &self.layout.scroll_areas[i];
+            let moved = self.scrollbar_state(&scroll.scrollbar) as f32 * scroll.step;
+
+            if scroll.horizontal {
+                offset.0 -= moved;
+            } else {
+                offset.1 -= moved;
+            }
+
+            area = scroll.parent;
+        }
+
+        offset
+    }
+
+    /// the part of the screen a widget in a scroll area is visible in
+    fn clip(&self, area: Option<usize>) -> Option<Rect> {
+        let i = area?;
+        let scroll = &self.layout.scroll_areas[i];
+        let parent = scroll.parent;
+
+        let offset = self.scroll_offset(parent);
+        let rect = Rect::new(
+            scroll.rect.x + offset.0,
+            scroll.rect.y + offset.1,
+            scroll.rect.w,
+            scroll.rect.h,
+        );
+
+        Some(match self.clip(parent) {
+            Some(clip) => clip
+                .intersect(rect)
+                .unwrap_or(Rect::new(rect.x, rect.y, 0.0, 0.0)),
+            None => rect,
+        })
+    }
+
+    /// where a widget is on screen after scrolling
+    fn rect(&self, widget: &Widget) -> Rect {
+        let offset = self.scroll_offset(widget.scroll);
+        let rect = widget.rect;
+        Rect::new(rect.x + offset.0, rect.y + offset.1, rect.w, rect.h)
+    }
+
+    /// whether the mouse is over a widget and not scrolled out of view
+    fn hovered(&self, widget: &Widget) -> bool {
+        let (x, y) = self.mouse;
+        self.rect(widget).contains(x, y)
+            && self
+                .clip(widget.scroll)
+                .map_or(true, |clip| clip.contains(x, y))
+    }
+
+    /// the topmost widget under the mouse that can be interacted with
+    fn widget_at_mouse(&self) -> Option<usize> {
+        self.layout.widgets.iter().rposition(|widget| {
+            self.hovered(widget)
+                && matches!(
+                    self.spec.elements[widget.element],
+                    Element::List { .. }
+                        | Element::Field { .. }
+                        | Element::Button { .. }
+                        | Element::TextList { .. }
+                        | Element::TabHeader { .. }
+                        | Element::Dropdown { .. }
+                        | Element::Checkbox { .. }
+                        | Element::Scrollbar { .. }
+                )
+        })
+    }
+
+    fn row_height(&self) -> f32 {
+        (self.layout.geometry.font_size * 1.5).round()
+    }
+
+    /// the popup of an open dropdown, its widget and the rectangle of its items
+    fn popup(&self) -> Option<(usize, Rect)> {
+        self.layout
+            .widgets
+            .iter()
+            .enumerate()
+            .find_map(|(i, widget)| {
+                match (
+                    &self.spec.elements[widget.element],
+                    &self.states[widget.element],
+                ) {
+                    (Element::Dropdown { items, .. }, State::Dropdown { open: true, .. }) => {
+                        let rect = self.rect(widget);
+                        let h = rect.h * items.len() as f32;
+                        Some((i, Rect::new(rect.x, rect.y + rect.h, rect.w, h)))
+                    }
+                    _ => None,
+                }
+            })
+    }
+
+    /// the list slot under the mouse, along with the widget it's in
+    fn slot_at_mouse(&self) -> Option<(usize, InvSlot)> {
+        let i = self.widget_at_mouse()?;
+        let widget = &self.layout.widgets[i];
+
+        let (l, list, size, start) = match &self.spec.elements[widget.element] {
+            Element::List {
+                location: l,
+                list,
+                size,
+                start,
+                ..
+            } => (l, list, size, start),
+            _ => return None,
+        };
+
+        let rect = self.rect(widget);
+        let step = self.layout.geometry.slot_step(widget.real);
+        let (x, y) = (self.mouse.0 - rect.x, self.mouse.1 - rect.y);
+        let (col, row) = ((x / step.x) as usize, (y / step.y) as usize);
+
+        // the gaps between slots don't belong to any
+        let imgsize = self.layout.geometry.imgsize;
+        if x - col as f32 * step.x >= imgsize || y - row as f32 * step.y >= imgsize {
+            return None;
+        }
+
+        let index = start + row * size.x as usize + col;
+        Some((
+            i,
+            InvSlot {
+                inv: l.clone(),
+                list: list.clone(),
+                index: index as u16,
+            },
+        ))
+    }
+
+    /// values of all fields, along with the one that triggered sending them
+    fn fields(&self, name: &str, value: &str) -> HashMap<String, String> {
+        let mut fields = HashMap::new();
+
+        for (element, state) in self.spec.elements.iter().zip(&self.states) {
+            match (element, state) {
+                (Element::Field { name, .. }, State::Field { text, .. }) => {
+                    fields.insert(name.clone(), text.clone());
+                }
+                (
+                    Element::Dropdown {
+                        name,
+                        items,
+                        index_event,
+                        ..
+                    },
+                    State::Dropdown { selected, .. },
+                ) => {
+                    let value = if *index_event {
+                        (selected + 1).to_string()
+                    } else {
+                        items.get(*selected).cloned().unwrap_or_default()
+                    };
+                    fields.insert(name.clone(), value);
+                }
+                (Element::Scrollbar { name, .. }, State::Scrollbar(value)) => {
+                    fields.insert(name.clone(), format!("VAL:{value}"));
+                }
+                _ => {}
+            }
+        }
+
+        fields.remove("");
+        if !name.is_empty() {
+            fields.insert(name.into(), value.into());
+        }
+
+        fields
+    }
+
+    /// fields sent when the form is closed
+    pub fn close(&self) -> FormEvent {
+        FormEvent::Fields(self.fields("quit", "true"))
+    }
+
+    fn close_dropdowns(&mut self) {
+        for state in &mut self.states {
+            if let State::Dropdown { open, .. } = state {
+                *open = false;
+            }
+        }
+    }
+
+    pub fn cursor_moved(&mut self, x: f32, y: f32) {
+        self.mouse = (x, y);
+
+        // dragging a scrollbar's thumb
+        let widget = match self.pressed {
+            Some(i) => self.layout.widgets[i],
+            None => return,
+        };
+
+        if let Element::Scrollbar {
+            horizontal,
+            options,
+            ..
+        } = &self.spec.elements[widget.element]
+        {
+            let value = scrollbar_value(self.rect(&widget), *horizontal, options, self.mouse);
+            self.states[widget.element] = State::Scrollbar(value);
+        }
+    }
+
+    pub fn mouse_input(
+        &mut self,
+        button: MouseButton,
+        pressed: bool,
+        ctx: &FormContext,
+    ) -> Vec<FormEvent> {
+        if !pressed {
+            return self.release();
+        }
+
+        // an open dropdown takes all clicks
+        if let Some((i, rect)) = self.popup() {
+            self.close_dropdowns();
+
+            if !rect.contains(self.mouse.0, self.mouse.1) || button != MouseButton::Left {
+                return Vec::new();
+            }
+
+            // the selected item is sent along with the other fields
+            let element = self.layout.widgets[i].element;
+            let row = ((self.mouse.1 - rect.y) / self.layout.widgets[i].rect.h) as usize;
+            if let State::Dropdown { selected, .. } = &mut self.states[element] {
+                *selected = row;
+            }
+
+            return vec![FormEvent::Fields(self.fields("", ""))];
+        }
+
+        if let Some((_, slot)) = self.slot_at_mouse() {
+            return self.click_slot(slot, button, ctx);
+        }
+
+        let i = match self.widget_at_mouse() {
+            Some(i) => i,
+            None => {
+                self.focus = None;
+
+                // items dropped outside of the form are thrown away
+                let (x, y) = self.mouse;
+                return match self.held.take() {
+                    Some((from, count)) if !self.layout.geometry.form.contains(x, y) => {
+                        let count = match button {
+                            MouseButton::Right => 1,
+                            _ => count,
+                        };
+                        vec![FormEvent::InvAction(InvAction::Drop { count, from })]
+                    }
+                    held => {
+                        self.held = held;
+                        Vec::new()
+                    }
+                };
+            }
+        };
+
+        if button != MouseButton::Left {
+            return Vec::new();
+        }
+
+        self.click(i)
+    }
+
+    fn click(&mut self, i: usize) -> Vec<FormEvent> {
+        let widget = self.layout.widgets[i];
+        let rect = self.rect(&widget);
+        let element = widget.element;
+        let row_h = self.row_height();
+
+        match (&self.spec.elements[element], &mut self.states[element]) {
+            (Element::Field { .. }, State::Field { text, cursor }) => {
+                self.focus = Some(element);
+                *cursor = text.chars().count();
+                Vec::new()
+            }
+            (Element::Button { .. }, _) => {
+                self.pressed = Some(i);
+                Vec::new()
+            }
+            (Element::Checkbox { name, .. }, State::Checkbox(checked)) => {
+                *checked = !*checked;
+                let (name, value) = (name.clone(), checked.to_string());
+                vec![FormEvent::Fields(self.fields(&name, &value))]
+            }
+            (Element::Dropdown { .. }, State::Dropdown { open, .. }) => {
+                *open = true;
+                Vec::new()
+            }
+            (Element::TabHeader { name, captions, .. }, State::Tab(current)) => {
+                let tab = ((self.mouse.0 - rect.x) / rect.w * captions.len() as f32) as usize;
+                if tab >= captions.len() {
+                    return Vec::new();
+                }
+
+                *current = tab;
+                let name = name.clone();
+                vec![FormEvent::Fields(
+                    self.fields(&name, &(tab + 1).to_string()),
+                )]
+            }
+            (Element::TextList { name, items, .. }, State::TextList { selected, scroll }) => {
+                let row = *scroll + ((self.mouse.1 - rect.y) / row_h) as usize;
+                if row >= items.len() {
+                    return Vec::new();
+                }
+
+                *selected = Some(row);
+                let name = name.clone();
+
+                let now = Instant::now();
+                let double = matches!(
+                    self.last_click,
+                    Some((e, r, time)) if e == element && r == row && now - time < DOUBLE_CLICK
+                );
+                self.last_click = (!double).then_some((element, row, now));
+
+                let kind = if double { "DCL" } else { "CHG" };
+                vec![FormEvent::Fields(
+                    self.fields(&name, &format!("{kind}:{}", row + 1)),
+                )]
+            }
+            (
+                Element::Scrollbar {
+                    name,
+                    horizontal,
+                    options,
+                    ..
+                },
+                State::Scrollbar(value),
+            ) => {
+                let (track, thumb) = scrollbar_parts(rect, *horizontal, options, *value);
+                let (mouse, track_start, track_end, thumb_start, thumb_end) = if *horizontal {
+                    (
+                        self.mouse.0,
+                        track.x,
+                        track.x + track.w,
+                        thumb.x,
+                        thumb.x + thumb.w,
+                    )
+                } else {
+                    (
+                        self.mouse.1,
+                        track.y,
+                        track.y + track.h,
+                        thumb.y,
+                        thumb.y + thumb.h,
+                    )
+                };
+
+                let step = if mouse < track_start {
+                    -options.small_step
+                } else if mouse >= track_end {
+                    options.small_step
+                } else if mouse < thumb_start {
+                    -options.large_step
+                } else if mouse >= thumb_end {
+                    options.large_step
+                } else {
+                    self.pressed = Some(i);
+                    return Vec::new();
+                };
+
+                *value = (*value + step).clamp(options.min, options.max.max(options.min));
+                let (name, value) = (name.clone(), format!("CHG:{value}"));
+                vec![FormEvent::Fields(self.fields(&name, &value))]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// finishes clicks on buttons and drags of scrollbars
+    fn release(&mut self) -> Vec<FormEvent> {
+        let i = match self.pressed.take() {
+            Some(i) => i,
+            None => return Vec::new(),
+        };
+
+        let widget = self.layout.widgets[i];
+        match (
+            &self.spec.elements[widget.element],
+            &self.states[widget.element],
+        ) {
+            (
+                Element::Button {
+                    name, label, exit, ..
+                },
+                _,
+            ) if self.hovered(&widget) => {
+                let mut fields = self.fields(name, label);
+                if *exit {
+                    fields.insert("quit".into(), "true".into());
+                }
+                vec![FormEvent::Fields(fields)]
+            }
+            (Element::Scrollbar { name, .. }, State::Scrollbar(value)) => {
+                vec![FormEvent::Fields(
+                    self.fields(name, &format!("CHG:{value}")),
+                )]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// moves items between slots like upstream: the first click takes a stack, the second one
+    /// puts it down, shift moves it to the next list of the list ring right away
+    fn click_slot(
+        &mut self,
+        slot: InvSlot,
+        button: MouseButton,
+        ctx: &FormContext,
+    ) -> Vec<FormEvent> {
+        let count = match button {
+            MouseButton::Left => 0,
+            MouseButton::Right => 1,
+            _ => return Vec::new(),
+        };
+
+        if let Some((from, held)) = self.held.take() {
+            if from == slot {
+                return Vec::new();
+            }
+
+            // right clicks put down one item at a time
+            let left = ctx.stack(&from).map_or(0, |stack| stack.count);
+            let moved = if count == 0 { held } else { 1 };
+            if count == 1 && (held == 0 || held > 1) && left > 1 {
+                self.held = Some((from.clone(), held.saturating_sub(1)));
+            }
+
+            return vec![FormEvent::InvAction(InvAction::Move {
+                count: moved,
+                from,
+                to: slot,
+            })];
+        }
+
+        let stack = match ctx.stack(&slot) {
+            Some(stack) if !stack.is_empty() => stack,
+            _ => return Vec::new(),
+        };
+
+        if self.shift && count == 0 {
+            return self
+                .ring_target(&slot, stack, ctx)
+                .map(|to| {
+                    FormEvent::InvAction(InvAction::Move {
+                        count: 0,
+                        from: slot,
+                        to,
+                    })
+                })
+                .into_iter()
+                .collect();
+        }
+
+        // right clicks take half the stack
+        let count = match count {
+            0 => 0,
+            _ => (stack.count + 1) / 2,
+        };
+        self.held = Some((slot, count));
+        Vec::new()
+    }
+
+    /// where shift clicking a stack moves it: a slot in the list after its own in the list ring
+    fn ring_target(&self, from: &InvSlot, stack: &ItemStack, ctx: &FormContext) -> Option<InvSlot> {
+        let ring: Vec<_> = self
+            .spec
+            .elements
+            .iter()
+            .filter_map(|element| match element {
+                Element::ListRing { location, list } => Some((location, list)),
+                _ => None,
+            })
+            .collect();
+
+        let pos = ring
+            .iter()
+            .position(|&(inv, list)| *inv == from.inv && *list == from.list)?;
+        let &(inv, list) = ring.get(pos + 1).or(ring.first())?;
+        if *inv == from.inv && *list == from.list {
+            return None;
+        }
+
+        // prefer a stack of the same item to merge with, then an empty slot
+        let target = ctx.inventory(inv)?.list(list)?;
+        let index = target
+            .stacks
+            .iter()
+            .position(|s| !s.is_empty() && s.name == stack.name && s.wear == stack.wear)
+            .or_else(|| target.stacks.iter().position(ItemStack::is_empty))?;
+
+        Some(InvSlot {
+            inv: inv.clone(),
+            list: list.clone(),
+            index: index as u16,
+        })
+    }
+
+    /// scrolls text lists and scrollbars under the mouse, delta is in lines
+    pub fn scroll(&mut self, delta: i32) -> Vec<FormEvent> {
+        let i = match self.widget_at_mouse() {
+            Some(i) => i,
+            None => {
+                // scroll containers scroll with their scrollbar
+                let (x, y) = self.mouse;
+                let area = self
+                    .layout
+                    .scroll_areas
+                    .iter()
+                    .rposition(|area| area.rect.contains(x, y));
+
+                let name = match area {
+                    Some(area) => self.layout.scroll_areas[area].scrollbar.clone(),
+                    None => return Vec::new(),
+                };
+
+                match self.layout.widgets.iter().position(|widget| {
+                    element_name(&self.spec.elements[widget.element]) == Some(name.as_str())
+                }) {
+                    Some(i) => i,
+                    None => return Vec::new(),
+                }
+            }
+        };
+
+        let element = self.layout.widgets[i].element;
+        let rows = (self.layout.widgets[i].rect.h / self.row_height()) as usize;
+
+        match (&self.spec.elements[element], &mut self.states[element]) {
+            (Element::TextList { items, .. }, State::TextList { scroll, .. }) => {
+                let max = items.len().saturating_sub(rows);
+                *scroll = (*scroll as i32 - delta).clamp(0, max as i32) as usize;
+                Vec::new()
+            }
+            (Element::Scrollbar { name, options, .. }, State::Scrollbar(value)) => {
+                let new = (*value - delta * options.small_step)
+                    .clamp(options.min, options.max.max(options.min));
+                if new == *value {
+                    return Vec::new();
+                }
+
+                *value = new;
+                let name = name.clone();
+                vec![FormEvent::Fields(self.fields(&name, &format!("CHG:{new}")))]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn key(&mut self, key: Key) -> Vec<FormEvent> {
+        if key == Key::Escape {
+            if self.popup().is_some() {
+                self.close_dropdowns();
+                return Vec::new();
+            }
+
+            return vec![self.close()];
+        }
+
+        let focus = match self.focus {
+            Some(focus) => focus,
+            None => return Vec::new(),
+        };
+
+        let (name, kind) = match &self.spec.elements[focus] {
+            Element::Field { name, kind, .. } => (name.clone(), *kind),
+            _ => return Vec::new(),
+        };
+
+        if key == Key::Tab {
+            // focus the next field, wrapping around
+            let fields = self.spec.elements.len();
+            self.focus = (1..=fields)
+                .map(|i| (focus + i) % fields)
+                .find(|&i| matches!(self.spec.elements[i], Element::Field { .. }));
+            return Vec::new();
+        }
+
+        if matches!(key, Key::Return | Key::NumpadEnter) && kind != FieldKind::TextArea {
+            let close = self
+                .spec
+                .elements
+                .iter()
+                .rev()
+                .find_map(|element| match element {
+                    Element::FieldCloseOnEnter { name: n, close } if *n == name => Some(*close),
+                    _ => None,
+                })
+                .unwrap_or(true);
+
+            let mut fields = self.fields("key_enter_field", &name);
+            fields.insert("key_enter".into(), "true".into());
+            if close {
+                fields.insert("quit".into(), "true".into());
+            }
+
+            return vec![FormEvent::Fields(fields)];
+        }
+
+        if let State::Field { text, cursor } = &mut self.states[focus] {
+            let len = text.chars().count();
+
+            match key {
+                Key::Back if *cursor > 0 => {
+                    *cursor -= 1;
+                    text.remove(byte_index(text, *cursor));
+                }
+                Key::Delete if *cursor < len => {
+                    text.remove(byte_index(text, *cursor));
+                }
+                Key::Left => *cursor = cursor.saturating_sub(1),
+                Key::Right => *cursor = (*cursor + 1).min(len),
+                Key::Home => *cursor = 0,
+                Key::End => *cursor = len,
+                Key::Return | Key::NumpadEnter => {
+                    text.insert(byte_index(text, *cursor), '\n');
+                    *cursor += 1;
+                }
+                _ => {}
+            }
+        }
+
+        Vec::new()
+    }
+
+    /// types a character into the focused field
+    pub fn character(&mut self, c: char) {
+        if c.is_control() {
+            return;
+        }
+
+        if let Some(State::Field { text, cursor }) = self.focus.map(|i| &mut self.states[i]) {
+            text.insert(byte_index(text, *cursor), c);
+            *cursor += 1;
+        }
+    }
+
+    fn text<'a>(
+        &self,
+        text: &'a str,
+        pos: (f32, f32),
+        bounds: (f32, f32),
+        color: [f32; 4],
+    ) -> Section<'a> {
+        Section {
+            screen_position: pos,
+            bounds,
+            text: vec![Text::new(text)
+                .with_color(color)
+                .with_scale(self.layout.geometry.font_size)],
+            ..Section::default()
+        }
+    }
+
+    /// text centered in rect
+    fn centered<'a>(&self, text: &'a str, rect: Rect, color: [f32; 4]) -> Section<'a> {
+        Section {
+            layout: TextLayout::default_single_line()
+                .h_align(HorizontalAlign::Center)
+                .v_align(VerticalAlign::Center),
+            ..self.text(
+                text,
+                (rect.x + rect.w / 2.0, rect.y + rect.h / 2.0),
+                (rect.w, rect.h),
+                color,
+            )
+        }
+    }
+
+    pub fn render(&self, ctx: &FormContext, ui: &mut Ui, font: &mut Font) {
+        let z = ui.z();
+        ui.set_z(Z);
+
+        let screen = ui.screen_size();
+        let form = self.layout.geometry.form;
+
+        let bgcolor = self.spec.bgcolor.as_ref();
+        if bgcolor.map_or(false, |bg| bg.fullscreen) {
+            let color = bgcolor
+                .and_then(|bg| bg.fullscreen_color)
+                .unwrap_or(BACKGROUND);
+            ui.fill(Rect::new(0.0, 0.0, screen.0, screen.1), color);
+        }
+        if bgcolor.map_or(true, |bg| bg.form) {
+            let color = bgcolor.and_then(|bg| bg.color).unwrap_or(BACKGROUND);
+            ui.fill(form, color);
+        }
+
+        let colors = self
+            .spec
+            .elements
+            .iter()
+            .rev()
+            .find_map(|element| match element {
+                Element::ListColors {
+                    normal,
+                    hover,
+                    border,
+                } => Some(SlotColors {
+                    normal: *normal,
+                    hover: *hover,
+                    border: *border,
+                }),
+                _ => None,
+            })
+            .unwrap_or(SlotColors {
+                normal: SLOT,
+                hover: SLOT_HOVER,
+                border: None,
+            });
+
+        for (i, widget) in self.layout.widgets.iter().enumerate() {
+            let clip = self.clip(widget.scroll);
+            if let Some(clip) = clip {
+                ui.push_clip(clip);
+            }
+
+            self.render_widget(i, colors, ctx, ui, font);
+
+            if clip.is_some() {
+                ui.pop_clip();
+            }
+        }
+
+        ui.set_z(Z + 1);
+        self.render_popup(ui);
+
+        ui.set_z(Z + 2);
+        self.render_held(ctx, ui);
+
+        ui.set_z(Z + 3);
+        self.render_tooltip(ctx, ui, font);
+
+        ui.set_z(z);
+    }
+
+    fn render_widget(
+        &self,
+        i: usize,
+        colors: SlotColors,
+        ctx: &FormContext,
+        ui: &mut Ui,
+        font: &mut Font,
+    ) {
+        let widget = &self.layout.widgets[i];
+        let rect = self.rect(widget);
+        let hovered = self.hovered(widget);
+        let geom = &self.layout.geometry;
+        let state = &self.states[widget.element];
+
+        match &self.spec.elements[widget.element] {
+            Element::Image {
+                texture, middle, ..
+            }
+            | Element::Background {
+                texture, middle, ..
+            } => match middle {
+                Some(middle) => {
+                    if let Some(size) = ui.texture_size(texture) {
+                        ui.image_nine_slice(texture, rect, borders(*middle, size), WHITE);
+                    }
+                }
+                None if !texture.is_empty() => ui.image(texture, rect, WHITE),
+                None => {}
+            },
+            Element::AnimatedImage {
+                texture,
+                frames,
+                duration,
+                start,
+                ..
+            } => {
+                // the frames are stacked vertically in the texture
+                let elapsed = self.opened.elapsed().as_millis() as u32 / duration;
+                let frame = (start.saturating_sub(1) + elapsed) % frames;
+                let h = 1.0 / *frames as f32;
+                ui.image_region(
+                    texture,
+                    rect,
+                    Rect::new(0.0, frame as f32 * h, 1.0, h),
+                    WHITE,
+                );
+            }
+            Element::ItemImage { item, .. } => {
+                let stack = item.parse().unwrap_or_default();
+                hotbar::draw_item(ctx.items, ctx.map, &stack, rect, self.scale, ui);
+            }
+            Element::Box { color, .. } => ui.fill(rect, *color),
+            Element::Label { text, vertical, .. } => {
+                let text = strip_escapes(text);
+                let text = if *vertical {
+                    text.chars()
+                        .map(String::from)
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                } else {
+                    text
+                };
+
+                ui.text(self.text(&text, (rect.x, rect.y), ui.screen_size(), WHITE));
+            }
+            Element::Hypertext { text, .. } => {
+                let text = strip_tags(text);
+                ui.push_clip(rect);
+                ui.text(self.text(&text, (rect.x, rect.y), (rect.w, rect.h), WHITE));
+                ui.pop_clip();
+            }
+            Element::List {
+                location: l,
+                list,
+                size,
+                start,
+                ..
+            } => {
+                let stacks = ctx.inventory(l).and_then(|inv| inv.list(list));
+                let step = geom.slot_step(widget.real);
+                let hovered_slot = self
+                    .slot_at_mouse()
+                    .filter(|(w, _)| *w == i)
+                    .map(|(_, slot)| slot.index as usize);
+
+                for row in 0..size.y as usize {
+                    for col in 0..size.x as usize {
+                        let index = start + row * size.x as usize + col;
+                        let slot_rect = Rect::new(
+                            rect.x + col as f32 * step.x,
+                            rect.y + row as f32 * step.y,
+                            geom.imgsize,
+                            geom.imgsize,
+                        );
+
+                        let hover = hovered_slot == Some(index);
+                        ui.fill(slot_rect, if hover { colors.hover } else { colors.normal });
+                        if let Some(border) = colors.border {
+                            outline(ui, slot_rect, border);
+                        }
+
+                        let mut stack = match stacks.and_then(|l| l.stacks.get(index)) {
+                            Some(stack) => stack.clone(),
+                            None => continue,
+                        };
+
+                        // the held items follow the mouse instead
+                        if let Some((from, held)) = &self.held {
+                            if from.list == *list && from.index as usize == index && *l == from.inv
+                            {
+                                stack.count -= if *held == 0 {
+                                    stack.count
+                                } else {
+                                    (*held).min(stack.count)
+                                };
+                            }
+                        }
+
+                        hotbar::draw_item(ctx.items, ctx.map, &stack, slot_rect, self.scale, ui);
+                    }
+                }
+            }
+            Element::Field { kind, label, .. } => {
+                let (text, cursor) = match state {
+                    State::Field { text, cursor } => (text, *cursor),
+                    _ => return,
+                };
+                let focused = self.focus == Some(widget.element);
+
+                if !label.is_empty() {
+                    let line = geom.font_size * 1.25;
+                    ui.text(self.text(label, (rect.x, rect.y - line), ui.screen_size(), WHITE));
+                }
+
+                ui.fill(rect, FIELD);
+                outline(ui, rect, if focused { WHITE } else { [0.5, 0.5, 0.5, 1.0] });
+
+                let shown = match kind {
+                    FieldKind::Password => "*".repeat(text.chars().count()),
+                    _ => text.clone(),
+                };
+                let pad = geom.font_size / 4.0;
+                let inner = Rect::new(rect.x + pad, rect.y, rect.w - pad * 2.0, rect.h);
+
+                ui.push_clip(rect);
+                let section = match kind {
+                    FieldKind::TextArea => self.text(
+                        &shown,
+                        (inner.x, inner.y + pad),
+                        (inner.w, f32::INFINITY),
+                        WHITE,
+                    ),
+                    _ => Section {
+                        layout: TextLayout::default_single_line().v_align(VerticalAlign::Center),
+                        ..self.text(
+                            &shown,
+                            (inner.x, inner.y + inner.h / 2.0),
+                            (f32::INFINITY, inner.h),
+                            WHITE,
+                        )
+                    },
+                };
+                ui.text(section);
+
+                if focused {
+                    // the cursor goes after the last line of the text before it
+                    let before = &shown[..byte_index(&shown, cursor)];
+                    let line = before.rsplit('\n').next().unwrap_or_default();
+                    let lines = before.matches('\n').count() as f32;
+                    let width = font
+                        .text_size(self.text(
+                            line,
+                            (0.0, 0.0),
+                            (f32::INFINITY, f32::INFINITY),
+                            WHITE,
+                        ))
+                        .0;
+
+                    let h = geom.font_size;
+                    let y = match kind {
+                        FieldKind::TextArea => inner.y + pad + lines * h,
+                        _ => inner.y + (inner.h - h) / 2.0,
+                    };
+                    ui.fill(Rect::new(inner.x + width, y, 1.0, h), WHITE);
+                }
+                ui.pop_clip();
+            }
+            Element::Button {
+                label,
+                image,
+                pressed_image,
+                item,
+                border,
+                ..
+            } => {
+                let pressed = self.pressed == Some(i) && hovered;
+
+                match image {
+                    Some(image) => {
+                        let texture = match pressed_image {
+                            Some(texture) if pressed => texture,
+                            _ => image,
+                        };
+                        if !texture.is_empty() {
+                            ui.image(texture, rect, WHITE);
+                        }
+                    }
+                    None if item.is_none() || *border => ui.fill(
+                        rect,
+                        match (pressed, hovered) {
+                            (true, _) => BUTTON_PRESSED,
+                            (_, true) => BUTTON_HOVER,
+                            _ => BUTTON,
+                        },
+                    ),
+                    None => {}
+                }
+
+                if let Some(item) = item {
+                    let stack = item.parse().unwrap_or_default();
+                    hotbar::draw_item(ctx.items, ctx.map, &stack, rect, self.scale, ui);
+                }
+                if *border {
+                    outline(ui, rect, [0.0, 0.0, 0.0, 1.0]);
+                }
+
+                ui.text(self.centered(label, rect, WHITE));
+            }
+            Element::Checkbox { label, .. } => {
+                let checked = matches!(state, State::Checkbox(true));
+                let size = rect.h;
+                let check = Rect::new(rect.x, rect.y, size, size);
+
+                ui.fill(check, FIELD);
+                outline(
+                    ui,
+                    check,
+                    if hovered { WHITE } else { [0.5, 0.5, 0.5, 1.0] },
+                );
+                if checked {
+                    let pad = (size / 4.0).floor();
+                    ui.fill(
+                        Rect::new(
+                            check.x + pad,
+                            check.y + pad,
+                            size - pad * 2.0,
+                            size - pad * 2.0,
+                        ),
+                        WHITE,
+                    );
+                }
+
+                let label_rect = Rect::new(rect.x + size * 1.5, rect.y, rect.w, size);
+                ui.text(Section {
+                    layout: TextLayout::default_single_line().v_align(VerticalAlign::Center),
+                    ..self.text(
+                        label,
+                        (label_rect.x, label_rect.y + size / 2.0),
+                        ui.screen_size(),
+                        WHITE,
+                    )
+                });
+            }
+            Element::Dropdown { items, .. } => {
+                let selected = match state {
+                    State::Dropdown { selected, .. } => *selected,
+                    _ => return,
+                };
+
+                ui.fill(rect, if hovered { BUTTON_HOVER } else { BUTTON });
+                outline(ui, rect, [0.0, 0.0, 0.0, 1.0]);
+
+                // an arrow pointing down at the right
+                let arrow = rect.h / 3.0;
+                for row in 0..arrow as i32 {
+                    let w = (arrow - row as f32) * 2.0;
+                    let x = rect.x + rect.w - arrow * 2.0 - w / 2.0;
+                    ui.fill(Rect::new(x, rect.y + arrow + row as f32, w, 1.0), WHITE);
+                }
+
+                if let Some(item) = items.get(selected) {
+                    let text_rect = Rect::new(rect.x, rect.y, rect.w - arrow * 3.0, rect.h);
+                    ui.text(self.centered(item, text_rect, WHITE));
+                }
+            }
+            Element::TabHeader {
+                captions,
+                transparent,
+                border,
+                ..
+            } => {
+                let current = match state {
+                    State::Tab(current) => *current,
+                    _ => return,
+                };
+                let w = rect.w / captions.len().max(1) as f32;
+
+                for (tab, caption) in captions.iter().enumerate() {
+                    let tab_rect = Rect::new(rect.x + tab as f32 * w, rect.y, w, rect.h);
+
+                    if tab == current {
+                        ui.fill(tab_rect, BUTTON_HOVER);
+                    } else if !transparent {
+                        ui.fill(tab_rect, BUTTON);
+                    }
+                    if *border {
+                        outline(ui, tab_rect, [0.0, 0.0, 0.0, 1.0]);
+                    }
+
+                    ui.text(self.centered(caption, tab_rect, WHITE));
+                }
+            }
+            Element::TextList {
+                items, transparent, ..
+            } => {
+                let (selected, scroll) = match state {
+                    State::TextList { selected, scroll } => (*selected, *scroll),
+                    _ => return,
+                };
+                let row_h = self.row_height();
+
+                if !transparent {
+                    ui.fill(rect, FIELD);
+                }
+
+                ui.push_clip(rect);
+                for (row, item) in items.iter().enumerate().skip(scroll) {
+                    let y = rect.y + (row - scroll) as f32 * row_h;
+                    if y > rect.y + rect.h {
+                        break;
+                    }
+
+                    let row_rect = Rect::new(rect.x, y, rect.w, row_h);
+                    if selected == Some(row) {
+                        ui.fill(row_rect, SELECTED);
+                    }
+
+                    let (text, color) = row_text(item);
+                    let pad = geom.font_size / 4.0;
+                    ui.text(Section {
+                        layout: TextLayout::default_single_line().v_align(VerticalAlign::Center),
+                        ..self.text(
+                            text,
+                            (rect.x + pad, y + row_h / 2.0),
+                            (f32::INFINITY, row_h),
+                            color,
+                        )
+                    });
+                }
+                ui.pop_clip();
+            }
+            Element::Scrollbar {
+                horizontal,
+                options,
+                ..
+            } => {
+                let value = match state {
+                    State::Scrollbar(value) => *value,
+                    _ => return,
+                };
+
+                let (track, thumb) = scrollbar_parts(rect, *horizontal, options, value);
+                ui.fill(rect, BUTTON);
+                ui.fill(track, FIELD);
+                ui.fill(
+                    thumb,
+                    if hovered || self.pressed == Some(i) {
+                        BUTTON_HOVER
+                    } else {
+                        BUTTON
+                    },
+                );
+                outline(ui, thumb, [0.0, 0.0, 0.0, 1.0]);
+            }
+            _ => {}
+        }
+    }
+
+    fn render_popup(&self, ui: &mut Ui) {
+        let (i, rect) = match self.popup() {
+            Some(popup) => popup,
+            None => return,
+        };
+
+        let widget = &self.layout.widgets[i];
+        let (items, selected) = match (
+            &self.spec.elements[widget.element],
+            &self.states[widget.element],
+        ) {
+            (Element::Dropdown { items, .. }, State::Dropdown { selected, .. }) => {
+                (items, *selected)
+            }
+            _ => return,
+        };
+
+        ui.fill(rect, FIELD);
+        outline(ui, rect, [0.5, 0.5, 0.5, 1.0]);
+
+        let row_h = widget.rect.h;
+        for (row, item) in items.iter().enumerate() {
+            let row_rect = Rect::new(rect.x, rect.y + row as f32 * row_h, rect.w, row_h);
+            if row == selected || row_rect.contains(self.mouse.0, self.mouse.1) {
+                ui.fill(row_rect, SELECTED);
+            }
+
+            ui.text(self.centered(item, row_rect, WHITE));
+        }
+    }
+
+    /// the stack taken from a slot, drawn at the mouse
+    fn render_held(&self, ctx: &FormContext, ui: &mut Ui) {
+        let (from, held) = match &self.held {
+            Some(held) => held,
+            None => return,
+        };
+
+        let mut stack = match ctx.stack(from) {
+            Some(stack) if !stack.is_empty() => stack.clone(),
+            _ => return,
+        };
+        if *held > 0 {
+            stack.count = stack.count.min(*held);
+        }
+
+        let size = self.layout.geometry.imgsize;
+        let rect = Rect::new(
+            self.mouse.0 - size / 2.0,
+            self.mouse.1 - size / 2.0,
+            size,
+            size,
+        );
+        hotbar::draw_item(ctx.items, ctx.map, &stack, rect, self.scale, ui);
+    }
+
+    /// tooltip of the element or item under the mouse
+    fn tooltip(&self, ctx: &FormContext) -> Option<String> {
+        if self.held.is_some() {
+            return None;
+        }
+
+        if let Some((_, slot)) = self.slot_at_mouse() {
+            let stack = ctx.stack(&slot).filter(|stack| !stack.is_empty())?;
+            let text = match stack.description() {
+                Some(description) => description.to_string(),
+                None => ctx
+                    .items
+                    .get(&stack.name)
+                    .map_or(stack.name.clone(), |def| def.description.clone()),
+            };
+
+            return Some(strip_escapes(&text));
+        }
+
+        let name = self
+            .widget_at_mouse()
+            .and_then(|i| element_name(&self.spec.elements[self.layout.widgets[i].element]));
+
+        self.spec
+            .elements
+            .iter()
+            .rev()
+            .find_map(|element| match element {
+                Element::Tooltip {
+                    target: TooltipTarget::Name(target),
+                    text,
+                } if Some(target.as_str()) == name => Some(text.clone()),
+                _ => None,
+            })
+            .or_else(|| {
+                self.layout.widgets.iter().rev().find_map(|widget| {
+                    match &self.spec.elements[widget.element] {
+                        Element::Tooltip {
+                            target: TooltipTarget::Area(..),
+                            text,
+                        } if self.hovered(widget) => Some(text.clone()),
+                        _ => None,
+                    }
+                })
+            })
+    }
+
+    fn render_tooltip(&self, ctx: &FormContext, ui: &mut Ui, font: &mut Font) {
+        let text = match self.tooltip(ctx) {
+            Some(text) if !text.is_empty() => text,
+            _ => return,
+        };
+
+        let pad = self.layout.geometry.font_size / 3.0;
+        let pos = (self.mouse.0 + pad * 4.0, self.mouse.1 + pad * 4.0);
+        let section = self.text(&text, (pos.0 + pad, pos.1 + pad), ui.screen_size(), WHITE);
+        let (w, h) = font.text_size(section.clone());
+
+        ui.fill(
+            Rect::new(pos.0, pos.1, w + pad * 2.0, h + pad * 2.0),
+            [0.0, 0.0, 0.0, 0.8],
+        );
+        ui.text(section);
+    }
+}
@@ -0,0 +1,403 @@
+use super::{
+    super::ui::Rect,
+    parse::{Element, FieldKind, Formspec, TooltipTarget},
+};
+use cgmath::Vector2;
+
+// upstream's default screen density, used for fixed size forms
+const SCREEN_DPI: f32 = 96.0;
+// font size relative to the size of an inventory slot
+const FONT_SIZE: f32 = 0.35;
+
+/// sizes in pixels that formspec units are converted with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geometry {
+    /// size of an inventory slot, one unit in real coordinates
+    pub imgsize: f32,
+    /// distance between legacy coordinates
+    pub spacing: Vector2<f32>,
+    /// space between the form's border and legacy elements
+    pub padding: Vector2<f32>,
+    /// half the height of legacy buttons and fields
+    pub btn_height: f32,
+    pub font_size: f32,
+    /// the form on screen
+    pub form: Rect,
+}
+
+impl Geometry {
+    pub fn new(spec: &Formspec, screen: (f32, f32), scale: f32) -> Self {
+        let invsize = spec.size.unwrap_or(Vector2::new(0.0, 0.0));
+        let padded = (
+            screen.0 * (1.0 - spec.padding.x * 2.0),
+            screen.1 * (1.0 - spec.padding.y * 2.0),
+        );
+
+        let imgsize = if spec.fixed_size {
+            0.5555 * SCREEN_DPI * scale
+        } else {
+            // as big as preferred, but small enough to fit the screen
+            let fit = if spec.size_real {
+                (padded.0 / invsize.x, padded.1 / invsize.y)
+            } else {
+                (
+                    padded.0 / (5.0 / 4.0 * (0.5 + invsize.x)),
+                    padded.1 / (15.0 / 13.0 * (0.85 + invsize.y)),
+                )
+            };
+
+            let prefer = padded.0.min(padded.1) / 15.0 * scale;
+            prefer.min(fit.0).min(fit.1)
+        };
+
+        let spacing = Vector2::new(imgsize * 5.0 / 4.0, imgsize * 15.0 / 13.0);
+        let padding = Vector2::new(imgsize * 3.0 / 8.0, imgsize * 3.0 / 8.0);
+        let btn_height = imgsize * 15.0 / 13.0 * 0.35;
+
+        let size = match spec.size {
+            None => (0.0, 0.0),
+            Some(_) if spec.size_real => (invsize.x * imgsize, invsize.y * imgsize),
+            Some(_) => (
+                padding.x * 2.0 + spacing.x * (invsize.x - 1.0) + imgsize,
+                padding.y * 2.0 + spacing.y * (invsize.y - 1.0) + imgsize + btn_height * 2.0 / 3.0,
+            ),
+        };
+
+        let x = spec.position.x * screen.0 - spec.anchor.x * size.0;
+        let y = spec.position.y * screen.1 - spec.anchor.y * size.1;
+
+        Self {
+            imgsize,
+            spacing,
+            padding,
+            btn_height,
+            font_size: (imgsize * FONT_SIZE).max(12.0 * scale).round(),
+            form: Rect::new(x.floor(), y.floor(), size.0.floor(), size.1.floor()),
+        }
+    }
+
+    /// top left corner of an element at pos inside containers offset by container
+    pub fn pos(&self, pos: Vector2<f32>, container: Vector2<f32>, real: bool) -> (f32, f32) {
+        let pos = pos + container;
+
+        if real {
+            (
+                self.form.x + pos.x * self.imgsize,
+                self.form.y + pos.y * self.imgsize,
+            )
+        } else {
+            (
+                self.form.x + self.padding.x + pos.x * self.spacing.x,
+                self.form.y + self.padding.y + pos.y * self.spacing.y,
+            )
+        }
+    }
+
+    /// distance between list slots
+    pub fn slot_step(&self, real: bool) -> Vector2<f32> {
+        if real {
+            Vector2::new(self.imgsize * 1.25, self.imgsize * 1.25)
+        } else {
+            self.spacing
+        }
+    }
+
+    /// size of an element given in spacing units in legacy coordinates
+    fn spaced(&self, size: Vector2<f32>, real: bool) -> (f32, f32) {
+        if real {
+            (size.x * self.imgsize, size.y * self.imgsize)
+        } else {
+            (size.x * self.spacing.x, size.y * self.spacing.y)
+        }
+    }
+
+    /// size of a legacy button like element, which leaves out the gap to the next one
+    fn button(&self, size: Vector2<f32>, real: bool) -> (f32, f32) {
+        let (w, h) = self.spaced(size, real);
+
+        if real {
+            (w, h)
+        } else {
+            (
+                w - (self.spacing.x - self.imgsize),
+                h - (self.spacing.y - self.imgsize),
+            )
+        }
+    }
+}
+
+/// an element placed on screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Widget {
+    /// index into the formspec's elements
+    pub element: usize,
+    pub rect: Rect,
+    /// the innermost scroll container the widget is in
+    pub scroll: Option<usize>,
+    /// whether the element uses real coordinates
+    pub real: bool,
+}
+
+/// a scroll_container, moving its widgets by the value of a scrollbar
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScrollArea {
+    pub rect: Rect,
+    pub scrollbar: String,
+    pub horizontal: bool,
+    /// pixels scrolled per scrollbar step
+    pub step: f32,
+    pub parent: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    pub geometry: Geometry,
+    pub widgets: Vec<Widget>,
+    pub scroll_areas: Vec<ScrollArea>,
+}
+
+impl Layout {
+    pub fn new(spec: &Formspec, screen: (f32, f32), scale: f32) -> Self {
+        let geom = Geometry::new(spec, screen, scale);
+        let mut widgets = Vec::new();
+        let mut scroll_areas: Vec<ScrollArea> = Vec::new();
+
+        let mut real = false;
+        // offsets of the containers the current element is in, and whether they scroll
+        let mut containers: Vec<(Vector2<f32>, bool)> = Vec::new();
+        let mut scroll: Vec<usize> = Vec::new();
+
+        for (i, element) in spec.elements.iter().enumerate() {
+            // elements inside scroll containers are relative to the container
+            let offset: Vector2<f32> = containers.iter().map(|(pos, _)| *pos).sum();
+            let pos = move |pos: Vector2<f32>| geom.pos(pos, offset, real);
+
+            let rect = match element {
+                Element::RealCoordinates(enabled) => {
+                    real = *enabled;
+                    None
+                }
+                Element::Container(pos) => {
+                    containers.push((*pos, false));
+                    None
+                }
+                Element::ScrollContainer {
+                    pos: p,
+                    size,
+                    scrollbar,
+                    horizontal,
+                    factor,
+                } => {
+                    let (x, y) = pos(*p);
+                    let (w, h) = geom.spaced(*size, real);
+
+                    scroll_areas.push(ScrollArea {
+                        rect: Rect::new(x, y, w, h),
+                        scrollbar: scrollbar.clone(),
+                        horizontal: *horizontal,
+                        step: factor * if real { geom.imgsize } else { geom.spacing.y },
+                        parent: scroll.last().copied(),
+                    });
+
+                    scroll.push(scroll_areas.len() - 1);
+                    containers.push((*p, true));
+                    None
+                }
+                Element::ContainerEnd | Element::ScrollContainerEnd => {
+                    match containers.pop() {
+                        Some((_, true)) => {
+                            scroll.pop();
+                        }
+                        Some((_, false)) => {}
+                        None => eprintln!("formspec container ends without a container"),
+                    }
+                    None
+                }
+                Element::List {
+                    pos: p, size: dim, ..
+                } => {
+                    let (x, y) = pos(*p);
+                    let step = geom.slot_step(real);
+                    let w = step.x * (dim.x - 1.0).max(0.0) + geom.imgsize;
+                    let h = step.y * (dim.y - 1.0).max(0.0) + geom.imgsize;
+
+                    Some(Rect::new(x, y, w, h))
+                }
+                Element::Image { pos: p, size, .. }
+                | Element::AnimatedImage { pos: p, size, .. }
+                | Element::ItemImage { pos: p, size, .. } => {
+                    let (x, y) = pos(*p);
+                    Some(Rect::new(
+                        x,
+                        y,
+                        size.x * geom.imgsize,
+                        size.y * geom.imgsize,
+                    ))
+                }
+                Element::Background {
+                    auto_clip: true, ..
+                } => Some(geom.form),
+                Element::Background { pos: p, size, .. } => {
+                    let (mut x, mut y) = pos(*p);
+                    let (w, h) = geom.spaced(*size, real);
+
+                    // legacy backgrounds cover the padding around the elements
+                    if !real {
+                        x -= (geom.spacing.x - geom.imgsize) / 2.0;
+                        y -= (geom.spacing.y - geom.imgsize) / 2.0;
+                    }
+
+                    Some(Rect::new(x, y, w, h))
+                }
+                Element::Box { pos: p, size, .. }
+                | Element::Hypertext { pos: p, size, .. }
+                | Element::Scrollbar { pos: p, size, .. }
+                | Element::TextList { pos: p, size, .. } => {
+                    let (x, y) = pos(*p);
+                    let (w, h) = geom.spaced(*size, real);
+                    Some(Rect::new(x, y, w, h))
+                }
+                Element::Tooltip {
+                    target: TooltipTarget::Area(p, size),
+                    ..
+                } => {
+                    let (x, y) = pos(*p);
+                    let (w, h) = geom.spaced(*size, real);
+                    Some(Rect::new(x, y, w, h))
+                }
+                Element::Label {
+                    pos: p,
+                    text,
+                    vertical,
+                } => {
+                    let (x, y) = pos(*p);
+                    let line = geom.font_size * 1.25;
+                    let lines = if *vertical {
+                        text.chars().count()
+                    } else {
+                        text.lines().count()
+                    };
+
+                    // legacy labels are centered in the slot row, real ones on their position
+                    let center = if real { y } else { y + geom.imgsize / 2.0 };
+                    let w = if *vertical { line } else { 0.0 };
+                    Some(Rect::new(
+                        x,
+                        center - line / 2.0,
+                        w,
+                        line * lines.max(1) as f32,
+                    ))
+                }
+                Element::Checkbox { pos: p, label, .. } => {
+                    let (x, y) = pos(*p);
+                    let line = geom.font_size * 1.25;
+                    let center = if real { y } else { y + geom.imgsize / 2.0 };
+
+                    // the label is clickable too, estimate its width
+                    let label = label.chars().count() as f32 * geom.font_size * 0.5;
+                    Some(Rect::new(x, center - line / 2.0, line * 1.5 + label, line))
+                }
+                Element::Field {
+                    rect: Some((p, size)),
+                    kind,
+                    ..
+                } => {
+                    let (x, mut y) = pos(*p);
+                    let (w, mut h) = geom.button(*size, real);
+
+                    if !real && *kind != FieldKind::TextArea {
+                        y += size.y * geom.imgsize / 2.0 - geom.btn_height;
+                        h = geom.btn_height * 2.0;
+                    }
+
+                    Some(Rect::new(x, y, w, h))
+                }
+                Element::Field { rect: None, .. } => {
+                    // fields without a position are centered in the form
+                    let w = (geom.form.w - geom.padding.x * 2.0).max(geom.imgsize * 4.0);
+                    let h = geom.btn_height * 2.0;
+                    Some(Rect::new(
+                        geom.form.x + (geom.form.w - w) / 2.0,
+                        geom.form.y + (geom.form.h - h) / 2.0,
+                        w,
+                        h,
+                    ))
+                }
+                Element::Button {
+                    pos: p,
+                    size,
+                    image,
+                    item,
+                    ..
+                } => {
+                    let (x, mut y) = pos(*p);
+                    let (w, mut h) = geom.button(*size, real);
+
+                    // legacy text buttons have a fixed height
+                    if !real && image.is_none() && item.is_none() {
+                        y += size.y * geom.imgsize / 2.0 - geom.btn_height;
+                        h = geom.btn_height * 2.0;
+                    }
+
+                    Some(Rect::new(x, y, w, h))
+                }
+                Element::Dropdown {
+                    pos: p,
+                    width,
+                    height,
+                    ..
+                } => {
+                    let (x, y) = pos(*p);
+                    let size = Vector2::new(*width, height.unwrap_or(1.0));
+                    let w = geom.button(size, real).0;
+                    let h = match height {
+                        Some(_) if real => size.y * geom.imgsize,
+                        _ => geom.btn_height * 2.0,
+                    };
+
+                    Some(Rect::new(x, y, w, h))
+                }
+                Element::TabHeader {
+                    pos: p,
+                    size,
+                    captions,
+                    ..
+                } => {
+                    let (x, y) = pos(*p);
+                    let h = match size {
+                        Some(size) if real => size.y * geom.imgsize,
+                        _ => geom.btn_height * 2.0,
+                    };
+                    let w = match size {
+                        Some(size) if real && size.x > 0.0 => size.x * geom.imgsize,
+                        // tabs get a width that fits most captions
+                        _ => captions.len() as f32 * geom.imgsize * 2.0,
+                    };
+
+                    // tabs sit on top of their position
+                    Some(Rect::new(x, y - h, w, h))
+                }
+                Element::ListRing { .. }
+                | Element::ListColors { .. }
+                | Element::Tooltip { .. }
+                | Element::FieldCloseOnEnter { .. }
+                | Element::SetFocus(_) => None,
+            };
+
+            if let Some(rect) = rect {
+                widgets.push(Widget {
+                    element: i,
+                    rect,
+                    scroll: scroll.last().copied(),
+                    real,
+                });
+            }
+        }
+
+        Self {
+            geometry: geom,
+            widgets,
+            scroll_areas,
+        }
+    }
+}
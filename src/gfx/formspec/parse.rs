@@ -0,0 +1,1158 @@
+use crate::inventory::InvLocation;
+use cgmath::Vector2;
+
+// newest formspec version this parser knows about
+pub const MAX_VERSION: u32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Field,
+    Password,
+    TextArea,
+}
+
+/// limits of a scrollbar, set by scrollbaroptions for the scrollbars after it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollbarOptions {
+    pub min: i32,
+    pub max: i32,
+    pub small_step: i32,
+    pub large_step: i32,
+    pub thumb_size: i32,
+    pub arrows: bool,
+}
+
+impl Default for ScrollbarOptions {
+    fn default() -> Self {
+        Self {
+            min: 0,
+            max: 1000,
+            small_step: 10,
+            large_step: 100,
+            thumb_size: 10,
+            arrows: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TooltipTarget {
+    Name(String),
+    Area(Vector2<f32>, Vector2<f32>),
+}
+
+/// a formspec element, positions and sizes are in formspec units
+#[derive(Debug, Clone, PartialEq)]
+pub enum Element {
+    RealCoordinates(bool),
+    Container(Vector2<f32>),
+    ContainerEnd,
+    ScrollContainer {
+        pos: Vector2<f32>,
+        size: Vector2<f32>,
+        scrollbar: String,
+        horizontal: bool,
+        /// units scrolled per scrollbar step
+        factor: f32,
+    },
+    ScrollContainerEnd,
+    List {
+        location: InvLocation,
+        list: String,
+        pos: Vector2<f32>,
+        /// columns and rows
+        size: Vector2<f32>,
+        start: usize,
+    },
+    ListRing {
+        location: InvLocation,
+        list: String,
+    },
+    ListColors {
+        normal: [f32; 4],
+        hover: [f32; 4],
+        border: Option<[f32; 4]>,
+    },
+    Tooltip {
+        target: TooltipTarget,
+        text: String,
+    },
+    Image {
+        pos: Vector2<f32>,
+        size: Vector2<f32>,
+        texture: String,
+        /// borders of a nine-slice image in texture pixels
+        middle: Option<[f32; 4]>,
+    },
+    AnimatedImage {
+        pos: Vector2<f32>,
+        size: Vector2<f32>,
+        texture: String,
+        frames: u32,
+        /// milliseconds each frame is shown
+        duration: u32,
+        /// first frame shown, counting from 1
+        start: u32,
+    },
+    ItemImage {
+        pos: Vector2<f32>,
+        size: Vector2<f32>,
+        item: String,
+    },
+    Background {
+        pos: Vector2<f32>,
+        size: Vector2<f32>,
+        texture: String,
+        /// cover the whole form instead
+        auto_clip: bool,
+        middle: Option<[f32; 4]>,
+    },
+    Label {
+        pos: Vector2<f32>,
+        text: String,
+        vertical: bool,
+    },
+    Hypertext {
+        pos: Vector2<f32>,
+        size: Vector2<f32>,
+        text: String,
+    },
+    Field {
+        /// position and size, None for fields centered in the form
+        rect: Option<(Vector2<f32>, Vector2<f32>)>,
+        kind: FieldKind,
+        name: String,
+        label: String,
+        default: String,
+    },
+    FieldCloseOnEnter {
+        name: String,
+        close: bool,
+    },
+    Button {
+        pos: Vector2<f32>,
+        size: Vector2<f32>,
+        name: String,
+        label: String,
+        exit: bool,
+        image: Option<String>,
+        pressed_image: Option<String>,
+        item: Option<String>,
+        border: bool,
+    },
+    TextList {
+        pos: Vector2<f32>,
+        size: Vector2<f32>,
+        name: String,
+        items: Vec<String>,
+        /// counting from 1, 0 if nothing is selected
+        selected: usize,
+        transparent: bool,
+    },
+    TabHeader {
+        pos: Vector2<f32>,
+        /// width and height, a zero width picks one for each tab
+        size: Option<Vector2<f32>>,
+        name: String,
+        captions: Vec<String>,
+        /// counting from 1
+        current: usize,
+        transparent: bool,
+        border: bool,
+    },
+    Box {
+        pos: Vector2<f32>,
+        size: Vector2<f32>,
+        color: [f32; 4],
+    },
+    Dropdown {
+        pos: Vector2<f32>,
+        /// the height is None in the legacy syntax
+        width: f32,
+        height: Option<f32>,
+        name: String,
+        items: Vec<String>,
+        /// counting from 1
+        selected: usize,
+        /// send the selected index instead of the item
+        index_event: bool,
+    },
+    Checkbox {
+        pos: Vector2<f32>,
+        name: String,
+        label: String,
+        selected: bool,
+    },
+    Scrollbar {
+        pos: Vector2<f32>,
+        size: Vector2<f32>,
+        horizontal: bool,
+        name: String,
+        value: i32,
+        options: ScrollbarOptions,
+    },
+    SetFocus(String),
+}
+
+/// how the background behind and around the form is drawn
+#[derive(Debug, Clone, PartialEq)]
+pub struct BgColor {
+    pub color: Option<[f32; 4]>,
+    pub form: bool,
+    pub fullscreen: bool,
+    pub fullscreen_color: Option<[f32; 4]>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formspec {
+    pub version: u32,
+    pub size: Option<Vector2<f32>>,
+    /// whether size was given in real coordinates
+    pub size_real: bool,
+    pub fixed_size: bool,
+    /// where the form's anchor is on screen, relative to the screen size
+    pub position: Vector2<f32>,
+    /// point of the form placed at position, relative to the form size
+    pub anchor: Vector2<f32>,
+    /// minimum space around the form, relative to the screen size
+    pub padding: Vector2<f32>,
+    pub no_prepend: bool,
+    pub bgcolor: Option<BgColor>,
+    pub elements: Vec<Element>,
+}
+
+impl Default for Formspec {
+    fn default() -> Self {
+        Self {
+            version: 1,
+            size: None,
+            size_real: false,
+            fixed_size: false,
+            position: Vector2::new(0.5, 0.5),
+            anchor: Vector2::new(0.5, 0.5),
+            padding: Vector2::new(0.05, 0.05),
+            no_prepend: false,
+            bgcolor: None,
+            elements: Vec::new(),
+        }
+    }
+}
+
+impl Formspec {
+    /// inserts the elements of the server's formspec prepend before this formspec's
+    pub fn prepend(&mut self, prepend: &Formspec) {
+        if self.no_prepend {
+            return;
+        }
+
+        self.elements.splice(0..0, prepend.elements.iter().cloned());
+
+        if self.bgcolor.is_none() {
+            self.bgcolor = prepend.bgcolor.clone();
+        }
+    }
+}
+
+/// splits s at sep, except where sep is escaped with a backslash
+pub fn split_escaped(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == sep {
+            parts.push(&s[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+
+    parts.push(&s[start..]);
+    parts
+}
+
+/// removes the backslashes escaping formspec syntax
+pub fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            out.extend(chars.next());
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+/// splits a formspec into the names and parameters of its elements
+pub fn elements(formspec: &str) -> Vec<(&str, &str)> {
+    let mut elements = Vec::new();
+    let mut start = 0;
+    let mut open = None;
+    let mut escaped = false;
+
+    for (i, c) in formspec.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+
+        match c {
+            '\\' => escaped = true,
+            '[' if open.is_none() => open = Some(i),
+            ']' => {
+                if let Some(open) = open.take() {
+                    let name = formspec[start..open].trim();
+                    elements.push((name, &formspec[open + 1..i]));
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    elements
+}
+
+/// a color in upstream's ColorString format, like #RGB, #RRGGBBAA or a name
+pub fn parse_color(s: &str) -> Option<[f32; 4]> {
+    let s = s.trim();
+
+    if let Some(hex) = s.strip_prefix('#') {
+        let digits = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as f32))
+            .collect::<Option<Vec<_>>>()?;
+
+        let short = |d: f32| d * 17.0 / 255.0;
+        let long = |hi: f32, lo: f32| (hi * 16.0 + lo) / 255.0;
+
+        return match digits[..] {
+            [r, g, b] => Some([short(r), short(g), short(b), 1.0]),
+            [r, g, b, a] => Some([short(r), short(g), short(b), short(a)]),
+            [r1, r2, g1, g2, b1, b2] => Some([long(r1, r2), long(g1, g2), long(b1, b2), 1.0]),
+            [r1, r2, g1, g2, b1, b2, a1, a2] => {
+                Some([long(r1, r2), long(g1, g2), long(b1, b2), long(a1, a2)])
+            }
+            _ => None,
+        };
+    }
+
+    // optionally followed by an alpha like red#80
+    let (name, alpha) = match s.split_once('#') {
+        Some((name, alpha)) => (name, u8::from_str_radix(alpha, 16).ok()? as f32 / 255.0),
+        None => (s, 1.0),
+    };
+
+    let [r, g, b] = match name.to_ascii_lowercase().as_str() {
+        "transparent" => return Some([0.0; 4]),
+        "black" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "green" => [0, 128, 0],
+        "lime" => [0, 255, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" | "aqua" => [0, 255, 255],
+        "magenta" | "fuchsia" => [255, 0, 255],
+        "gray" | "grey" => [128, 128, 128],
+        "darkgray" | "darkgrey" => [169, 169, 169],
+        "lightgray" | "lightgrey" => [211, 211, 211],
+        "silver" => [192, 192, 192],
+        "orange" => [255, 165, 0],
+        "brown" => [165, 42, 42],
+        "purple" => [128, 0, 128],
+        "pink" => [255, 192, 203],
+        "maroon" => [128, 0, 0],
+        "navy" => [0, 0, 128],
+        "olive" => [128, 128, 0],
+        "teal" => [0, 128, 128],
+        "gold" => [255, 215, 0],
+        _ => return None,
+    }
+    .map(|c: u8| c as f32 / 255.0);
+
+    Some([r, g, b, alpha])
+}
+
+/// upstream's is_yes, also accepting nonzero numbers
+fn boolean(s: &str) -> bool {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" => true,
+        s => s.parse::<f32>().map_or(false, |n| n != 0.0),
+    }
+}
+
+fn number(s: &str) -> Result<f32, String> {
+    s.trim().parse().map_err(|_| format!("invalid number: {s}"))
+}
+
+fn vector(s: &str) -> Result<Vector2<f32>, String> {
+    match split_escaped(s, ',')[..] {
+        [x, y] => Ok(Vector2::new(number(x)?, number(y)?)),
+        _ => Err(format!("invalid vector: {s}")),
+    }
+}
+
+fn list(s: &str) -> Vec<String> {
+    if s.is_empty() {
+        return Vec::new();
+    }
+
+    split_escaped(s, ',').into_iter().map(unescape).collect()
+}
+
+/// borders of a nine-slice image: one value for all sides, x,y or x,y,x2,y2 where
+/// negative x2 and y2 count from the right and bottom like upstream's middle rect
+fn middle(s: &str) -> Result<[f32; 4], String> {
+    let values = split_escaped(s, ',')
+        .into_iter()
+        .map(number)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match values[..] {
+        [a] => Ok([a; 4]),
+        [x, y] => Ok([x, y, x, y]),
+        [x, y, x2, y2] => Ok([x, y, -x2, -y2]),
+        _ => Err(format!("invalid middle: {s}")),
+    }
+}
+
+fn optional<'a>(params: &[&'a str], index: usize) -> Option<&'a str> {
+    params.get(index).copied().filter(|s| !s.is_empty())
+}
+
+/// state carried between elements while parsing
+struct Parser {
+    /// what "context" refers to, the node a form was opened for or the current player
+    context: InvLocation,
+    scrollbar_options: ScrollbarOptions,
+    /// number of cells per row of the next table
+    table_columns: Option<usize>,
+    /// the last two lists, for listring[] without parameters
+    lists: Vec<(InvLocation, String)>,
+}
+
+impl Parser {
+    fn inv_location(&self, s: &str) -> Result<InvLocation, String> {
+        match unescape(s).as_str() {
+            "context" => Ok(self.context.clone()),
+            s => s.parse(),
+        }
+    }
+
+    fn element(&mut self, name: &str, p: &[&str], spec: &mut Formspec) -> Result<(), String> {
+        let text = |i: usize| p.get(i).map_or(String::new(), |s| unescape(s));
+        let count = p.len();
+        let mut push = |element| spec.elements.push(element);
+
+        match (name, count) {
+            ("formspec_version", 1) => {
+                spec.version = number(p[0])? as u32;
+                if spec.version > MAX_VERSION {
+                    eprintln!("formspec version {} is not fully supported", spec.version);
+                }
+            }
+            ("size", 1 | 2) => {
+                spec.size = Some(vector(p[0])?);
+                spec.size_real = spec.version >= 2
+                    || spec
+                        .elements
+                        .iter()
+                        .rev()
+                        .find_map(|element| match element {
+                            Element::RealCoordinates(real) => Some(*real),
+                            _ => None,
+                        })
+                        .unwrap_or(false);
+                spec.fixed_size = p.get(1).map_or(false, |s| boolean(s));
+            }
+            ("position", 1) => spec.position = vector(p[0])?,
+            ("anchor", 1) => spec.anchor = vector(p[0])?,
+            ("padding", 1) => spec.padding = vector(p[0])?,
+            ("no_prepend", _) => spec.no_prepend = true,
+            ("real_coordinates", 1) => push(Element::RealCoordinates(boolean(p[0]))),
+            ("container", 1) => push(Element::Container(vector(p[0])?)),
+            ("container_end", _) => push(Element::ContainerEnd),
+            ("scroll_container", 3..) => push(Element::ScrollContainer {
+                pos: vector(p[0])?,
+                size: vector(p[1])?,
+                scrollbar: text(2),
+                horizontal: optional(p, 3) == Some("horizontal"),
+                factor: optional(p, 4).map_or(Ok(0.1), number)?,
+            }),
+            ("scroll_container_end", _) => push(Element::ScrollContainerEnd),
+            ("list", 4 | 5) => {
+                let (location, list) = (self.inv_location(p[0])?, text(1));
+
+                self.lists.push((location.clone(), list.clone()));
+                if self.lists.len() > 2 {
+                    self.lists.remove(0);
+                }
+
+                push(Element::List {
+                    location,
+                    list,
+                    pos: vector(p[2])?,
+                    size: vector(p[3])?,
+                    start: optional(p, 4).map_or(Ok(0.0), number)?.max(0.0) as usize,
+                });
+            }
+            ("listring", 2) => push(Element::ListRing {
+                location: self.inv_location(p[0])?,
+                list: text(1),
+            }),
+            ("listring", _) => {
+                // shorthand for rings of the last two lists
+                for (location, list) in self.lists.clone() {
+                    push(Element::ListRing { location, list });
+                }
+            }
+            ("listcolors", 2..) => push(Element::ListColors {
+                normal: parse_color(p[0]).ok_or("invalid color")?,
+                hover: parse_color(p[1]).ok_or("invalid color")?,
+                border: optional(p, 2).and_then(parse_color),
+            }),
+            ("tooltip", 2..=4) if !p[0].contains(',') => push(Element::Tooltip {
+                target: TooltipTarget::Name(text(0)),
+                text: text(1),
+            }),
+            ("tooltip", 3..) => push(Element::Tooltip {
+                target: TooltipTarget::Area(vector(p[0])?, vector(p[1])?),
+                text: text(2),
+            }),
+            ("image", 3 | 4) => push(Element::Image {
+                pos: vector(p[0])?,
+                size: vector(p[1])?,
+                texture: text(2),
+                middle: optional(p, 3).map(middle).transpose()?,
+            }),
+            // legacy images without size keep the texture's size
+            ("image", 2) => push(Element::Image {
+                pos: vector(p[0])?,
+                size: Vector2::new(1.0, 1.0),
+                texture: text(1),
+                middle: None,
+            }),
+            ("animated_image", 6..) => push(Element::AnimatedImage {
+                pos: vector(p[0])?,
+                size: vector(p[1])?,
+                texture: text(3),
+                frames: (number(p[4])? as u32).max(1),
+                duration: (number(p[5])? as u32).max(1),
+                start: optional(p, 6).map_or(Ok(1.0), number)? as u32,
+            }),
+            ("item_image", 3) => push(Element::ItemImage {
+                pos: vector(p[0])?,
+                size: vector(p[1])?,
+                item: text(2),
+            }),
+            ("background" | "background9", 3..) => push(Element::Background {
+                pos: vector(p[0])?,
+                size: vector(p[1])?,
+                texture: text(2),
+                auto_clip: optional(p, 3).map_or(false, boolean),
+                middle: match name {
+                    "background9" => Some(middle(p.get(4).ok_or("missing middle")?)?),
+                    _ => None,
+                },
+            }),
+            ("bgcolor", 1..) => {
+                // version 1 only had a flag for the fullscreen background
+                let (form, fullscreen) = match optional(p, 1) {
+                    Some("both") => (true, true),
+                    Some("neither") => (false, false),
+                    Some(s) if spec.version < 3 && boolean(s) => (false, true),
+                    Some(s) => (true, boolean(s)),
+                    None => (true, false),
+                };
+
+                spec.bgcolor = Some(BgColor {
+                    color: parse_color(p[0]),
+                    form,
+                    fullscreen,
+                    fullscreen_color: optional(p, 2)
+                        .and_then(parse_color)
+                        .or_else(|| (spec.version < 3).then(|| parse_color(p[0])).flatten()),
+                });
+            }
+            ("label" | "vertlabel", 2) => push(Element::Label {
+                pos: vector(p[0])?,
+                text: text(1),
+                vertical: name == "vertlabel",
+            }),
+            ("hypertext", 5) => push(Element::Hypertext {
+                pos: vector(p[0])?,
+                size: vector(p[1])?,
+                text: text(4),
+            }),
+            ("field", 3) => push(Element::Field {
+                rect: None,
+                kind: FieldKind::Field,
+                name: text(0),
+                label: text(1),
+                default: text(2),
+            }),
+            ("field" | "textarea", 5) | ("pwdfield", 4) => push(Element::Field {
+                rect: Some((vector(p[0])?, vector(p[1])?)),
+                kind: match name {
+                    "field" => FieldKind::Field,
+                    "pwdfield" => FieldKind::Password,
+                    _ => FieldKind::TextArea,
+                },
+                name: text(2),
+                label: text(3),
+                default: text(4),
+            }),
+            ("field_close_on_enter", 2) => push(Element::FieldCloseOnEnter {
+                name: text(0),
+                close: boolean(p[1]),
+            }),
+            ("button" | "button_exit", 4) => push(Element::Button {
+                pos: vector(p[0])?,
+                size: vector(p[1])?,
+                name: text(2),
+                label: text(3),
+                exit: name == "button_exit",
+                image: None,
+                pressed_image: None,
+                item: None,
+                border: true,
+            }),
+            ("image_button" | "image_button_exit", 5 | 8) => push(Element::Button {
+                pos: vector(p[0])?,
+                size: vector(p[1])?,
+                name: text(3),
+                label: text(4),
+                exit: name == "image_button_exit",
+                image: Some(text(2)),
+                pressed_image: optional(p, 7).map(unescape),
+                item: None,
+                border: optional(p, 6).map_or(true, boolean),
+            }),
+            ("item_image_button", 5) => push(Element::Button {
+                pos: vector(p[0])?,
+                size: vector(p[1])?,
+                name: text(3),
+                label: text(4),
+                exit: false,
+                image: None,
+                pressed_image: None,
+                item: Some(text(2)),
+                border: true,
+            }),
+            ("textlist", 4..) => push(Element::TextList {
+                pos: vector(p[0])?,
+                size: vector(p[1])?,
+                name: text(2),
+                items: list(p[3]),
+                selected: optional(p, 4).map_or(Ok(0.0), number)?.max(0.0) as usize,
+                transparent: optional(p, 5).map_or(false, boolean),
+            }),
+            ("tablecolumns", _) => self.table_columns = Some(count.max(1)),
+            // tables are shown as text lists with one row per line
+            ("table", 4..) => {
+                let cells = list(p[3]);
+                let items = match self.table_columns.take() {
+                    Some(columns) => cells.chunks(columns).map(|row| row.join("  ")).collect(),
+                    None => cells,
+                };
+
+                push(Element::TextList {
+                    pos: vector(p[0])?,
+                    size: vector(p[1])?,
+                    name: text(2),
+                    items,
+                    selected: optional(p, 4).map_or(Ok(0.0), number)?.max(0.0) as usize,
+                    transparent: false,
+                });
+            }
+            ("tabheader", 4..=7) => {
+                // the position is followed by an optional height or size
+                let (size, rest) = match vector(p[1]) {
+                    Ok(size) => (Some(size), &p[2..]),
+                    Err(_) => match (number(p[1]), p.len()) {
+                        (Ok(h), 5..) => (Some(Vector2::new(0.0, h)), &p[2..]),
+                        _ => (None, &p[1..]),
+                    },
+                };
+
+                if rest.len() < 3 {
+                    return Err("not enough parameters".into());
+                }
+
+                push(Element::TabHeader {
+                    pos: vector(p[0])?,
+                    size,
+                    name: unescape(rest[0]),
+                    captions: list(rest[1]),
+                    current: number(rest[2])?.max(1.0) as usize,
+                    transparent: optional(rest, 3).map_or(false, boolean),
+                    border: optional(rest, 4).map_or(true, boolean),
+                });
+            }
+            ("box", 3) => push(Element::Box {
+                pos: vector(p[0])?,
+                size: vector(p[1])?,
+                color: parse_color(p[2]).unwrap_or([1.0; 4]),
+            }),
+            ("dropdown", 5 | 6) => {
+                let (width, height) = match vector(p[1]) {
+                    Ok(size) => (size.x, Some(size.y)),
+                    Err(_) => (number(p[1])?, None),
+                };
+
+                push(Element::Dropdown {
+                    pos: vector(p[0])?,
+                    width,
+                    height,
+                    name: text(2),
+                    items: list(p[3]),
+                    selected: number(p[4])?.max(1.0) as usize,
+                    index_event: optional(p, 5).map_or(false, boolean),
+                });
+            }
+            ("checkbox", 3 | 4) => push(Element::Checkbox {
+                pos: vector(p[0])?,
+                name: text(1),
+                label: text(2),
+                selected: optional(p, 3).map_or(false, boolean),
+            }),
+            ("scrollbar", 5) => push(Element::Scrollbar {
+                pos: vector(p[0])?,
+                size: vector(p[1])?,
+                horizontal: p[2] == "horizontal",
+                name: text(3),
+                value: number(p[4])? as i32,
+                options: self.scrollbar_options,
+            }),
+            ("scrollbaroptions", _) => {
+                let opts = &mut self.scrollbar_options;
+
+                for option in p {
+                    let (key, value) = option.split_once('=').unwrap_or((option, ""));
+                    let int = || value.trim().parse::<i32>();
+
+                    match (key.trim(), int()) {
+                        ("min", Ok(v)) => opts.min = v,
+                        ("max", Ok(v)) => opts.max = v,
+                        ("smallstep", Ok(v)) => opts.small_step = v,
+                        ("largestep", Ok(v)) => opts.large_step = v,
+                        ("thumbsize", Ok(v)) => opts.thumb_size = v,
+                        ("arrows", _) => opts.arrows = value.trim() != "hide",
+                        _ => eprintln!("invalid scrollbar option: {option}"),
+                    }
+                }
+            }
+            ("set_focus", 1 | 2) => push(Element::SetFocus(text(0))),
+            // styling, models and table details aren't supported
+            ("style" | "style_type" | "model" | "tableoptions", _) => {}
+            (name, _) => return Err(format!("unknown element or invalid parameters: {name}")),
+        }
+
+        Ok(())
+    }
+}
+
+/// parses a formspec, invalid elements are skipped
+pub fn parse(formspec: &str, context: &InvLocation) -> Formspec {
+    let mut spec = Formspec::default();
+    let mut parser = Parser {
+        context: context.clone(),
+        scrollbar_options: ScrollbarOptions::default(),
+        table_columns: None,
+        lists: Vec::new(),
+    };
+
+    for (name, params) in elements(formspec) {
+        let params = split_escaped(params, ';');
+
+        if let Err(e) = parser.element(name, &params, &mut spec) {
+            eprintln!("invalid formspec element {name}[{}]: {e}", params.join(";"));
+        }
+    }
+
+    // formspec version 2 and newer always use real coordinates
+    if spec.version >= 2 {
+        spec.elements.insert(0, Element::RealCoordinates(true));
+    }
+
+    spec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Point3;
+
+    // minetest_game's chest, furnace and creative inventory forms
+    const CHEST: &str = "size[8,9]\
+        bgcolor[#080808BB;true]\
+        background[5,5;1,1;gui_formbg.png;true]\
+        listcolors[#00000069;#5A5A5A;#141318;#30434C;#FFF]\
+        list[nodemeta:0,5,-3;main;0,0.3;8,4;]\
+        list[current_player;main;0,4.85;8,1;]\
+        list[current_player;main;0,6.08;8,3;8]\
+        listring[nodemeta:0,5,-3;main]\
+        listring[current_player;main]";
+
+    const FURNACE: &str = "size[8,8.5]\
+        list[context;src;2.75,0.5;1,1;]\
+        list[context;fuel;2.75,2.5;1,1;]\
+        image[2.75,1.5;1,1;default_furnace_fire_bg.png]\
+        image[3.75,1.5;1,1;gui_furnace_arrow_bg.png^[transformR270]\
+        list[context;dst;4.75,0.96;2,2;]\
+        list[current_player;main;0,4.25;8,1;]\
+        list[current_player;main;0,5.5;8,3;8]\
+        listring[context;dst]\
+        listring[current_player;main]\
+        listring[context;src]\
+        listring[current_player;main]\
+        listring[context;fuel]\
+        listring[current_player;main]";
+
+    const CREATIVE: &str = "size[8,9.3]\
+        list[current_player;main;0,5.3;8,1;]\
+        list[current_player;main;0,6.55;8,3;8]\
+        list[detached:creative_sam;main;0,0;8,4;32]\
+        listring[current_player;main]\
+        listring[detached:creative_sam;main]\
+        image_button[0,4.05;0.8,0.8;creative_prev_icon.png;creative_prev;]\
+        image_button[7.2,4.05;0.8,0.8;creative_next_icon.png;creative_next;]\
+        label[5.8,4.15;2 / 5]\
+        field_close_on_enter[creative_filter;false]\
+        field[0.3,4.2;2.8,1.2;creative_filter;;wood]\
+        tooltip[creative_search;Search]\
+        image_button[2.63,4.05;0.8,0.8;creative_search_icon.png;creative_search;]\
+        tabheader[0,0;creative_tabs;All,Nodes,Tools,Items;2;false;false]";
+
+    fn rgba(r: u8, g: u8, b: u8, a: u8) -> [f32; 4] {
+        [r, g, b, a].map(|c| c as f32 / 255.0)
+    }
+
+    fn inv_list(
+        location: InvLocation,
+        list: &str,
+        pos: [f32; 2],
+        size: [f32; 2],
+        start: usize,
+    ) -> Element {
+        Element::List {
+            location,
+            list: list.into(),
+            pos: pos.into(),
+            size: size.into(),
+            start,
+        }
+    }
+
+    fn ring(location: InvLocation, list: &str) -> Element {
+        Element::ListRing {
+            location,
+            list: list.into(),
+        }
+    }
+
+    #[test]
+    fn chest() {
+        let spec = parse(CHEST, &InvLocation::CurrentPlayer);
+        let chest = InvLocation::NodeMeta(Point3::new(0, 5, -3));
+
+        assert_eq!(spec.version, 1);
+        assert_eq!(spec.size, Some(Vector2::new(8.0, 9.0)));
+        assert!(!spec.size_real);
+        assert!(!spec.fixed_size);
+
+        // version 1 only knows about the fullscreen background
+        let color = Some(rgba(0x08, 0x08, 0x08, 0xbb));
+        assert_eq!(
+            spec.bgcolor,
+            Some(BgColor {
+                color,
+                form: false,
+                fullscreen: true,
+                fullscreen_color: color,
+            })
+        );
+
+        assert_eq!(
+            spec.elements,
+            vec![
+                Element::Background {
+                    pos: Vector2::new(5.0, 5.0),
+                    size: Vector2::new(1.0, 1.0),
+                    texture: "gui_formbg.png".into(),
+                    auto_clip: true,
+                    middle: None,
+                },
+                Element::ListColors {
+                    normal: rgba(0, 0, 0, 0x69),
+                    hover: rgba(0x5a, 0x5a, 0x5a, 0xff),
+                    border: Some(rgba(0x14, 0x13, 0x18, 0xff)),
+                },
+                inv_list(chest.clone(), "main", [0.0, 0.3], [8.0, 4.0], 0),
+                inv_list(
+                    InvLocation::CurrentPlayer,
+                    "main",
+                    [0.0, 4.85],
+                    [8.0, 1.0],
+                    0
+                ),
+                inv_list(
+                    InvLocation::CurrentPlayer,
+                    "main",
+                    [0.0, 6.08],
+                    [8.0, 3.0],
+                    8
+                ),
+                ring(chest, "main"),
+                ring(InvLocation::CurrentPlayer, "main"),
+            ]
+        );
+    }
+
+    #[test]
+    fn furnace_context() {
+        let furnace = InvLocation::NodeMeta(Point3::new(10, -2, 7));
+        let spec = parse(FURNACE, &furnace);
+
+        assert_eq!(spec.elements.len(), 13);
+        assert_eq!(
+            spec.elements[0],
+            inv_list(furnace.clone(), "src", [2.75, 0.5], [1.0, 1.0], 0)
+        );
+        assert_eq!(
+            spec.elements[4],
+            inv_list(furnace.clone(), "dst", [4.75, 0.96], [2.0, 2.0], 0)
+        );
+        assert_eq!(spec.elements[7], ring(furnace.clone(), "dst"));
+        assert_eq!(spec.elements[11], ring(furnace, "fuel"));
+
+        // brackets of texture modifiers don't end the element
+        assert_eq!(
+            spec.elements[3],
+            Element::Image {
+                pos: Vector2::new(3.75, 1.5),
+                size: Vector2::new(1.0, 1.0),
+                texture: "gui_furnace_arrow_bg.png^[transformR270".into(),
+                middle: None,
+            }
+        );
+
+        // forms that aren't shown for a node refer to the player's inventory
+        let spec = parse(FURNACE, &InvLocation::CurrentPlayer);
+        assert_eq!(
+            spec.elements[1],
+            inv_list(
+                InvLocation::CurrentPlayer,
+                "fuel",
+                [2.75, 2.5],
+                [1.0, 1.0],
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn creative() {
+        let spec = parse(CREATIVE, &InvLocation::CurrentPlayer);
+        let creative = InvLocation::Detached("creative_sam".into());
+
+        assert_eq!(spec.elements.len(), 13);
+        assert_eq!(
+            spec.elements[2],
+            inv_list(creative.clone(), "main", [0.0, 0.0], [8.0, 4.0], 32)
+        );
+        assert_eq!(spec.elements[4], ring(creative, "main"));
+        assert_eq!(
+            spec.elements[5],
+            Element::Button {
+                pos: Vector2::new(0.0, 4.05),
+                size: Vector2::new(0.8, 0.8),
+                name: "creative_prev".into(),
+                label: String::new(),
+                exit: false,
+                image: Some("creative_prev_icon.png".into()),
+                pressed_image: None,
+                item: None,
+                border: true,
+            }
+        );
+        assert_eq!(
+            spec.elements[7],
+            Element::Label {
+                pos: Vector2::new(5.8, 4.15),
+                text: "2 / 5".into(),
+                vertical: false,
+            }
+        );
+        assert_eq!(
+            spec.elements[8],
+            Element::FieldCloseOnEnter {
+                name: "creative_filter".into(),
+                close: false,
+            }
+        );
+        assert_eq!(
+            spec.elements[9],
+            Element::Field {
+                rect: Some((Vector2::new(0.3, 4.2), Vector2::new(2.8, 1.2))),
+                kind: FieldKind::Field,
+                name: "creative_filter".into(),
+                label: String::new(),
+                default: "wood".into(),
+            }
+        );
+        assert_eq!(
+            spec.elements[10],
+            Element::Tooltip {
+                target: TooltipTarget::Name("creative_search".into()),
+                text: "Search".into(),
+            }
+        );
+        assert_eq!(
+            spec.elements[12],
+            Element::TabHeader {
+                pos: Vector2::new(0.0, 0.0),
+                size: None,
+                name: "creative_tabs".into(),
+                captions: vec!["All".into(), "Nodes".into(), "Tools".into(), "Items".into()],
+                current: 2,
+                transparent: false,
+                border: false,
+            }
+        );
+    }
+
+    #[test]
+    fn real_coordinates() {
+        let spec = parse(
+            "formspec_version[4]\
+            size[10.75,11]\
+            list[current_player;craft;2.5,0.5;3,3;]\
+            list[current_player;craftpreview;7,1.75;1,1;]\
+            list[current_player;main;0.5,5.75;8,4;]\
+            listring[]",
+            &InvLocation::CurrentPlayer,
+        );
+
+        assert_eq!(spec.version, 4);
+        assert_eq!(spec.size, Some(Vector2::new(10.75, 11.0)));
+        assert!(spec.size_real);
+        assert_eq!(spec.elements.len(), 6);
+        assert_eq!(spec.elements[0], Element::RealCoordinates(true));
+
+        // listring[] connects the last two lists
+        assert_eq!(
+            spec.elements[4..],
+            [
+                ring(InvLocation::CurrentPlayer, "craftpreview"),
+                ring(InvLocation::CurrentPlayer, "main"),
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        let spec = parse(
+            r"label[0,0;Hello\, world\; \[x\] \\o/]
+            textlist[0,1;4,3;items;one\,two,three\;four,\]five;2;true]
+            dropdown[1,5;3;choice;a\,b,c;1]",
+            &InvLocation::CurrentPlayer,
+        );
+
+        assert_eq!(
+            spec.elements,
+            vec![
+                Element::Label {
+                    pos: Vector2::new(0.0, 0.0),
+                    text: r"Hello, world; [x] \o/".into(),
+                    vertical: false,
+                },
+                Element::TextList {
+                    pos: Vector2::new(0.0, 1.0),
+                    size: Vector2::new(4.0, 3.0),
+                    name: "items".into(),
+                    items: vec!["one,two".into(), "three;four".into(), "]five".into()],
+                    selected: 2,
+                    transparent: true,
+                },
+                Element::Dropdown {
+                    pos: Vector2::new(1.0, 5.0),
+                    width: 3.0,
+                    height: None,
+                    name: "choice".into(),
+                    items: vec!["a,b".into(), "c".into()],
+                    selected: 1,
+                    index_event: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#f0a"), Some(rgba(0xff, 0x00, 0xaa, 0xff)));
+        assert_eq!(parse_color("#f0a8"), Some(rgba(0xff, 0x00, 0xaa, 0x88)));
+        assert_eq!(parse_color("#12ab34"), Some(rgba(0x12, 0xab, 0x34, 0xff)));
+        assert_eq!(parse_color("#FF000080"), Some(rgba(0xff, 0, 0, 0x80)));
+
+        assert_eq!(parse_color("red"), Some(rgba(255, 0, 0, 255)));
+        assert_eq!(parse_color("Gray"), Some(rgba(128, 128, 128, 255)));
+        assert_eq!(parse_color("red#80"), Some(rgba(255, 0, 0, 0x80)));
+        assert_eq!(parse_color("transparent"), Some([0.0; 4]));
+
+        for invalid in ["", "#12345", "#ggg", "notacolor", "red#zz"] {
+            assert_eq!(parse_color(invalid), None, "{invalid}");
+        }
+
+        let spec = parse("box[0,0;1,1;#0f08]", &InvLocation::CurrentPlayer);
+        assert_eq!(
+            spec.elements,
+            vec![Element::Box {
+                pos: Vector2::new(0.0, 0.0),
+                size: Vector2::new(1.0, 1.0),
+                color: rgba(0x00, 0xff, 0x00, 0x88),
+            }]
+        );
+    }
+
+    #[test]
+    fn unknown_elements() {
+        let spec = parse(
+            "size[8,9]
+            foo[bar;baz]
+            label[0,0;kept]
+            image[broken]
+            label[x,y;invalid position]
+            style[btn;bgcolor=red]
+            model[0,0;1,1;m;mesh.obj;tex.png]
+            checkbox[0,2;cb;Check;maybe]",
+            &InvLocation::CurrentPlayer,
+        );
+
+        assert_eq!(spec.size, Some(Vector2::new(8.0, 9.0)));
+        assert_eq!(
+            spec.elements,
+            vec![
+                Element::Label {
+                    pos: Vector2::new(0.0, 0.0),
+                    text: "kept".into(),
+                    vertical: false,
+                },
+                Element::Checkbox {
+                    pos: Vector2::new(0.0, 2.0),
+                    name: "cb".into(),
+                    label: "Check".into(),
+                    selected: false,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,414 @@
+use super::input::Action;
+use cgmath::{prelude::*, Vector2};
+use gilrs::{Axis, Button, EventType, Gilrs};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use winit::event::VirtualKeyCode as Key;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct GamepadSettings {
+    pub enabled: bool,
+    /// stick deflection below which input is ignored, from 0 to 1
+    pub deadzone: f32,
+    /// camera rotation at full right stick deflection, in degrees per second
+    pub look_sensitivity: f32,
+    /// how far triggers have to be pulled to count as pressed
+    pub trigger_threshold: f32,
+    pub buttons: GamepadBindings,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            deadzone: 0.15,
+            look_sensitivity: 180.0,
+            trigger_threshold: 0.5,
+            buttons: Default::default(),
+        }
+    }
+}
+
+// start is left out since it always opens the pause menu, like Escape
+const BUTTON_NAMES: [(Button, &str); 16] = [
+    (Button::South, "south"),
+    (Button::East, "east"),
+    (Button::North, "north"),
+    (Button::West, "west"),
+    (Button::LeftTrigger, "left_trigger"),
+    (Button::LeftTrigger2, "left_trigger2"),
+    (Button::RightTrigger, "right_trigger"),
+    (Button::RightTrigger2, "right_trigger2"),
+    (Button::Select, "select"),
+    (Button::Mode, "mode"),
+    (Button::LeftThumb, "left_thumb"),
+    (Button::RightThumb, "right_thumb"),
+    (Button::DPadUp, "dpad_up"),
+    (Button::DPadDown, "dpad_down"),
+    (Button::DPadLeft, "dpad_left"),
+    (Button::DPadRight, "dpad_right"),
+];
+
+/// maps gamepad buttons to actions, actions missing from the settings file keep their default
+///
+/// buttons are stored by name: "south", "east", "north", "west", "left_trigger",
+/// "left_trigger2", "right_trigger", "right_trigger2", "select", "mode", "left_thumb",
+/// "right_thumb", "dpad_up", "dpad_down", "dpad_left" and "dpad_right"
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(
+    try_from = "BTreeMap<Action, String>",
+    into = "BTreeMap<Action, String>"
+)]
+pub struct GamepadBindings(BTreeMap<Action, Button>);
+
+impl Default for GamepadBindings {
+    fn default() -> Self {
+        use Action::*;
+
+        Self(BTreeMap::from([
+            (Jump, Button::South),
+            (Sneak, Button::East),
+            (Inventory, Button::North),
+            (Drop, Button::West),
+            (Aux1, Button::LeftThumb),
+            (Zoom, Button::RightThumb),
+            (Dig, Button::RightTrigger2),
+            (Place, Button::LeftTrigger2),
+            (HotbarNext, Button::RightTrigger),
+            (HotbarPrev, Button::LeftTrigger),
+            (ToggleFly, Button::DPadUp),
+            (ToggleFast, Button::DPadRight),
+            (ToggleNoclip, Button::DPadLeft),
+            (TogglePitchMove, Button::DPadDown),
+            (ToggleDebug, Button::Select),
+        ]))
+    }
+}
+
+impl TryFrom<BTreeMap<Action, String>> for GamepadBindings {
+    type Error = String;
+
+    fn try_from(map: BTreeMap<Action, String>) -> Result<Self, Self::Error> {
+        let map = map
+            .into_iter()
+            .map(|(action, name)| {
+                BUTTON_NAMES
+                    .iter()
+                    .find(|(_, n)| *n == name)
+                    .map(|&(button, _)| (action, button))
+                    .ok_or_else(|| format!("invalid gamepad button: {name}"))
+            })
+            .collect::<Result<BTreeMap<_, _>, _>>()?;
+
+        let mut bindings = Self::default();
+        // defaults using a button the user bound to another action are unbound
+        bindings.0.retain(|action, button| {
+            map.contains_key(action) || !map.values().any(|bound| bound == button)
+        });
+        bindings.0.extend(map);
+
+        Ok(bindings)
+    }
+}
+
+impl From<GamepadBindings> for BTreeMap<Action, String> {
+    fn from(bindings: GamepadBindings) -> Self {
+        bindings
+            .0
+            .into_iter()
+            .filter_map(|(action, button)| {
+                BUTTON_NAMES
+                    .iter()
+                    .find(|&&(b, _)| b == button)
+                    .map(|(_, name)| (action, name.to_string()))
+            })
+            .collect()
+    }
+}
+
+impl GamepadBindings {
+    pub fn action(&self, button: Button) -> Option<Action> {
+        self.0
+            .iter()
+            .find(|(_, &bound)| bound == button)
+            .map(|(&action, _)| action)
+    }
+}
+
+/// key a button stands for in the pause menu, which is navigated like with the keyboard
+pub fn menu_key(button: Button) -> Option<Key> {
+    Some(match button {
+        Button::Start | Button::East => Key::Escape,
+        Button::South => Key::Return,
+        Button::DPadUp => Key::Up,
+        Button::DPadDown => Key::Down,
+        Button::DPadLeft => Key::Left,
+        Button::DPadRight => Key::Right,
+        _ => return None,
+    })
+}
+
+/// scales stick input so it starts at zero at the edge of the deadzone
+fn apply_deadzone(v: Vector2<f32>, deadzone: f32) -> Vector2<f32> {
+    let len = v.magnitude();
+
+    if len <= deadzone {
+        Vector2::zero()
+    } else {
+        v / len * ((len - deadzone) / (1.0 - deadzone)).min(1.0)
+    }
+}
+
+/// controller state, independent of the actual device so it can be fed by hand
+#[derive(Default)]
+pub struct GamepadState {
+    pub settings: GamepadSettings,
+    left: Vector2<f32>,
+    right: Vector2<f32>,
+    pressed: Vec<Button>,
+}
+
+impl GamepadState {
+    pub fn new(settings: &GamepadSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn axis(&mut self, axis: Axis, value: f32) {
+        match axis {
+            Axis::LeftStickX => self.left.x = value,
+            Axis::LeftStickY => self.left.y = value,
+            Axis::RightStickX => self.right.x = value,
+            Axis::RightStickY => self.right.y = value,
+            _ => {}
+        }
+    }
+
+    /// updates a button, value ranges from 0 to 1 for analog triggers.
+    /// returns the new pressed state if it changed
+    pub fn button(&mut self, button: Button, value: f32) -> Option<bool> {
+        let pressed = value >= self.settings.trigger_threshold;
+        let was_pressed = self.pressed.contains(&button);
+
+        if pressed == was_pressed {
+            return None;
+        }
+
+        if pressed {
+            self.pressed.push(button);
+        } else {
+            self.pressed.retain(|&b| b != button);
+        }
+
+        Some(pressed)
+    }
+
+    /// releases everything, returns the released buttons
+    pub fn reset(&mut self) -> Vec<(Button, bool)> {
+        self.left = Vector2::zero();
+        self.right = Vector2::zero();
+
+        self.pressed
+            .drain(..)
+            .map(|button| (button, false))
+            .collect()
+    }
+
+    /// action bound to a button
+    pub fn action(&self, button: Button) -> Option<Action> {
+        self.settings.buttons.action(button)
+    }
+
+    /// left stick movement, x to the right and y forward
+    pub fn movement(&self) -> Vector2<f32> {
+        apply_deadzone(self.left, self.settings.deadzone)
+    }
+
+    /// camera rotation from the right stick over dt seconds, in degrees, using
+    /// the same directions as mouse movement
+    pub fn look(&self, dt: f32) -> Vector2<f32> {
+        let v = apply_deadzone(self.right, self.settings.deadzone);
+        Vector2::new(v.x, -v.y) * self.settings.look_sensitivity * dt
+    }
+}
+
+pub struct Gamepad {
+    gilrs: Option<Gilrs>,
+    pub state: GamepadState,
+}
+
+impl Gamepad {
+    pub fn new(settings: &GamepadSettings) -> Self {
+        let gilrs = settings
+            .enabled
+            .then(|| {
+                Gilrs::new()
+                    .map_err(|e| eprintln!("gamepad support unavailable: {e}"))
+                    .ok()
+            })
+            .flatten();
+
+        Self {
+            gilrs,
+            state: GamepadState::new(settings),
+        }
+    }
+
+    /// processes pending controller events, returns the buttons that changed
+    pub fn poll(&mut self) -> Vec<(Button, bool)> {
+        let mut buttons = Vec::new();
+
+        let Some(gilrs) = &mut self.gilrs else {
+            return buttons;
+        };
+
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::AxisChanged(axis, value, _) => self.state.axis(axis, value),
+                EventType::ButtonChanged(button, value, _) => {
+                    if let Some(pressed) = self.state.button(button, value) {
+                        buttons.push((button, pressed));
+                    }
+                }
+                EventType::Disconnected => buttons.extend(self.state.reset()),
+                _ => {}
+            }
+        }
+
+        buttons
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(v: Vector2<f32>, x: f32, y: f32) {
+        assert!(
+            (v.x - x).abs() < 1e-4 && (v.y - y).abs() < 1e-4,
+            "{v:?} != ({x}, {y})"
+        );
+    }
+
+    #[test]
+    fn deadzone() {
+        let mut state = GamepadState::new(&Default::default());
+
+        state.axis(Axis::LeftStickX, 0.1);
+        state.axis(Axis::LeftStickY, -0.1);
+        assert_near(state.movement(), 0.0, 0.0);
+
+        // rescaled to start at zero at the edge of the deadzone
+        state.axis(Axis::LeftStickX, 0.575);
+        state.axis(Axis::LeftStickY, 0.0);
+        assert_near(state.movement(), 0.5, 0.0);
+
+        state.axis(Axis::LeftStickX, 1.0);
+        assert_near(state.movement(), 1.0, 0.0);
+
+        // full diagonal deflection isn't faster than straight
+        state.axis(Axis::LeftStickY, 1.0);
+        assert!((state.movement().magnitude() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn look_scaling() {
+        let mut state = GamepadState::new(&GamepadSettings {
+            deadzone: 0.0,
+            look_sensitivity: 180.0,
+            ..Default::default()
+        });
+
+        state.axis(Axis::RightStickX, 1.0);
+        assert_near(state.look(0.5), 90.0, 0.0);
+
+        // pushing up looks up, like moving the mouse up
+        state.axis(Axis::RightStickX, 0.0);
+        state.axis(Axis::RightStickY, 0.5);
+        assert_near(state.look(1.0), 0.0, -90.0);
+
+        // the left stick doesn't rotate the camera
+        state.axis(Axis::RightStickY, 0.0);
+        state.axis(Axis::LeftStickX, 1.0);
+        assert_near(state.look(1.0), 0.0, 0.0);
+    }
+
+    #[test]
+    fn button_edges() {
+        let mut state = GamepadState::new(&Default::default());
+
+        assert_eq!(state.button(Button::South, 1.0), Some(true));
+        assert_eq!(state.button(Button::South, 1.0), None);
+        assert_eq!(state.button(Button::South, 0.0), Some(false));
+        assert_eq!(state.button(Button::South, 0.0), None);
+    }
+
+    #[test]
+    fn trigger_threshold() {
+        let mut state = GamepadState::new(&Default::default());
+
+        assert_eq!(state.button(Button::RightTrigger2, 0.3), None);
+        assert_eq!(state.button(Button::RightTrigger2, 0.6), Some(true));
+        assert_eq!(state.button(Button::RightTrigger2, 0.9), None);
+        assert_eq!(state.button(Button::RightTrigger2, 0.4), Some(false));
+    }
+
+    #[test]
+    fn reset_releases_everything() {
+        let mut state = GamepadState::new(&Default::default());
+
+        state.button(Button::South, 1.0);
+        state.button(Button::North, 1.0);
+        state.axis(Axis::LeftStickX, 1.0);
+
+        assert_eq!(
+            state.reset(),
+            vec![(Button::South, false), (Button::North, false)]
+        );
+        assert_near(state.movement(), 0.0, 0.0);
+        assert_eq!(state.reset(), Vec::new());
+    }
+
+    #[test]
+    fn default_bindings() {
+        let state = GamepadState::new(&Default::default());
+
+        assert_eq!(state.action(Button::South), Some(Action::Jump));
+        assert_eq!(state.action(Button::North), Some(Action::Inventory));
+        assert_eq!(state.action(Button::RightTrigger), Some(Action::HotbarNext));
+        assert_eq!(state.action(Button::LeftTrigger), Some(Action::HotbarPrev));
+        assert_eq!(state.action(Button::Start), None);
+        assert_eq!(menu_key(Button::Start), Some(Key::Escape));
+    }
+
+    #[test]
+    fn bindings_from_settings() {
+        let bindings: GamepadBindings = serde_yaml::from_str("jump: mode\n").unwrap();
+        assert_eq!(bindings.action(Button::Mode), Some(Action::Jump));
+        assert_eq!(bindings.action(Button::South), None);
+        // actions missing from the file keep their default
+        assert_eq!(bindings.action(Button::East), Some(Action::Sneak));
+
+        // rebinding onto a used button unbinds the action that had it by default
+        let bindings: GamepadBindings = serde_yaml::from_str("jump: east\n").unwrap();
+        assert_eq!(bindings.action(Button::East), Some(Action::Jump));
+        assert_eq!(bindings.action(Button::South), None);
+        assert_eq!(bindings.action(Button::North), Some(Action::Inventory));
+
+        // unless that action is rebound as well
+        let bindings: GamepadBindings = serde_yaml::from_str("jump: east\nsneak: south\n").unwrap();
+        assert_eq!(bindings.action(Button::East), Some(Action::Jump));
+        assert_eq!(bindings.action(Button::South), Some(Action::Sneak));
+
+        assert!(serde_yaml::from_str::<GamepadBindings>("jump: start\n").is_err());
+
+        let yaml = serde_yaml::to_string(&GamepadBindings::default()).unwrap();
+        assert_eq!(
+            serde_yaml::from_str::<GamepadBindings>(&yaml).unwrap(),
+            GamepadBindings::default()
+        );
+    }
+}
@@ -1,3 +1,8 @@
+use super::{
+    post::{PostProcess, HDR_FORMAT},
+    settings::Settings,
+};
+
 pub struct Frame<'a> {
     pub gpu: &'a mut Gpu,
     pub encoder: wgpu::CommandEncoder,
@@ -25,11 +30,22 @@ impl<'a> Frame<'a> {
     }
 
     pub fn pass(&mut self) -> wgpu::RenderPass {
+        let target = match &self.gpu.post {
+            Some(post) => post.scene_view(),
+            None => &self.view,
+        };
+
+        // render to the multisampled texture and resolve into the actual target
+        let (view, resolve_target) = match &self.gpu.msaa_texture {
+            Some(msaa) => (msaa, Some(target)),
+            None => (target, None),
+        };
+
         self.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: None,
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &self.view,
-                resolve_target: None,
+                view,
+                resolve_target,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color {
                         r: 0x87 as f64 / 255.0,
@@ -37,7 +53,7 @@ impl<'a> Frame<'a> {
                         b: 0xEB as f64 / 255.0,
                         a: 1.0,
                     }),
-                    store: true,
+                    store: resolve_target.is_none(),
                 },
             })],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
@@ -51,6 +67,13 @@ impl<'a> Frame<'a> {
         })
     }
 
+    /// resolve the scene into the swapchain, must be called after the scene pass
+    pub fn post_process(&mut self) {
+        if let Some(post) = &self.gpu.post {
+            post.run(&self.gpu.queue, &mut self.encoder, &self.view);
+        }
+    }
+
     pub fn finish(self) {
         self.gpu.queue.submit(Some(self.encoder.finish()));
         self.output.present();
@@ -58,15 +81,19 @@ impl<'a> Frame<'a> {
 }
 
 pub struct Gpu {
+    pub adapter: wgpu::Adapter,
     pub surface: wgpu::Surface,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
     pub depth_texture: wgpu::TextureView,
+    pub msaa_texture: Option<wgpu::TextureView>,
+    pub sample_count: u32,
+    pub post: Option<PostProcess>,
 }
 
 impl Gpu {
-    pub async fn new(window: &winit::window::Window) -> Self {
+    pub async fn new(window: &winit::window::Window, settings: &Settings) -> Self {
         let size = window.inner_size();
 
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
@@ -85,10 +112,14 @@ impl Gpu {
             .await
             .unwrap();
 
+        // needed for sample counts other than 1 and 4
+        let features =
+            adapter.features() & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::empty(),
+                    features,
                     limits: Default::default(),
                     label: None,
                 },
@@ -115,14 +146,33 @@ impl Gpu {
             view_formats: vec![],
         };
 
-        let depth_texture = Self::create_depth_texture(&config, &device);
+        let post = PostProcess::new(&device, &config, &settings.post);
+        let scene_format = if post.is_some() {
+            HDR_FORMAT
+        } else {
+            config.format
+        };
+
+        let sample_count = Self::choose_sample_count(
+            &adapter,
+            features,
+            &[scene_format, wgpu::TextureFormat::Depth32Float],
+            settings.msaa,
+        );
+
+        let depth_texture = Self::create_depth_texture(&config, &device, sample_count);
+        let msaa_texture = Self::create_msaa_texture(&config, &device, scene_format, sample_count);
 
         let mut state = Self {
+            adapter,
             surface,
             device,
             queue,
             config,
             depth_texture,
+            msaa_texture,
+            sample_count,
+            post,
         };
 
         state.resize(size);
@@ -130,9 +180,73 @@ impl Gpu {
         state
     }
 
+    fn choose_sample_count(
+        adapter: &wgpu::Adapter,
+        features: wgpu::Features,
+        formats: &[wgpu::TextureFormat],
+        wanted: u32,
+    ) -> u32 {
+        let supported = |count: u32| {
+            formats.iter().all(|&format| {
+                let flags = if features
+                    .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+                {
+                    adapter.get_texture_format_features(format).flags
+                } else {
+                    format.describe().guaranteed_format_features.flags
+                };
+
+                flags.sample_count_supported(count)
+            })
+        };
+
+        let count = [8, 4, 2]
+            .into_iter()
+            .filter(|&count| count <= wanted)
+            .find(|&count| supported(count))
+            .unwrap_or(1);
+
+        if count != wanted.max(1) {
+            eprintln!("{wanted}x MSAA is not supported by the adapter, using {count}x");
+        }
+
+        count
+    }
+
+    pub fn create_msaa_texture(
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Option<wgpu::TextureView> {
+        if sample_count <= 1 {
+            return None;
+        }
+
+        Some(
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("msaa texture"),
+                    size: wgpu::Extent3d {
+                        width: config.width,
+                        height: config.height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+        )
+    }
+
     pub fn create_depth_texture(
         config: &wgpu::SurfaceConfiguration,
         device: &wgpu::Device,
+        sample_count: u32,
     ) -> wgpu::TextureView {
         device
             .create_texture(&wgpu::TextureDescriptor {
@@ -143,7 +257,7 @@ impl Gpu {
                     depth_or_array_layers: 1,
                 },
                 mip_level_count: 1,
-                sample_count: 1,
+                sample_count,
                 dimension: wgpu::TextureDimension::D2,
                 format: wgpu::TextureFormat::Depth32Float,
                 usage: wgpu::TextureUsages::RENDER_ATTACHMENT
@@ -158,7 +272,35 @@ impl Gpu {
             self.config.width = size.width;
             self.config.height = size.height;
             self.configure_surface();
-            self.depth_texture = Self::create_depth_texture(&self.config, &self.device);
+            self.depth_texture =
+                Self::create_depth_texture(&self.config, &self.device, self.sample_count);
+            self.msaa_texture = Self::create_msaa_texture(
+                &self.config,
+                &self.device,
+                self.scene_format(),
+                self.sample_count,
+            );
+
+            if let Some(post) = &mut self.post {
+                post.resize(&self.device, &self.config);
+            }
+        }
+    }
+
+    pub fn multisample(&self) -> wgpu::MultisampleState {
+        wgpu::MultisampleState {
+            count: self.sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        }
+    }
+
+    /// format of the target the scene is rendered to
+    pub fn scene_format(&self) -> wgpu::TextureFormat {
+        if self.post.is_some() {
+            HDR_FORMAT
+        } else {
+            self.config.format
         }
     }
 
@@ -0,0 +1,224 @@
+use super::{
+    hud::Direction,
+    items::ItemRegistry,
+    map::MapRender,
+    ui::{Rect, Ui},
+};
+use crate::inventory::{Inventory, ItemStack};
+use wgpu_glyph::{HorizontalAlign, Layout, Section, Text, VerticalAlign};
+
+// size of a slot in pixels at a HUD scale of 1
+const SLOT_SIZE: f32 = 48.0;
+const MAX_ITEM_COUNT: u16 = 32;
+
+/// the player's main list items that can be wielded, shown at the bottom of the screen
+pub struct Hotbar {
+    pub selected: u16,
+    /// number of slots, set by the server
+    pub item_count: u16,
+    /// background texture of the whole hotbar, set by the server
+    pub image: String,
+    /// texture drawn over the selected slot, set by the server
+    pub selected_image: String,
+}
+
+impl Default for Hotbar {
+    fn default() -> Self {
+        Self {
+            selected: 0,
+            item_count: 8,
+            image: String::new(),
+            selected_image: String::new(),
+        }
+    }
+}
+
+impl Hotbar {
+    /// number of usable slots, the main list may be smaller than the item count
+    pub fn len(&self, inventory: &Inventory) -> u16 {
+        let main = inventory.list("main").map_or(0, |list| list.stacks.len());
+        self.item_count.min(main as u16)
+    }
+
+    pub fn set_item_count(&mut self, count: i32) {
+        self.item_count = count.clamp(1, MAX_ITEM_COUNT as i32) as u16;
+        self.selected = self.selected.min(self.item_count - 1);
+    }
+
+    /// selects a slot, returns whether the selection changed
+    pub fn select(&mut self, slot: u16, inventory: &Inventory) -> bool {
+        if slot >= self.len(inventory) || slot == self.selected {
+            return false;
+        }
+
+        self.selected = slot;
+        true
+    }
+
+    /// moves the selection by delta slots, wrapping around
+    pub fn scroll(&mut self, delta: i32, inventory: &Inventory) -> bool {
+        let len = self.len(inventory) as i32;
+        if len == 0 {
+            return false;
+        }
+
+        let slot = (self.selected as i32 + delta).rem_euclid(len);
+        self.select(slot as u16, inventory)
+    }
+
+    pub fn render(
+        &self,
+        items: &ItemRegistry,
+        map: &MapRender,
+        inventory: &Inventory,
+        scale: f32,
+        ui: &mut Ui,
+    ) {
+        let main = match inventory.list("main") {
+            Some(main) => main,
+            None => return,
+        };
+
+        let len = self.len(inventory);
+        if len == 0 {
+            return;
+        }
+
+        // centered at the bottom of the screen
+        let screen = ui.screen_size();
+        let (size, padding) = slot_size(scale);
+        let width = (size + padding * 2.0) * len as f32;
+        let origin = (
+            ((screen.0 - width) / 2.0).floor(),
+            screen.1 - size - padding * 3.0,
+        );
+
+        if !self.image.is_empty() {
+            let bar = Rect::new(origin.0, origin.1, width, size + padding * 2.0);
+            ui.image(&self.image, bar, [1.0; 4]);
+        }
+
+        let slots = slot_rects(origin, Direction::LeftRight, len, scale)
+            .into_iter()
+            .zip(&main.stacks)
+            .enumerate()
+            .map(|(i, (rect, stack))| (rect, stack, i as u16 == self.selected));
+
+        self.draw_items(items, map, slots, scale, ui);
+    }
+
+    /// draws item stacks in slots, highlighting the selected ones like the hotbar does
+    pub fn draw_items<'a>(
+        &self,
+        items: &ItemRegistry,
+        map: &MapRender,
+        slots: impl Iterator<Item = (Rect, &'a ItemStack, bool)>,
+        scale: f32,
+        ui: &mut Ui,
+    ) {
+        let padding = slot_size(scale).1;
+
+        for (rect, stack, selected) in slots {
+            if self.image.is_empty() {
+                ui.fill(rect, [0.0, 0.0, 0.0, 0.4]);
+            }
+
+            if selected {
+                let outer = Rect::new(
+                    rect.x - padding,
+                    rect.y - padding,
+                    rect.w + padding * 2.0,
+                    rect.h + padding * 2.0,
+                );
+
+                if self.selected_image.is_empty() {
+                    let w = padding.max(1.0);
+                    let color = [1.0, 1.0, 1.0, 0.8];
+                    ui.fill(Rect::new(outer.x, outer.y, outer.w, w), color);
+                    ui.fill(Rect::new(outer.x, outer.y + outer.h - w, outer.w, w), color);
+                    ui.fill(Rect::new(outer.x, outer.y, w, outer.h), color);
+                    ui.fill(Rect::new(outer.x + outer.w - w, outer.y, w, outer.h), color);
+                } else {
+                    ui.image(&self.selected_image, outer, [1.0; 4]);
+                }
+            }
+
+            draw_item(items, map, stack, rect, scale, ui);
+        }
+    }
+}
+
+/// draws an item stack in a slot with its wear bar and count
+pub fn draw_item(
+    items: &ItemRegistry,
+    map: &MapRender,
+    stack: &ItemStack,
+    rect: Rect,
+    scale: f32,
+    ui: &mut Ui,
+) {
+    if stack.is_empty() {
+        return;
+    }
+
+    let node = map
+        .node_by_name(items.resolve(&stack.name))
+        .map(|(_, def)| def);
+    let texture = items.inventory_texture(&stack.name, node);
+    ui.image(&texture, rect, [1.0; 4]);
+
+    // wear bar of tools, from green to red
+    if stack.wear > 0 {
+        let left = 1.0 - stack.wear as f32 / 65535.0;
+        let bar = Rect::new(
+            rect.x + rect.w / 8.0,
+            rect.y + rect.h * 0.85,
+            rect.w * 0.75,
+            rect.h / 16.0,
+        );
+
+        ui.fill(bar, [0.0, 0.0, 0.0, 1.0]);
+        ui.fill(
+            Rect::new(bar.x, bar.y, bar.w * left, bar.h),
+            [(1.0 - left) * 2.0, left * 2.0, 0.0, 1.0].map(|c| c.min(1.0)),
+        );
+    }
+
+    if stack.count > 1 {
+        ui.text(Section {
+            screen_position: (rect.x + rect.w, rect.y + rect.h),
+            bounds: ui.screen_size(),
+            text: vec![Text::new(&stack.count.to_string())
+                .with_color([1.0, 1.0, 1.0, 1.0])
+                .with_scale(16.0 * scale)],
+            layout: Layout::default_single_line()
+                .h_align(HorizontalAlign::Right)
+                .v_align(VerticalAlign::Bottom),
+        });
+    }
+}
+
+/// size and padding of inventory slots in pixels
+pub fn slot_size(scale: f32) -> (f32, f32) {
+    let size = (SLOT_SIZE * scale).floor();
+    (size, (size / 12.0).floor())
+}
+
+/// slots of a row or column of items starting at origin, laid out like upstream does
+pub fn slot_rects(origin: (f32, f32), dir: Direction, slots: u16, scale: f32) -> Vec<Rect> {
+    let (size, padding) = slot_size(scale);
+    let step = size + padding * 2.0;
+    let (dx, dy) = dir.step();
+
+    (0..slots)
+        .map(|i| {
+            let along = padding + step * i as f32;
+            let (x, y) = match dir {
+                Direction::LeftRight | Direction::RightLeft => (dx * along, padding),
+                Direction::TopBottom | Direction::BottomTop => (padding, dy * along),
+            };
+
+            Rect::new(origin.0 + x, origin.1 + y, size, size)
+        })
+        .collect()
+}
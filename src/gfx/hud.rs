@@ -0,0 +1,880 @@
+use super::{
+    camera::Camera,
+    font::Font,
+    hotbar::{self, Hotbar},
+    items::ItemRegistry,
+    map::MapRender,
+    player::NodeMap,
+    ui::{Rect, Ui},
+};
+use crate::inventory::Inventory;
+use cgmath::{prelude::*, Deg, Matrix4, Point3, Rad, Vector2};
+use mt_net::{enumset::EnumSet, HudChange, HudElement, HudFlag, HudParam, HudType};
+use std::collections::HashMap;
+use wgpu_glyph::{HorizontalAlign, Layout, Section, Text};
+
+// font size of text elements in pixels at a HUD scale of 1
+const TEXT_SIZE: f32 = 16.0;
+// maximum health and breath of players, in half icons
+const HP_MAX: u16 = 20;
+const BREATH_MAX: u16 = 20;
+// size of the builtin statbars' icons in pixels
+const STATBAR_SIZE: i32 = 24;
+// nodes shown around the player by the minimap, horizontally and vertically
+const MINIMAP_RADIUS: i32 = 24;
+const MINIMAP_HEIGHT: i32 = 16;
+
+/// direction statbars and inventory elements extend in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    LeftRight,
+    RightLeft,
+    TopBottom,
+    BottomTop,
+}
+
+impl From<u32> for Direction {
+    fn from(dir: u32) -> Self {
+        match dir {
+            1 => Direction::RightLeft,
+            2 => Direction::TopBottom,
+            3 => Direction::BottomTop,
+            _ => Direction::LeftRight,
+        }
+    }
+}
+
+impl Direction {
+    /// unit step from one icon or slot to the next
+    pub fn step(self) -> (f32, f32) {
+        match self {
+            Direction::LeftRight => (1.0, 0.0),
+            Direction::RightLeft => (-1.0, 0.0),
+            Direction::TopBottom => (0.0, 1.0),
+            Direction::BottomTop => (0.0, -1.0),
+        }
+    }
+}
+
+/// screen position of an element's pos, which is relative to the screen size
+pub fn anchor(pos: Vector2<f32>, screen: (f32, f32)) -> (f32, f32) {
+    (
+        (pos.x * screen.0 + 0.5).floor(),
+        (pos.y * screen.1 + 0.5).floor(),
+    )
+}
+
+/// top left corner of something of the given size placed at anchor,
+/// an alignment of -1, 0 or 1 puts it before, centered on or after the anchor
+pub fn align(anchor: (f32, f32), size: (f32, f32), align: Vector2<f32>) -> (f32, f32) {
+    (
+        anchor.0 + (align.x - 1.0) * size.0 / 2.0,
+        anchor.1 + (align.y - 1.0) * size.1 / 2.0,
+    )
+}
+
+/// size of a scaled image, negative scales are percentages of the screen size
+pub fn image_size(
+    texture: (u32, u32),
+    scale: Vector2<f32>,
+    screen: (f32, f32),
+    hud_scale: f32,
+) -> (f32, f32) {
+    let size = |texture: u32, scale: f32, screen: f32| {
+        if scale < 0.0 {
+            screen * -scale / 100.0
+        } else {
+            texture as f32 * scale * hud_scale
+        }
+    };
+
+    (
+        size(texture.0, scale.x, screen.0),
+        size(texture.1, scale.y, screen.1),
+    )
+}
+
+/// size of an element with an explicit size, negative sizes are percentages of the screen size
+pub fn element_size(size: Vector2<i32>, screen: (f32, f32), hud_scale: f32) -> (f32, f32) {
+    let size = |size: i32, screen: f32| {
+        if size < 0 {
+            screen * -size as f32 / 100.0
+        } else {
+            size as f32 * hud_scale
+        }
+    };
+
+    (size(size.x, screen.0), size(size.y, screen.1))
+}
+
+/// rectangles and texture coordinates of a statbar's icons, count is in half icons
+pub fn statbar_icons(
+    origin: (f32, f32),
+    icon: (f32, f32),
+    dir: Direction,
+    count: u32,
+) -> Vec<(Rect, Rect)> {
+    let (dx, dy) = dir.step();
+    let rect = |i: u32| {
+        Rect::new(
+            origin.0 + dx * icon.0 * i as f32,
+            origin.1 + dy * icon.1 * i as f32,
+            icon.0,
+            icon.1,
+        )
+    };
+
+    let mut icons: Vec<_> = (0..count / 2).map(|i| (rect(i), Rect::UNIT)).collect();
+
+    if count % 2 == 1 {
+        // the half closer to the start of the bar
+        let half = match dir {
+            Direction::LeftRight => Rect::new(0.0, 0.0, 0.5, 1.0),
+            Direction::RightLeft => Rect::new(0.5, 0.0, 0.5, 1.0),
+            Direction::TopBottom => Rect::new(0.0, 0.0, 1.0, 0.5),
+            Direction::BottomTop => Rect::new(0.0, 0.5, 1.0, 0.5),
+        };
+
+        let full = rect(count / 2);
+        icons.push((
+            Rect::new(
+                full.x + half.x * full.w,
+                full.y + half.y * full.h,
+                half.w * full.w,
+                half.h * full.h,
+            ),
+            half,
+        ));
+    }
+
+    icons
+}
+
+/// screen position of a point in the world, None if it's behind the camera
+pub fn project(
+    view_proj: Matrix4<f32>,
+    pos: Point3<f32>,
+    screen: (f32, f32),
+) -> Option<(f32, f32)> {
+    let clip = view_proj * pos.to_homogeneous();
+    if clip.w <= 0.0 {
+        return None;
+    }
+
+    Some((
+        (clip.x / clip.w + 1.0) / 2.0 * screen.0,
+        (1.0 - clip.y / clip.w) / 2.0 * screen.1,
+    ))
+}
+
+/// copies of a scrolling compass texture covering rect, clipped to it;
+/// angle is in degrees and moves the texture to the right
+pub fn compass_tiles(
+    rect: Rect,
+    texture: (u32, u32),
+    scale: Vector2<f32>,
+    angle: f32,
+) -> Vec<(Rect, Rect)> {
+    let h = rect.h * scale.y;
+    let w = rect.h * scale.x * texture.0 as f32 / texture.1.max(1) as f32;
+    if w <= 0.0 || h <= 0.0 {
+        return Vec::new();
+    }
+
+    let y = rect.y + (rect.h - h) / 2.0;
+    let mut x = rect.x + (rect.w - w) / 2.0 + angle / 360.0 * w;
+    // start with the first copy reaching into rect
+    x -= ((x - rect.x) / w).ceil() * w;
+
+    let mut tiles = Vec::new();
+    while x < rect.x + rect.w {
+        tiles.extend(Rect::new(x, y, w, h).clip(Rect::UNIT, rect));
+        x += w;
+    }
+
+    tiles
+}
+
+/// removes the escape sequences servers use for translations and colors
+pub fn strip_escapes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            out.push(c);
+            continue;
+        }
+
+        // either \e(...) or a single character like \eE
+        if chars.next() == Some('(') {
+            for c in chars.by_ref() {
+                if c == ')' {
+                    break;
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// adds text placed at anchor like an image of the same size would be
+fn draw_text(
+    ui: &mut Ui,
+    font: &mut Font,
+    text: &str,
+    anchor: (f32, f32),
+    align: Vector2<f32>,
+    size: f32,
+    color: [f32; 4],
+) {
+    let section = |screen_position, h_align| Section {
+        screen_position,
+        text: vec![Text::new(text).with_scale(size).with_color(color)],
+        layout: Layout::default().h_align(h_align),
+        ..Section::default()
+    };
+
+    let (w, h) = font.text_size(section((0.0, 0.0), HorizontalAlign::Left));
+    let (x, y) = self::align(anchor, (w, h), align);
+
+    // align lines within the text the same way
+    let (x, h_align) = if align.x < 0.0 {
+        (x + w, HorizontalAlign::Right)
+    } else if align.x == 0.0 {
+        (x + w / 2.0, HorizontalAlign::Center)
+    } else {
+        (x, HorizontalAlign::Left)
+    };
+
+    ui.text(section((x, y), h_align));
+}
+
+fn rgb(color: u32) -> [f32; 4] {
+    let channel = |shift: u32| ((color >> shift) & 0xFF) as f32 / 255.0;
+    [channel(16), channel(8), channel(0), 1.0]
+}
+
+/// compass bearing of the camera in degrees, clockwise from north
+fn bearing(camera: &Camera) -> f32 {
+    let dir = camera.look_dir();
+    Deg::from(Rad(dir.x.atan2(dir.z))).0
+}
+
+/// radar of the nodes around the player, like upstream's radar minimap
+#[derive(Default)]
+struct Minimap {
+    center: Option<Point3<i32>>,
+    /// number of walkable nodes in each column, row by row from the north west
+    density: Vec<u8>,
+}
+
+impl Minimap {
+    fn update(&mut self, map: &impl NodeMap, center: Point3<i32>) {
+        if self.center == Some(center) {
+            return;
+        }
+
+        self.center = Some(center);
+
+        let size = MINIMAP_RADIUS * 2;
+        self.density = (0..size * size)
+            .map(|i| {
+                let x = center.x - MINIMAP_RADIUS + i % size;
+                let z = center.z + MINIMAP_RADIUS - 1 - i / size;
+
+                (-MINIMAP_HEIGHT..MINIMAP_HEIGHT)
+                    .filter(|y| {
+                        map.node(Point3::new(x, center.y + y, z))
+                            .map_or(false, |def| def.walkable)
+                    })
+                    .count() as u8
+            })
+            .collect();
+    }
+
+    fn render(&self, rect: Rect, bearing: f32, ui: &mut Ui) {
+        ui.fill(rect, [0.0, 0.0, 0.0, 1.0]);
+
+        let size = MINIMAP_RADIUS * 2;
+        let cell = (rect.w / size as f32, rect.h / size as f32);
+
+        for (i, &density) in self.density.iter().enumerate() {
+            if density == 0 {
+                continue;
+            }
+
+            let (col, row) = ((i as i32 % size) as f32, (i as i32 / size) as f32);
+            let green = density as f32 / (MINIMAP_HEIGHT * 2) as f32;
+
+            ui.fill(
+                Rect::new(rect.x + col * cell.0, rect.y + row * cell.1, cell.0, cell.1),
+                [0.0, 0.2 + green * 0.8, 0.0, 1.0],
+            );
+        }
+
+        ui.image("minimap_overlay_square.png", rect, [1.0; 4]);
+
+        let marker = Rect::new(
+            rect.x + rect.w * 0.45,
+            rect.y + rect.h * 0.45,
+            rect.w * 0.1,
+            rect.h * 0.1,
+        );
+        ui.image_rotated("player_marker.png", marker, Deg(-bearing).into(), [1.0; 4]);
+    }
+}
+
+/// everything HUD elements are drawn from, besides the HUD itself
+pub struct HudContext<'a> {
+    pub camera: &'a Camera,
+    pub items: &'a ItemRegistry,
+    pub map: &'a MapRender,
+    pub inventory: &'a Inventory,
+    /// size of HUD elements relative to upstream's default
+    pub scale: f32,
+}
+
+/// a statbar, either builtin or added by the server
+struct Statbar<'a> {
+    texture: &'a str,
+    background: &'a str,
+    /// in half icons
+    count: u32,
+    max: u32,
+    dir: Direction,
+    /// size of an icon, zero for the texture's size
+    size: Vector2<i32>,
+    offset: Vector2<f32>,
+}
+
+/// elements added by the server and the builtin hotbar and statbars
+pub struct Hud {
+    elements: HashMap<u32, HudElement>,
+    pub flags: EnumSet<HudFlag>,
+    pub hotbar: Hotbar,
+    pub hp: u16,
+    pub breath: u16,
+    minimap: Minimap,
+}
+
+impl Default for Hud {
+    fn default() -> Self {
+        Self {
+            elements: HashMap::new(),
+            flags: EnumSet::all() - HudFlag::RadarMinimap,
+            hotbar: Default::default(),
+            hp: HP_MAX,
+            breath: BREATH_MAX / 2,
+            minimap: Default::default(),
+        }
+    }
+}
+
+impl Hud {
+    pub fn add(&mut self, id: u32, elem: HudElement) {
+        self.elements.insert(id, elem);
+    }
+
+    pub fn remove(&mut self, id: u32) {
+        self.elements.remove(&id);
+    }
+
+    pub fn change(&mut self, id: u32, change: HudChange) {
+        let elem = match self.elements.get_mut(&id) {
+            Some(elem) => elem,
+            None => {
+                eprintln!("change of unknown HUD element {id}");
+                return;
+            }
+        };
+
+        match change {
+            HudChange::Pos(v) => elem.pos = v,
+            HudChange::Name(v) => elem.name = v,
+            HudChange::Scale(v) => elem.scale = v,
+            HudChange::Text(v) => elem.text = v,
+            HudChange::Number(v) => elem.number = v,
+            HudChange::Item(v) => elem.item = v,
+            HudChange::Dir(v) => elem.dir = v,
+            HudChange::Align(v) => elem.align = v,
+            HudChange::Offset(v) => elem.offset = v,
+            HudChange::WorldPos(v) => elem.world_pos = v,
+            HudChange::Size(v) => elem.size = v,
+            HudChange::ZIndex(v) => elem.z_index = v,
+            HudChange::Text2(v) => elem.text_2 = v,
+            HudChange::Style(v) => elem.style = v,
+        }
+    }
+
+    /// sets the flags in mask to the ones in flags
+    pub fn set_flags(&mut self, flags: EnumSet<HudFlag>, mask: EnumSet<HudFlag>) {
+        self.flags = (self.flags - mask) | (flags & mask);
+    }
+
+    pub fn set_param(&mut self, param: HudParam) {
+        match param {
+            HudParam::HotbarItemCount(count) => self.hotbar.set_item_count(count),
+            HudParam::HotbarImage(image) => self.hotbar.image = image,
+            HudParam::HotbarImageSelected(image) => self.hotbar.selected_image = image,
+        }
+    }
+
+    /// whether a builtin part of the HUD is visible, basic hides all of them
+    pub fn shows(&self, flag: HudFlag) -> bool {
+        self.flags.contains(HudFlag::Basic) && self.flags.contains(flag)
+    }
+
+    // servers running upstream's builtin mods add statbars themselves, don't draw them twice
+    fn has_statbar(&self, texture: &str) -> bool {
+        self.elements
+            .values()
+            .any(|elem| elem.kind == HudType::Statbar && elem.text == texture)
+    }
+
+    pub fn render(&mut self, ctx: &HudContext, ui: &mut Ui, font: &mut Font) {
+        let screen = ui.screen_size();
+        let bottom = anchor(Vector2::new(0.5, 1.0), screen);
+
+        if self.shows(HudFlag::Hotbar) {
+            self.hotbar
+                .render(ctx.items, ctx.map, ctx.inventory, ctx.scale, ui);
+        }
+
+        if self.shows(HudFlag::HealthBar) && !self.has_statbar("heart.png") {
+            let bar = Statbar {
+                texture: "heart.png",
+                background: "heart_gone.png",
+                count: self.hp as u32,
+                max: HP_MAX as u32,
+                dir: Direction::LeftRight,
+                size: Vector2::new(STATBAR_SIZE, STATBAR_SIZE),
+                offset: Vector2::new(-10.0 * 24.0 - 25.0, -(48.0 + 24.0 + 16.0)),
+            };
+            self.draw_statbar(&bar, bottom, ctx.scale, ui);
+        }
+
+        // like upstream, breath is only shown while it's not full
+        if self.shows(HudFlag::BreathBar)
+            && self.breath * 2 < BREATH_MAX
+            && !self.has_statbar("bubble.png")
+        {
+            let bar = Statbar {
+                texture: "bubble.png",
+                background: "bubble_gone.png",
+                count: self.breath as u32 * 2,
+                max: BREATH_MAX as u32,
+                dir: Direction::LeftRight,
+                size: Vector2::new(STATBAR_SIZE, STATBAR_SIZE),
+                offset: Vector2::new(25.0, -(48.0 + 24.0 + 16.0)),
+            };
+            self.draw_statbar(&bar, bottom, ctx.scale, ui);
+        }
+
+        let has_minimap = self
+            .elements
+            .values()
+            .any(|elem| elem.kind == HudType::Minimap);
+        if has_minimap && self.shows(HudFlag::Minimap) {
+            let center = ctx.camera.pos.map(|x| x.round() as i32);
+            self.minimap.update(&ctx.map.view(), center);
+        }
+
+        let mut elements: Vec<_> = self.elements.iter().collect();
+        elements.sort_by_key(|(&id, elem)| (elem.z_index, id));
+
+        for (_, elem) in elements {
+            ui.set_z(elem.z_index as i32);
+            self.draw_element(elem, ctx, ui, font);
+        }
+        ui.set_z(0);
+    }
+
+    fn draw_statbar(&self, bar: &Statbar, anchor: (f32, f32), scale: f32, ui: &mut Ui) {
+        let icon = if bar.size == Vector2::zero() {
+            match ui.texture_size(bar.texture) {
+                Some((w, h)) => (w as f32 * scale, h as f32 * scale),
+                None => return,
+            }
+        } else {
+            (bar.size.x as f32 * scale, bar.size.y as f32 * scale)
+        };
+
+        let origin = (
+            anchor.0 + bar.offset.x * scale,
+            anchor.1 + bar.offset.y * scale,
+        );
+
+        if !bar.background.is_empty() {
+            for (rect, uv) in statbar_icons(origin, icon, bar.dir, bar.max) {
+                ui.image_region(bar.background, rect, uv, [1.0; 4]);
+            }
+        }
+
+        for (rect, uv) in statbar_icons(origin, icon, bar.dir, bar.count) {
+            ui.image_region(bar.texture, rect, uv, [1.0; 4]);
+        }
+    }
+
+    fn draw_element(&self, elem: &HudElement, ctx: &HudContext, ui: &mut Ui, font: &mut Font) {
+        let screen = ui.screen_size();
+        let scale = ctx.scale;
+        let offset = (elem.offset.x * scale, elem.offset.y * scale);
+
+        let pos = match elem.kind {
+            HudType::Waypoint | HudType::ImageWaypoint => {
+                let view_proj = ctx.camera.proj * ctx.camera.view;
+                match project(view_proj, Point3::from_vec(elem.world_pos), screen) {
+                    Some(pos) => pos,
+                    None => return,
+                }
+            }
+            _ => anchor(elem.pos, screen),
+        };
+
+        match elem.kind {
+            HudType::Image | HudType::ImageWaypoint => {
+                let size = match ui.texture_size(&elem.text) {
+                    Some(texture) => image_size(texture, elem.scale, screen, scale),
+                    None => return,
+                };
+                let (x, y) = align(pos, size, elem.align);
+
+                ui.image(
+                    &elem.text,
+                    Rect::new(x + offset.0, y + offset.1, size.0, size.1),
+                    [1.0; 4],
+                );
+            }
+            HudType::Text => {
+                let size = if elem.size.x > 0 {
+                    TEXT_SIZE * elem.size.x as f32
+                } else {
+                    TEXT_SIZE
+                };
+
+                draw_text(
+                    ui,
+                    font,
+                    &strip_escapes(&elem.text),
+                    (pos.0 + offset.0, pos.1 + offset.1),
+                    elem.align,
+                    size * scale,
+                    rgb(elem.number),
+                );
+            }
+            HudType::Statbar => {
+                let bar = Statbar {
+                    texture: &elem.text,
+                    background: &elem.text_2,
+                    count: elem.number,
+                    max: elem.item,
+                    dir: elem.dir.into(),
+                    size: elem.size,
+                    offset: elem.offset,
+                };
+                self.draw_statbar(&bar, pos, scale, ui);
+            }
+            HudType::Inventory => {
+                let list = match ctx.inventory.list(&elem.text) {
+                    Some(list) => list,
+                    None => return,
+                };
+
+                let count = (elem.number as usize).min(list.stacks.len());
+                let origin = (pos.0 + offset.0, pos.1 + offset.1);
+
+                // item is the selected slot counting from 1, 0 selects none
+                let slots = hotbar::slot_rects(origin, elem.dir.into(), count as u16, scale)
+                    .into_iter()
+                    .zip(&list.stacks)
+                    .enumerate()
+                    .map(|(i, (rect, stack))| (rect, stack, i as u32 + 1 == elem.item));
+
+                self.hotbar.draw_items(ctx.items, ctx.map, slots, scale, ui);
+            }
+            HudType::Waypoint => {
+                let mut text = strip_escapes(&elem.name);
+
+                // item is the precision of the distance plus one, 0 for the default of 10
+                let precision = match elem.item {
+                    0 => 10.0,
+                    item => item as f32 - 1.0,
+                };
+                if precision > 0.0 {
+                    let dist = (Point3::from_vec(elem.world_pos) - ctx.camera.pos).magnitude();
+                    let dist = (dist * precision).floor() / precision;
+                    text += &format!("\n{dist}{}", elem.text);
+                }
+
+                draw_text(
+                    ui,
+                    font,
+                    &text,
+                    (pos.0 + elem.offset.x, pos.1 + elem.offset.y),
+                    elem.align,
+                    TEXT_SIZE * scale,
+                    rgb(elem.number),
+                );
+            }
+            HudType::Compass => {
+                let size = element_size(elem.size, screen, 1.0);
+                if size.0 <= 0.0 || size.1 <= 0.0 {
+                    return;
+                }
+
+                let (x, y) = align(pos, size, elem.align);
+                let rect = Rect::new(x + offset.0, y + offset.1, size.0, size.1);
+                let angle = elem.number as f32 - bearing(ctx.camera);
+
+                match elem.dir {
+                    0 | 1 => {
+                        let angle = if elem.dir == 0 { -angle } else { angle };
+                        ui.image_rotated(&elem.text, rect, Deg(angle).into(), [1.0; 4]);
+                    }
+                    _ => {
+                        let texture = match ui.texture_size(&elem.text) {
+                            Some(texture) => texture,
+                            None => return,
+                        };
+
+                        let angle = if elem.dir == 2 { angle } else { -angle };
+                        for (rect, uv) in compass_tiles(rect, texture, elem.scale, angle) {
+                            ui.image_region(&elem.text, rect, uv, [1.0; 4]);
+                        }
+                    }
+                }
+            }
+            HudType::Minimap => {
+                if !self.shows(HudFlag::Minimap) {
+                    return;
+                }
+
+                let size = element_size(elem.size, screen, scale);
+                if size.0 <= 0.0 || size.1 <= 0.0 {
+                    return;
+                }
+
+                let (x, y) = align(pos, size, elem.align);
+                let rect = Rect::new(x + offset.0, y + offset.1, size.0, size.1);
+                self.minimap.render(rect, bearing(ctx.camera), ui);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector3;
+
+    fn assert_near(a: (f32, f32), b: (f32, f32)) {
+        assert!(
+            (a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn anchor_rounds_to_pixels() {
+        assert_eq!(
+            anchor(Vector2::new(0.5, 0.5), (801.0, 600.0)),
+            (401.0, 300.0)
+        );
+        assert_eq!(anchor(Vector2::new(0.0, 1.0), (800.0, 600.0)), (0.0, 600.0));
+    }
+
+    #[test]
+    fn align_offsets() {
+        let size = (40.0, 20.0);
+        assert_eq!(
+            align((100.0, 100.0), size, Vector2::new(-1.0, -1.0)),
+            (60.0, 80.0)
+        );
+        assert_eq!(
+            align((100.0, 100.0), size, Vector2::new(0.0, 0.0)),
+            (80.0, 90.0)
+        );
+        assert_eq!(
+            align((100.0, 100.0), size, Vector2::new(1.0, 1.0)),
+            (100.0, 100.0)
+        );
+    }
+
+    #[test]
+    fn image_size_scales() {
+        let screen = (800.0, 600.0);
+        assert_eq!(
+            image_size((16, 8), Vector2::new(2.0, 3.0), screen, 1.5),
+            (48.0, 36.0)
+        );
+        // negative scales are percentages of the screen, regardless of the texture and hud scale
+        assert_eq!(
+            image_size((16, 8), Vector2::new(-50.0, -10.0), screen, 1.5),
+            (400.0, 60.0)
+        );
+        assert_eq!(
+            image_size((16, 8), Vector2::new(-25.0, 1.0), screen, 2.0),
+            (200.0, 16.0)
+        );
+    }
+
+    #[test]
+    fn element_size_scales() {
+        let screen = (800.0, 600.0);
+        assert_eq!(
+            element_size(Vector2::new(10, 20), screen, 2.0),
+            (20.0, 40.0)
+        );
+        assert_eq!(
+            element_size(Vector2::new(-100, -50), screen, 2.0),
+            (800.0, 300.0)
+        );
+    }
+
+    #[test]
+    fn direction_from_param() {
+        assert_eq!(Direction::from(0), Direction::LeftRight);
+        assert_eq!(Direction::from(1), Direction::RightLeft);
+        assert_eq!(Direction::from(2), Direction::TopBottom);
+        assert_eq!(Direction::from(3), Direction::BottomTop);
+        assert_eq!(Direction::from(7), Direction::LeftRight);
+    }
+
+    #[test]
+    fn statbar_full_icons() {
+        let icons = statbar_icons((10.0, 20.0), (24.0, 24.0), Direction::LeftRight, 4);
+        assert_eq!(
+            icons,
+            vec![
+                (Rect::new(10.0, 20.0, 24.0, 24.0), Rect::UNIT),
+                (Rect::new(34.0, 20.0, 24.0, 24.0), Rect::UNIT),
+            ]
+        );
+
+        let icons = statbar_icons((10.0, 20.0), (24.0, 24.0), Direction::BottomTop, 4);
+        assert_eq!(icons[1].0, Rect::new(10.0, -4.0, 24.0, 24.0));
+    }
+
+    #[test]
+    fn statbar_half_icons() {
+        let half = |dir, count| {
+            *statbar_icons((10.0, 20.0), (24.0, 24.0), dir, count)
+                .last()
+                .unwrap()
+        };
+
+        let icons = statbar_icons((10.0, 20.0), (24.0, 24.0), Direction::LeftRight, 5);
+        assert_eq!(icons.len(), 3);
+        assert_eq!(
+            icons[2],
+            (
+                Rect::new(58.0, 20.0, 12.0, 24.0),
+                Rect::new(0.0, 0.0, 0.5, 1.0)
+            )
+        );
+
+        // the half icon is the half closer to the start of the bar
+        assert_eq!(
+            half(Direction::RightLeft, 3),
+            (
+                Rect::new(-2.0, 20.0, 12.0, 24.0),
+                Rect::new(0.5, 0.0, 0.5, 1.0)
+            )
+        );
+        assert_eq!(
+            half(Direction::TopBottom, 1),
+            (
+                Rect::new(10.0, 20.0, 24.0, 12.0),
+                Rect::new(0.0, 0.0, 1.0, 0.5)
+            )
+        );
+        assert_eq!(
+            half(Direction::BottomTop, 1),
+            (
+                Rect::new(10.0, 32.0, 24.0, 12.0),
+                Rect::new(0.0, 0.5, 1.0, 0.5)
+            )
+        );
+    }
+
+    fn view_proj() -> Matrix4<f32> {
+        // camera at the origin looking towards -z
+        cgmath::perspective(Deg(90.0), 1.0, 0.1, 100.0)
+            * Matrix4::look_at_rh(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(0.0, 0.0, -1.0),
+                Vector3::unit_y(),
+            )
+    }
+
+    #[test]
+    fn project_in_front() {
+        let screen = (200.0, 100.0);
+        let pos = project(view_proj(), Point3::new(0.0, 0.0, -5.0), screen).unwrap();
+        assert_near(pos, (100.0, 50.0));
+
+        // at the edges of the 90 degree field of view
+        let pos = project(view_proj(), Point3::new(2.0, 2.0, -2.0), screen).unwrap();
+        assert_near(pos, (200.0, 0.0));
+        let pos = project(view_proj(), Point3::new(-1.0, -1.0, -1.0), screen).unwrap();
+        assert_near(pos, (0.0, 100.0));
+    }
+
+    #[test]
+    fn project_behind() {
+        let screen = (200.0, 100.0);
+        assert_eq!(
+            project(view_proj(), Point3::new(0.0, 0.0, 5.0), screen),
+            None
+        );
+        assert_eq!(
+            project(view_proj(), Point3::new(3.0, -1.0, 1.0), screen),
+            None
+        );
+        assert_eq!(
+            project(view_proj(), Point3::new(0.0, 0.0, 0.0), screen),
+            None
+        );
+    }
+
+    #[test]
+    fn compass_tiles_cover_rect() {
+        let rect = Rect::new(0.0, 0.0, 100.0, 20.0);
+        let tiles = compass_tiles(rect, (40, 20), Vector2::new(1.0, 1.0), 0.0);
+        assert_eq!(
+            tiles,
+            vec![
+                (
+                    Rect::new(0.0, 0.0, 30.0, 20.0),
+                    Rect::new(0.25, 0.0, 0.75, 1.0)
+                ),
+                (Rect::new(30.0, 0.0, 40.0, 20.0), Rect::UNIT),
+                (
+                    Rect::new(70.0, 0.0, 30.0, 20.0),
+                    Rect::new(0.0, 0.0, 0.75, 1.0)
+                ),
+            ]
+        );
+
+        // turning by a full circle moves the texture by its width
+        for angle in [90.0, -45.0, 360.0] {
+            let tiles = compass_tiles(rect, (40, 20), Vector2::new(1.0, 1.0), angle);
+            let width: f32 = tiles.iter().map(|(rect, _)| rect.w).sum();
+            assert!((width - 100.0).abs() < 1e-3);
+            assert!(tiles
+                .iter()
+                .all(|(tile, _)| tile.x >= 0.0 && tile.x + tile.w <= 100.0));
+        }
+
+        assert!(compass_tiles(rect, (40, 20), Vector2::new(0.0, 1.0), 0.0).is_empty());
+    }
+
+    #[test]
+    fn strip_translation_escapes() {
+        assert_eq!(strip_escapes("\u{1b}(T@default)Hello\u{1b}E"), "Hello");
+        assert_eq!(strip_escapes("\u{1b}(c@#ff0000)red"), "red");
+        assert_eq!(strip_escapes("plain"), "plain");
+    }
+}
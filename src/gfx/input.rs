@@ -0,0 +1,406 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+use winit::event::{MouseButton, VirtualKeyCode as Key};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Forward,
+    Backward,
+    Left,
+    Right,
+    Jump,
+    Sneak,
+    Aux1,
+    Dig,
+    Place,
+    Zoom,
+    Drop,
+    Inventory,
+    HotbarNext,
+    HotbarPrev,
+    Slot1,
+    Slot2,
+    Slot3,
+    Slot4,
+    Slot5,
+    Slot6,
+    Slot7,
+    Slot8,
+    Slot9,
+    ToggleFly,
+    ToggleFast,
+    ToggleNoclip,
+    TogglePitchMove,
+    ToggleCinematic,
+    ChangeCamera,
+    ToggleDebug,
+}
+
+impl Action {
+    pub const ALL: [Action; 30] = [
+        Action::Forward,
+        Action::Backward,
+        Action::Left,
+        Action::Right,
+        Action::Jump,
+        Action::Sneak,
+        Action::Aux1,
+        Action::Dig,
+        Action::Place,
+        Action::Zoom,
+        Action::Drop,
+        Action::Inventory,
+        Action::HotbarNext,
+        Action::HotbarPrev,
+        Action::Slot1,
+        Action::Slot2,
+        Action::Slot3,
+        Action::Slot4,
+        Action::Slot5,
+        Action::Slot6,
+        Action::Slot7,
+        Action::Slot8,
+        Action::Slot9,
+        Action::ToggleFly,
+        Action::ToggleFast,
+        Action::ToggleNoclip,
+        Action::TogglePitchMove,
+        Action::ToggleCinematic,
+        Action::ChangeCamera,
+        Action::ToggleDebug,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Forward => "Forward",
+            Action::Backward => "Backward",
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::Jump => "Jump",
+            Action::Sneak => "Sneak",
+            Action::Aux1 => "Aux1",
+            Action::Dig => "Dig",
+            Action::Place => "Place",
+            Action::Zoom => "Zoom",
+            Action::Drop => "Drop item",
+            Action::Inventory => "Inventory",
+            Action::HotbarNext => "Next item",
+            Action::HotbarPrev => "Previous item",
+            Action::Slot1 => "Hotbar slot 1",
+            Action::Slot2 => "Hotbar slot 2",
+            Action::Slot3 => "Hotbar slot 3",
+            Action::Slot4 => "Hotbar slot 4",
+            Action::Slot5 => "Hotbar slot 5",
+            Action::Slot6 => "Hotbar slot 6",
+            Action::Slot7 => "Hotbar slot 7",
+            Action::Slot8 => "Hotbar slot 8",
+            Action::Slot9 => "Hotbar slot 9",
+            Action::ToggleFly => "Toggle fly",
+            Action::ToggleFast => "Toggle fast",
+            Action::ToggleNoclip => "Toggle noclip",
+            Action::TogglePitchMove => "Toggle pitch move",
+            Action::ToggleCinematic => "Toggle cinematic camera",
+            Action::ChangeCamera => "Change camera",
+            Action::ToggleDebug => "Toggle debug info",
+        }
+    }
+
+    /// index of the hotbar slot selected by this action
+    pub fn hotbar_slot(self) -> Option<u16> {
+        let slots = [
+            Action::Slot1,
+            Action::Slot2,
+            Action::Slot3,
+            Action::Slot4,
+            Action::Slot5,
+            Action::Slot6,
+            Action::Slot7,
+            Action::Slot8,
+            Action::Slot9,
+        ];
+
+        slots
+            .iter()
+            .position(|&slot| slot == self)
+            .map(|i| i as u16)
+    }
+}
+
+/// a physical input that can be bound to an action
+///
+/// stored as a string in the settings file: key names as in winit's VirtualKeyCode
+/// ("W", "Space", "LShift"), "scancode:<n>" for keys independent of the keyboard layout,
+/// "mouse:left", "mouse:right", "mouse:middle", "mouse:<n>", "scroll_up" and "scroll_down"
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub enum Input {
+    Key(Key),
+    Scancode(u32),
+    Mouse(MouseButton),
+    ScrollUp,
+    ScrollDown,
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Input::Key(key) => write!(f, "{key:?}"),
+            Input::Scancode(code) => write!(f, "scancode:{code}"),
+            Input::Mouse(MouseButton::Left) => write!(f, "mouse:left"),
+            Input::Mouse(MouseButton::Right) => write!(f, "mouse:right"),
+            Input::Mouse(MouseButton::Middle) => write!(f, "mouse:middle"),
+            Input::Mouse(MouseButton::Other(n)) => write!(f, "mouse:{n}"),
+            Input::ScrollUp => write!(f, "scroll_up"),
+            Input::ScrollDown => write!(f, "scroll_down"),
+        }
+    }
+}
+
+impl FromStr for Input {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid input: {s}");
+
+        if let Some(code) = s.strip_prefix("scancode:") {
+            return code.parse().map(Input::Scancode).map_err(|_| invalid());
+        }
+
+        if let Some(button) = s.strip_prefix("mouse:") {
+            return Ok(Input::Mouse(match button {
+                "left" => MouseButton::Left,
+                "right" => MouseButton::Right,
+                "middle" => MouseButton::Middle,
+                n => MouseButton::Other(n.parse().map_err(|_| invalid())?),
+            }));
+        }
+
+        match s {
+            "scroll_up" => Ok(Input::ScrollUp),
+            "scroll_down" => Ok(Input::ScrollDown),
+            key => Key::deserialize(
+                serde::de::value::StrDeserializer::<serde::de::value::Error>::new(key),
+            )
+            .map(Input::Key)
+            .map_err(|_| invalid()),
+        }
+    }
+}
+
+impl TryFrom<String> for Input {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Input> for String {
+    fn from(input: Input) -> Self {
+        input.to_string()
+    }
+}
+
+/// maps inputs to actions, actions missing from the settings file keep their default
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(from = "BTreeMap<Action, Input>", into = "BTreeMap<Action, Input>")]
+pub struct Bindings(BTreeMap<Action, Input>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Action::*;
+
+        Self(BTreeMap::from([
+            (Forward, Input::Key(Key::W)),
+            (Backward, Input::Key(Key::S)),
+            (Left, Input::Key(Key::A)),
+            (Right, Input::Key(Key::D)),
+            (Jump, Input::Key(Key::Space)),
+            (Sneak, Input::Key(Key::LShift)),
+            (Aux1, Input::Key(Key::E)),
+            (Dig, Input::Mouse(MouseButton::Left)),
+            (Place, Input::Mouse(MouseButton::Right)),
+            (Zoom, Input::Key(Key::Z)),
+            (Drop, Input::Key(Key::Q)),
+            (Inventory, Input::Key(Key::I)),
+            (HotbarNext, Input::ScrollDown),
+            (HotbarPrev, Input::ScrollUp),
+            (Slot1, Input::Key(Key::Key1)),
+            (Slot2, Input::Key(Key::Key2)),
+            (Slot3, Input::Key(Key::Key3)),
+            (Slot4, Input::Key(Key::Key4)),
+            (Slot5, Input::Key(Key::Key5)),
+            (Slot6, Input::Key(Key::Key6)),
+            (Slot7, Input::Key(Key::Key7)),
+            (Slot8, Input::Key(Key::Key8)),
+            (Slot9, Input::Key(Key::Key9)),
+            (ToggleFly, Input::Key(Key::K)),
+            (ToggleFast, Input::Key(Key::J)),
+            (ToggleNoclip, Input::Key(Key::H)),
+            (TogglePitchMove, Input::Key(Key::P)),
+            (ToggleCinematic, Input::Key(Key::F8)),
+            (ChangeCamera, Input::Key(Key::C)),
+            (ToggleDebug, Input::Key(Key::F3)),
+        ]))
+    }
+}
+
+impl From<BTreeMap<Action, Input>> for Bindings {
+    fn from(map: BTreeMap<Action, Input>) -> Self {
+        let mut bindings = Self::default();
+        // defaults using an input the user bound to another action are unbound
+        bindings.0.retain(|action, input| {
+            map.contains_key(action) || !map.values().any(|bound| bound == input)
+        });
+        bindings.0.extend(map);
+        bindings
+    }
+}
+
+impl From<Bindings> for BTreeMap<Action, Input> {
+    fn from(bindings: Bindings) -> Self {
+        bindings.0
+    }
+}
+
+impl Bindings {
+    pub fn get(&self, action: Action) -> Option<Input> {
+        self.0.get(&action).copied()
+    }
+
+    /// binds input to action, an action previously bound to input gets the old binding
+    pub fn set(&mut self, action: Action, input: Input) {
+        let old = self.0.insert(action, input);
+        let other = self
+            .0
+            .iter()
+            .find(|(&other, &bound)| other != action && bound == input)
+            .map(|(&other, _)| other);
+
+        if let Some(other) = other {
+            match old {
+                Some(old) => self.0.insert(other, old),
+                None => self.0.remove(&other),
+            };
+        }
+    }
+
+    pub fn action(&self, input: Input) -> Option<Action> {
+        self.0
+            .iter()
+            .find(|(_, &bound)| bound == input)
+            .map(|(&action, _)| action)
+    }
+
+    /// action for a keyboard event, matching both the key and the scancode
+    pub fn key_action(&self, key: Option<Key>, scancode: u32) -> Option<Action> {
+        key.and_then(|key| self.action(Input::Key(key)))
+            .or_else(|| self.action(Input::Scancode(scancode)))
+    }
+}
+
+/// input of a keyboard event, preferring the layout dependent key
+pub fn key_input(key: Option<Key>, scancode: u32) -> Input {
+    key.map_or(Input::Scancode(scancode), Input::Key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_strings() {
+        let inputs = [
+            (Input::Key(Key::W), "W"),
+            (Input::Key(Key::LShift), "LShift"),
+            (Input::Scancode(30), "scancode:30"),
+            (Input::Mouse(MouseButton::Left), "mouse:left"),
+            (Input::Mouse(MouseButton::Right), "mouse:right"),
+            (Input::Mouse(MouseButton::Middle), "mouse:middle"),
+            (Input::Mouse(MouseButton::Other(4)), "mouse:4"),
+            (Input::ScrollUp, "scroll_up"),
+            (Input::ScrollDown, "scroll_down"),
+        ];
+
+        for (input, s) in inputs {
+            assert_eq!(input.to_string(), s);
+            assert_eq!(s.parse::<Input>(), Ok(input));
+            assert_eq!(serde_yaml::from_str::<Input>(s).unwrap(), input);
+            let yaml = serde_yaml::to_string(&input).unwrap();
+            assert_eq!(serde_yaml::from_str::<Input>(&yaml).unwrap(), input);
+        }
+
+        for invalid in [
+            "",
+            "NotAKey",
+            "scancode:",
+            "scancode:x",
+            "mouse:side",
+            "scroll",
+        ] {
+            assert!(invalid.parse::<Input>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn bindings_round_trip() {
+        let bindings = Bindings::default();
+        let yaml = serde_yaml::to_string(&bindings).unwrap();
+        assert_eq!(serde_yaml::from_str::<Bindings>(&yaml).unwrap(), bindings);
+    }
+
+    #[test]
+    fn loaded_bindings_override_defaults() {
+        let bindings: Bindings = serde_yaml::from_str("jump: scancode:57").unwrap();
+
+        assert_eq!(bindings.get(Action::Jump), Some(Input::Scancode(57)));
+        assert_eq!(bindings.action(Input::Key(Key::Space)), None);
+        assert_eq!(bindings.get(Action::Forward), Some(Input::Key(Key::W)));
+    }
+
+    #[test]
+    fn loaded_bindings_unbind_conflicting_defaults() {
+        // an AZERTY layout moving forward onto the default zoom key
+        let bindings: Bindings = serde_yaml::from_str("forward: Z\nleft: Q").unwrap();
+
+        assert_eq!(bindings.get(Action::Forward), Some(Input::Key(Key::Z)));
+        assert_eq!(bindings.get(Action::Left), Some(Input::Key(Key::Q)));
+        assert_eq!(bindings.get(Action::Zoom), None);
+        assert_eq!(bindings.get(Action::Drop), None);
+        assert_eq!(bindings.action(Input::Key(Key::Z)), Some(Action::Forward));
+        assert_eq!(bindings.action(Input::Key(Key::Q)), Some(Action::Left));
+
+        // both sides of a swap can be given
+        let bindings: Bindings = serde_yaml::from_str("forward: Z\nzoom: W").unwrap();
+        assert_eq!(bindings.get(Action::Forward), Some(Input::Key(Key::Z)));
+        assert_eq!(bindings.get(Action::Zoom), Some(Input::Key(Key::W)));
+    }
+
+    #[test]
+    fn set_swaps_conflicting_bindings() {
+        let mut bindings = Bindings::default();
+
+        bindings.set(Action::Forward, Input::Key(Key::S));
+        assert_eq!(bindings.get(Action::Forward), Some(Input::Key(Key::S)));
+        assert_eq!(bindings.get(Action::Backward), Some(Input::Key(Key::W)));
+
+        bindings.set(Action::Zoom, Input::Scancode(44));
+        assert_eq!(bindings.get(Action::Zoom), Some(Input::Scancode(44)));
+        assert_eq!(bindings.action(Input::Key(Key::Z)), None);
+    }
+
+    #[test]
+    fn key_action_matches_key_or_scancode() {
+        let mut bindings = Bindings::default();
+        bindings.set(Action::Jump, Input::Scancode(57));
+
+        assert_eq!(bindings.key_action(Some(Key::W), 17), Some(Action::Forward));
+        assert_eq!(
+            bindings.key_action(Some(Key::Space), 57),
+            Some(Action::Jump)
+        );
+        assert_eq!(bindings.key_action(None, 57), Some(Action::Jump));
+        assert_eq!(bindings.key_action(None, 99), None);
+    }
+}
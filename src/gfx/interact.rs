@@ -0,0 +1,197 @@
+use super::{camera::CameraInput, player::NodeMap, raycast::PointedNode};
+use cgmath::Point3;
+use collision::Aabb3;
+use mt_net::ToolCaps;
+use std::collections::HashMap;
+
+// delay before the next node is dug while the dig button stays pressed
+const DIG_REPEAT: f32 = 0.15;
+// interval of repeated placing while the place button stays pressed
+const PLACE_REPEAT: f32 = 0.25;
+
+/// an action sent to the server in an Interact packet
+#[derive(Debug, Clone, PartialEq)]
+pub enum Interaction {
+    StartDigging(PointedNode),
+    StopDigging(PointedNode),
+    Dug(PointedNode),
+    Place(PointedNode),
+    /// place pressed while pointing at nothing
+    Activate,
+    /// dig pressed with an item that has on_use, pointing at a node or nothing
+    Use(Option<PointedNode>),
+}
+
+impl Interaction {
+    pub fn to_packet(&self) -> (mt_net::Interaction, mt_net::PointedThing) {
+        use mt_net::Interaction as Action;
+
+        match self {
+            Interaction::StartDigging(node) => (Action::Dig, node.pointed_thing()),
+            Interaction::StopDigging(node) => (Action::StopDigging, node.pointed_thing()),
+            Interaction::Dug(node) => (Action::Dug, node.pointed_thing()),
+            Interaction::Place(node) => (Action::Place, node.pointed_thing()),
+            Interaction::Activate => (Action::Activate, mt_net::PointedThing::None),
+            Interaction::Use(node) => (
+                Action::Use,
+                node.as_ref()
+                    .map_or(mt_net::PointedThing::None, PointedNode::pointed_thing),
+            ),
+        }
+    }
+}
+
+/// seconds it takes to dig a node with the given groups, None if the tool can't dig it.
+/// follows upstream's getDigParams, ignoring wear
+pub fn dig_time(caps: Option<&ToolCaps>, groups: &HashMap<String, i16>) -> Option<f32> {
+    match groups.get("dig_immediate") {
+        Some(2) => return Some(0.5),
+        Some(3) => return Some(0.0),
+        _ => {}
+    }
+
+    let level = groups.get("level").copied().unwrap_or(0);
+
+    caps?
+        .group_caps
+        .iter()
+        .filter_map(|(group, cap)| {
+            let rating = *groups.get(group)?;
+            let time = *cap.times.get(&rating)?;
+
+            let level_diff = cap.max_lvl - level;
+            if level_diff < 0 {
+                return None;
+            }
+
+            Some(time / level_diff.max(1) as f32)
+        })
+        .min_by(f32::total_cmp)
+}
+
+/// where a node placed against pointed ends up, None if there is no room.
+/// like upstream, buildable_to nodes are replaced instead of built upon
+pub fn place_target(map: &impl NodeMap, pointed: &PointedNode) -> Option<Point3<i32>> {
+    let buildable_to = |pos| map.node(pos).map_or(false, |def| def.buildable_to);
+
+    if buildable_to(pointed.under) {
+        Some(pointed.under)
+    } else if buildable_to(pointed.above) {
+        Some(pointed.above)
+    } else {
+        None
+    }
+}
+
+/// whether the node at pos overlaps aabb, used to avoid predicting nodes inside the player
+pub fn node_intersects(pos: Point3<i32>, aabb: &Aabb3<f32>) -> bool {
+    (0..3).all(|axis| {
+        let center = pos[axis] as f32;
+        aabb.min[axis] < center + 0.5 && aabb.max[axis] > center - 0.5
+    })
+}
+
+struct Dig {
+    node: PointedNode,
+    time: f32,
+    /// None if the node can't be dug, it's only punched then
+    duration: Option<f32>,
+}
+
+/// turns dig and place input into interactions with the pointed node
+#[derive(Default)]
+pub struct Interact {
+    /// tool capabilities of the wielded item, None digs only dig_immediate nodes
+    pub tool_caps: Option<ToolCaps>,
+    /// node_placement_prediction of the wielded item, empty disables prediction
+    pub place_prediction: String,
+    /// whether the wielded item has on_use, it's used instead of digging
+    pub usable: bool,
+    dig: Option<Dig>,
+    dig_cooldown: f32,
+    place_cooldown: f32,
+    was_digging: bool,
+    was_placing: bool,
+}
+
+impl Interact {
+    pub fn update(
+        &mut self,
+        map: &impl NodeMap,
+        input: &CameraInput,
+        pointed: Option<&PointedNode>,
+        dt: f32,
+    ) -> Vec<Interaction> {
+        let mut out = Vec::new();
+
+        self.dig_cooldown = (self.dig_cooldown - dt).max(0.0);
+        self.place_cooldown = (self.place_cooldown - dt).max(0.0);
+
+        let dig_pressed = input.dig && !self.was_digging;
+        self.was_digging = input.dig;
+
+        // stop when the button is released or the player looks at another node
+        if let Some(dig) = &self.dig {
+            if !input.dig || self.usable || pointed.map_or(true, |p| p.under != dig.node.under) {
+                out.push(Interaction::StopDigging(dig.node.clone()));
+                self.dig = None;
+            }
+        }
+
+        // like upstream, usable items are used once per click instead of digging
+        if self.usable && dig_pressed {
+            out.push(Interaction::Use(pointed.cloned()));
+        }
+
+        if let Some(pointed) =
+            pointed.filter(|_| input.dig && !self.usable && self.dig_cooldown == 0.0)
+        {
+            let dig = self.dig.get_or_insert_with(|| {
+                out.push(Interaction::StartDigging(pointed.clone()));
+
+                Dig {
+                    node: pointed.clone(),
+                    time: 0.0,
+                    duration: map
+                        .node(pointed.under)
+                        .and_then(|def| dig_time(self.tool_caps.as_ref(), &def.groups)),
+                }
+            });
+
+            dig.time += dt;
+
+            if dig.duration.map_or(false, |duration| dig.time >= duration) {
+                out.push(Interaction::Dug(dig.node.clone()));
+                self.dig = None;
+                self.dig_cooldown = DIG_REPEAT;
+            }
+        }
+
+        let place_pressed = input.place && !self.was_placing;
+        self.was_placing = input.place;
+
+        if input.place && (place_pressed || self.place_cooldown == 0.0) {
+            self.place_cooldown = PLACE_REPEAT;
+
+            match pointed {
+                Some(pointed) => out.push(Interaction::Place(pointed.clone())),
+                None if place_pressed => out.push(Interaction::Activate),
+                None => {}
+            }
+        }
+
+        out
+    }
+
+    /// digging progress from 0 to 1, for the crack overlay
+    pub fn dig_progress(&self) -> Option<f32> {
+        let dig = self.dig.as_ref()?;
+        let duration = dig.duration?;
+
+        Some(if duration > 0.0 {
+            (dig.time / duration).min(1.0)
+        } else {
+            1.0
+        })
+    }
+}
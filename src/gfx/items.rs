@@ -0,0 +1,106 @@
+use mt_net::{ItemDef, NodeDef, ToolCaps};
+use std::collections::HashMap;
+
+// longer alias chains are treated as cycles
+const MAX_ALIAS_DEPTH: usize = 16;
+
+/// item definitions sent by the server
+#[derive(Default)]
+pub struct ItemRegistry {
+    defs: HashMap<String, ItemDef>,
+    aliases: HashMap<String, String>,
+}
+
+impl ItemRegistry {
+    pub fn new(defs: Vec<ItemDef>, aliases: HashMap<String, String>) -> Self {
+        Self {
+            defs: defs
+                .into_iter()
+                .map(|def| (def.name.clone(), def))
+                .collect(),
+            aliases,
+        }
+    }
+
+    /// follows aliases to the name of a registered item
+    pub fn resolve<'a>(&'a self, mut name: &'a str) -> &'a str {
+        for _ in 0..MAX_ALIAS_DEPTH {
+            if self.defs.contains_key(name) {
+                break;
+            }
+
+            match self.aliases.get(name) {
+                Some(orig) => name = orig,
+                None => break,
+            }
+        }
+
+        name
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ItemDef> {
+        self.defs.get(self.resolve(name))
+    }
+
+    /// the item used when nothing is wielded
+    pub fn hand(&self) -> Option<&ItemDef> {
+        self.get("")
+    }
+
+    /// tool capabilities used when digging with the item, items without any use the hand's
+    pub fn tool_caps(&self, name: &str) -> Option<&ToolCaps> {
+        self.get(name)
+            .and_then(|def| def.tool_caps.as_ref())
+            .or_else(|| self.hand()?.tool_caps.as_ref())
+    }
+
+    /// texture string of the item's inventory image. nodes without one
+    /// get an [inventorycube of their tiles, node is the item's node definition
+    pub fn inventory_texture(&self, name: &str, node: Option<&NodeDef>) -> String {
+        let def = match self.get(name) {
+            Some(def) => def,
+            None => return "unknown_item.png".into(),
+        };
+
+        let mut texture = if !def.inventory_image.is_empty() {
+            def.inventory_image.clone()
+        } else if let Some(node) = node {
+            // modifiers inside [inventorycube use & instead of ^
+            let tile = |i: usize| {
+                node.tiles
+                    .get(i)
+                    .or(node.tiles.last())
+                    .map_or(String::new(), |tile| tile.texture.name.replace('^', "&"))
+            };
+
+            format!("[inventorycube{{{}{{{}{{{}", tile(0), tile(5), tile(2))
+        } else {
+            return "unknown_item.png".into();
+        };
+
+        if !def.inventory_overlay.is_empty() {
+            texture = format!("{texture}^{}", def.inventory_overlay);
+        }
+
+        texture
+    }
+
+    /// texture string of the item in hand, falls back to the inventory image
+    pub fn wield_texture(&self, name: &str) -> Option<String> {
+        let def = self.get(name)?;
+
+        let mut texture = if !def.wield_image.is_empty() {
+            def.wield_image.clone()
+        } else if !def.inventory_image.is_empty() {
+            def.inventory_image.clone()
+        } else {
+            return None;
+        };
+
+        if !def.wield_overlay.is_empty() {
+            texture = format!("{texture}^{}", def.wield_overlay);
+        }
+
+        Some(texture)
+    }
+}
@@ -1,8 +1,10 @@
 mod atlas;
 mod mesh;
+mod shadow;
 
 use super::{
-    camera::Camera, debug_menu::DebugMenu, gpu::Gpu, media::MediaMgr, util::MatrixUniform,
+    camera::Camera, debug_menu::DebugMenu, gpu::Gpu, media::MediaMgr, player::NodeMap,
+    util::MatrixUniform,
 };
 use atlas::create_atlas;
 use cgmath::{prelude::*, Matrix4, Point3, Vector3};
@@ -10,14 +12,19 @@ use collision::{prelude::*, Aabb3, Relation};
 use mesh::{create_mesh, MeshData};
 use mt_net::{MapBlock, NodeDef};
 use serde::{Deserialize, Serialize};
+use shadow::ShadowMap;
 use std::{
     collections::hash_map::{Entry, HashMap},
     ops::{Deref, DerefMut},
-    sync::{Arc, Mutex, RwLock},
+    sync::{Arc, Mutex, RwLock, RwLockReadGuard},
     time::Instant,
 };
 use wgpu::util::DeviceExt;
 
+/// all wave frequencies in the shaders are multiples of 0.1, so they line up again
+/// after a multiple of 20π seconds
+const WAVE_PERIOD: f64 = 1000.0 * std::f64::consts::PI;
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum LeavesMode {
@@ -26,10 +33,17 @@ pub enum LeavesMode {
     Fancy,
 }
 
+pub use shadow::ShadowSettings;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
 pub struct MapRenderSettings {
     pub leaves: LeavesMode,
     pub opaque_liquids: bool,
+    pub waving_leaves: bool,
+    pub waving_plants: bool,
+    pub waving_liquids: bool,
+    pub shadows: ShadowSettings,
 }
 
 impl Default for MapRenderSettings {
@@ -37,6 +51,10 @@ impl Default for MapRenderSettings {
         Self {
             leaves: LeavesMode::Fancy,
             opaque_liquids: false,
+            waving_leaves: true,
+            waving_plants: true,
+            waving_liquids: true,
+            shadows: Default::default(),
         }
     }
 }
@@ -62,9 +80,18 @@ struct DeferredBlock {
     time: Instant,
 }
 
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct MapUniform {
+    time: f32,
+    _padding: [f32; 3],
+}
+
 pub struct MapRender {
     pipeline: wgpu::RenderPipeline,
     atlas: wgpu::BindGroup,
+    uniform: wgpu::Buffer,
+    start: Instant,
     model: wgpu::BindGroupLayout,
     blocks: Arc<RwLock<HashMap<Point3<i16>, Arc<MapBlock>>>>,
     blocks_defer: HashMap<Point3<i16>, DeferredBlock>,
@@ -74,6 +101,7 @@ pub struct MapRender {
     meshgen_channel: crossbeam_channel::Sender<Point3<i16>>,
     queue_consume: MeshQueue,
     queue_produce: Arc<Mutex<MeshQueue>>,
+    shadow: Option<ShadowMap>,
 }
 
 #[repr(C)]
@@ -82,11 +110,12 @@ struct Vertex {
     pos: [f32; 3],
     tex_coords: [f32; 2],
     light: f32,
+    waving: f32,
 }
 
 impl Vertex {
-    const ATTRIBS: [wgpu::VertexAttribute; 3] =
-        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32];
+    const ATTRIBS: [wgpu::VertexAttribute; 4] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32, 3 => Float32];
 
     fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         wgpu::VertexBufferLayout {
@@ -137,6 +166,27 @@ fn block_float_pos(pos: Point3<i16>) -> Point3<f32> {
     pos.cast::<f32>().unwrap() * 16.0
 }
 
+/// read-only snapshot of the loaded map, holds a read lock on the blocks
+pub struct MapView<'a> {
+    blocks: RwLockReadGuard<'a, HashMap<Point3<i16>, Arc<MapBlock>>>,
+    nodes: &'a [Option<Box<NodeDef>>],
+}
+
+impl MapView<'_> {
+    pub fn content(&self, pos: Point3<i32>) -> Option<u16> {
+        let block = self.blocks.get(&pos.map(|x| x.div_euclid(16) as i16))?;
+        let local = pos.map(|x| x.rem_euclid(16) as usize);
+
+        Some(block.param_0[local.x | (local.y << 4) | (local.z << 8)])
+    }
+}
+
+impl NodeMap for MapView<'_> {
+    fn node(&self, pos: Point3<i32>) -> Option<&NodeDef> {
+        self.nodes[self.content(pos)? as usize].as_deref()
+    }
+}
+
 impl MapRender {
     pub fn render<'a>(
         &'a self,
@@ -147,6 +197,9 @@ impl MapRender {
         pass.set_pipeline(&self.pipeline);
         pass.set_bind_group(0, &self.atlas, &[]);
         pass.set_bind_group(1, &camera.uniform.bind_group, &[]);
+        if let Some(shadow) = &self.shadow {
+            pass.set_bind_group(3, &shadow.bind_group, &[]);
+        }
 
         let mut blend = Vec::new();
 
@@ -190,7 +243,113 @@ impl MapRender {
         }
     }
 
+    /// id and definition of the node named name
+    pub fn node_by_name(&self, name: &str) -> Option<(u16, &NodeDef)> {
+        self.meshgen_info
+            .nodes
+            .iter()
+            .enumerate()
+            .find_map(|(id, def)| {
+                Some((id as u16, def.as_deref()?)).filter(|(_, def)| def.name == name)
+            })
+    }
+
+    /// changes a node ahead of the server, used for client side prediction
+    pub fn set_node(&mut self, pos: Point3<i32>, content: u16, param2: u8) {
+        let block_pos = pos.map(|x| x.div_euclid(16) as i16);
+        let local = pos.map(|x| x.rem_euclid(16) as usize);
+        let index = local.x | (local.y << 4) | (local.z << 8);
+
+        let mut blocks = self.blocks.write().unwrap();
+        let block = match blocks.get_mut(&block_pos) {
+            Some(block) => Arc::make_mut(block),
+            None => return,
+        };
+
+        block.param_0[index] = content;
+        block.param_2[index] = param2;
+
+        // neighbors see this node's faces too
+        let mut remesh = vec![block_pos];
+        for axis in 0..3 {
+            let mut offset = Vector3::zero();
+            match local[axis] {
+                0 => offset[axis] = -1,
+                15 => offset[axis] = 1,
+                _ => continue,
+            }
+            if blocks.contains_key(&(block_pos + offset)) {
+                remesh.push(block_pos + offset);
+            }
+        }
+        drop(blocks);
+
+        for pos in remesh {
+            if !self.blocks_defer.contains_key(&pos) {
+                self.meshgen_channel.send(pos).ok();
+            }
+        }
+    }
+
+    pub fn view(&self) -> MapView<'_> {
+        MapView {
+            blocks: self.blocks.read().unwrap(),
+            nodes: &self.meshgen_info.nodes,
+        }
+    }
+
+    pub fn render_shadows(&self, encoder: &mut wgpu::CommandEncoder) {
+        let shadow = match &self.shadow {
+            Some(x) => x,
+            None => return,
+        };
+
+        let mut pass = match shadow.pass(encoder) {
+            Some(x) => x,
+            None => return,
+        };
+
+        pass.set_bind_group(0, &self.atlas, &[]);
+
+        // blocks outside the shadow area may still cast shadows into it
+        let reach = shadow.distance() * 2.0 + 14.0;
+
+        for (&pos, model) in self.block_models.iter() {
+            let center = block_float_pos(pos) + Vector3::new(7.5, 7.5, 7.5);
+
+            if center.distance(shadow.center) > reach {
+                continue;
+            }
+
+            if let Some(mesh) = &model.mesh {
+                mesh.render(&mut pass, &model.transform);
+            }
+        }
+    }
+
+    pub fn update_shadows(&mut self, gpu: &Gpu, camera: &Camera, light_dir: Vector3<f32>) {
+        if let Some(shadow) = &mut self.shadow {
+            shadow.update(gpu, camera, light_dir);
+        }
+    }
+
+    pub fn set_shadow_intensity(&mut self, intensity: f32) {
+        if let Some(shadow) = &mut self.shadow {
+            shadow.strength = intensity;
+        }
+    }
+
     pub fn update(&mut self, gpu: &Gpu) {
+        gpu.queue.write_buffer(
+            &self.uniform,
+            0,
+            bytemuck::cast_slice(&[MapUniform {
+                // wrap around to keep float precision in long sessions
+                time: (self.start.elapsed().as_secs_f64() % WAVE_PERIOD) as f32,
+                _padding: [0.0; 3],
+            }]),
+        );
+
         for (pos, _) in self
             .blocks_defer
             .drain_filter(|_, v| v.time.elapsed().as_millis() > 100)
@@ -281,6 +440,7 @@ impl MapRender {
         camera: &Camera,
         media: &MediaMgr,
         mut nodes: HashMap<u16, NodeDef>,
+        settings: &MapRenderSettings,
     ) -> Self {
         let (atlas_img, atlas_slices) = create_atlas(&mut nodes, media);
 
@@ -351,10 +511,28 @@ impl MapRender {
                             ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                             count: None,
                         },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::VERTEX,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
                     ],
                     label: Some("atlas.bind_group_layout"),
                 });
 
+        let uniform = gpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("map.buffer"),
+                contents: bytemuck::cast_slice(&[<MapUniform as bytemuck::Zeroable>::zeroed()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
         let atlas_bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &atlas_bind_group_layout,
             entries: &[
@@ -366,25 +544,42 @@ impl MapRender {
                     binding: 1,
                     resource: wgpu::BindingResource::Sampler(&atlas_sampler),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform.as_entire_binding(),
+                },
             ],
             label: Some("atlas.bind_group"),
         });
 
         let model_bind_group_layout = MatrixUniform::layout(&gpu.device, "mapblock");
 
+        let shadow = ShadowMap::new(
+            gpu,
+            &atlas_bind_group_layout,
+            &model_bind_group_layout,
+            &settings.shadows,
+        );
+
         let shader = gpu
             .device
             .create_shader_module(wgpu::include_wgsl!("../../assets/shaders/map.wgsl"));
 
+        let mut bind_group_layouts = vec![
+            &atlas_bind_group_layout,
+            &model_bind_group_layout,
+            &camera.layout,
+        ];
+
+        if let Some(shadow) = &shadow {
+            bind_group_layouts.push(&shadow.layout);
+        }
+
         let pipeline_layout = gpu
             .device
             .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: None,
-                bind_group_layouts: &[
-                    &atlas_bind_group_layout,
-                    &model_bind_group_layout,
-                    &camera.layout,
-                ],
+                bind_group_layouts: &bind_group_layouts,
                 push_constant_ranges: &[],
             });
 
@@ -395,14 +590,22 @@ impl MapRender {
                 layout: Some(&pipeline_layout),
                 vertex: wgpu::VertexState {
                     module: &shader,
-                    entry_point: "vs_main",
+                    entry_point: if shadow.is_some() {
+                        "vs_shadowed"
+                    } else {
+                        "vs_main"
+                    },
                     buffers: &[Vertex::desc()],
                 },
                 fragment: Some(wgpu::FragmentState {
                     module: &shader,
-                    entry_point: "fs_main",
+                    entry_point: if shadow.is_some() {
+                        "fs_shadowed"
+                    } else {
+                        "fs_main"
+                    },
                     targets: &[Some(wgpu::ColorTargetState {
-                        format: gpu.config.format,
+                        format: gpu.scene_format(),
                         blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                         write_mask: wgpu::ColorWrites::ALL,
                     })],
@@ -423,11 +626,7 @@ impl MapRender {
                     stencil: wgpu::StencilState::default(),
                     bias: wgpu::DepthBiasState::default(),
                 }),
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
+                multisample: gpu.multisample(),
                 multiview: None,
             });
 
@@ -446,7 +645,7 @@ impl MapRender {
             let input = meshgen_rx.clone();
             let output = meshgen_queue.clone();
             let info = meshgen_info.clone();
-            let config = Default::default();
+            let config = settings.clone();
             let blocks = blocks.clone();
 
             meshgen_threads.push(std::thread::spawn(move || {
@@ -490,6 +689,8 @@ impl MapRender {
         Self {
             pipeline,
             atlas: atlas_bind_group,
+            uniform,
+            start: Instant::now(),
             model: model_bind_group_layout,
             blocks,
             blocks_defer: HashMap::new(),
@@ -499,12 +700,13 @@ impl MapRender {
             meshgen_channel: meshgen_tx,
             queue_consume: HashMap::new(), // store this to keep capacity/allocations around
             queue_produce: meshgen_queue,
+            shadow,
         }
     }
 }
 
 #[rustfmt::skip]
-const CUBE: [[([f32; 3], [f32; 2]); 6]; 6] = [
+pub(super) const CUBE: [[([f32; 3], [f32; 2]); 6]; 6] = [
 	[
 		([-0.5,  0.5, -0.5], [ 0.0,  1.0]),
 		([ 0.5,  0.5,  0.5], [ 1.0,  0.0]),
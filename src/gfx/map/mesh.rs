@@ -62,6 +62,14 @@ pub(super) fn create_mesh(
             _ => 1.0,
         };
 
+        // see upstream's WaveType: 1 = plant tops, 2 = leaves, 3 = liquids
+        let waving = match def.waving as u8 {
+            1 if settings.waving_plants => 1.0,
+            2 if settings.waving_leaves => 2.0,
+            3 if settings.waving_liquids => 3.0,
+            _ => 0.0,
+        };
+
         let vertices = if def.alpha == mt_net::Alpha::Blend {
             &mut buffer.vertices_blend
         } else {
@@ -87,6 +95,7 @@ pub(super) fn create_mesh(
                     pos: [point.x, point.y, point.z],
                     tex_coords: texture[vertex.0],
                     light,
+                    waving: if vertex.1 .0[1] > 0.0 { waving } else { 0.0 },
                 });
             };
 
@@ -144,6 +153,7 @@ pub(super) fn create_mesh(
                         pos: array(|i| pos[i] as f32 + vertex.1 .0[i]),
                         tex_coords: texture[vertex.0],
                         light,
+                        waving,
                     });
                 };
 
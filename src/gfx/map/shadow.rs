@@ -0,0 +1,329 @@
+use super::{super::camera::Camera, super::gpu::Gpu, super::util::MatrixUniform, Vertex};
+use cgmath::{prelude::*, Matrix4, Point3, Vector3};
+use serde::{Deserialize, Serialize};
+use wgpu::util::DeviceExt;
+
+#[rustfmt::skip]
+const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    pub resolution: u32,
+    pub distance: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            resolution: 2048,
+            distance: 64.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShadowUniform {
+    light_view_proj: [[f32; 4]; 4],
+    // x: strength, y: texel size, z: depth bias
+    params: [f32; 4],
+}
+
+pub(super) struct ShadowMap {
+    pipeline: wgpu::RenderPipeline,
+    depth_texture: wgpu::TextureView,
+    light: MatrixUniform,
+    uniform: wgpu::Buffer,
+    pub layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    pub center: Point3<f32>,
+    pub strength: f32,
+    settings: ShadowSettings,
+    light_visible: bool,
+}
+
+impl ShadowMap {
+    pub fn new(
+        gpu: &Gpu,
+        atlas_layout: &wgpu::BindGroupLayout,
+        model_layout: &wgpu::BindGroupLayout,
+        settings: &ShadowSettings,
+    ) -> Option<Self> {
+        if !settings.enabled {
+            return None;
+        }
+
+        if !gpu
+            .adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPARISON_SAMPLERS)
+        {
+            eprintln!("adapter does not support comparison samplers, disabling shadows");
+            return None;
+        }
+
+        let size = settings
+            .resolution
+            .clamp(256, gpu.device.limits().max_texture_dimension_2d);
+
+        let depth_texture = gpu
+            .device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("shadow_map"),
+                size: wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Depth32Float,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = gpu.device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let uniform = gpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("shadow.buffer"),
+                contents: bytemuck::cast_slice(&[<ShadowUniform as bytemuck::Zeroable>::zeroed()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        let layout = gpu
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Depth,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                        count: None,
+                    },
+                ],
+                label: Some("shadow.bind_group_layout"),
+            });
+
+        let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&depth_texture),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("shadow.bind_group"),
+        });
+
+        let light_layout = MatrixUniform::layout(&gpu.device, "light");
+        let light = MatrixUniform::new(
+            &gpu.device,
+            &light_layout,
+            Matrix4::identity(),
+            "light",
+            true,
+        );
+
+        let shader = gpu
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../../../assets/shaders/shadow.wgsl"));
+
+        let pipeline_layout = gpu
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[atlas_layout, &light_layout, model_layout],
+                push_constant_ranges: &[],
+            });
+
+        let pipeline = gpu
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("shadow"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState {
+                        constant: 2,
+                        slope_scale: 2.0,
+                        clamp: 0.0,
+                    },
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            });
+
+        Some(Self {
+            pipeline,
+            depth_texture,
+            light,
+            uniform,
+            layout,
+            bind_group,
+            center: Point3::origin(),
+            // no shadows until the server sets an intensity
+            strength: 0.0,
+            settings: ShadowSettings {
+                resolution: size,
+                ..settings.clone()
+            },
+            light_visible: false,
+        })
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.settings.distance
+    }
+
+    pub fn update(&mut self, gpu: &Gpu, camera: &Camera, light_dir: Vector3<f32>) {
+        let distance = self.settings.distance;
+        // fade shadows out as the light source approaches the horizon
+        let fade = (light_dir.y / 0.1).clamp(0.0, 1.0);
+
+        self.light_visible = fade > 0.0;
+        if !self.light_visible {
+            gpu.queue.write_buffer(
+                &self.uniform,
+                0,
+                bytemuck::cast_slice(&[<ShadowUniform as bytemuck::Zeroable>::zeroed()]),
+            );
+            return;
+        }
+
+        let rot = Matrix4::look_at_dir(Point3::origin(), -light_dir, Vector3::unit_z());
+
+        // snap the light position to shadow map texels to avoid shimmering edges
+        let texel = 2.0 * distance / self.settings.resolution as f32;
+        let center = rot.transform_point(camera.pos);
+        let snapped = Vector3::new(
+            (center.x / texel).floor() * texel,
+            (center.y / texel).floor() * texel,
+            center.z + distance * 2.0,
+        );
+
+        self.center = camera.pos;
+
+        let view = Matrix4::from_translation(-snapped) * rot;
+        let proj = OPENGL_TO_WGPU_MATRIX
+            * cgmath::ortho(
+                -distance,
+                distance,
+                -distance,
+                distance,
+                0.1,
+                distance * 4.0,
+            );
+        let view_proj = proj * view;
+
+        self.light.set(&gpu.queue, view_proj);
+        gpu.queue.write_buffer(
+            &self.uniform,
+            0,
+            bytemuck::cast_slice(&[ShadowUniform {
+                light_view_proj: view_proj.into(),
+                params: [
+                    self.strength * fade,
+                    1.0 / self.settings.resolution as f32,
+                    0.0005,
+                    0.0,
+                ],
+            }]),
+        );
+    }
+
+    pub fn pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+    ) -> Option<wgpu::RenderPass<'a>> {
+        if !self.light_visible || self.strength <= 0.0 {
+            return None;
+        }
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("shadow"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(1, &self.light.bind_group, &[]);
+
+        Some(pass)
+    }
+}
@@ -70,6 +70,9 @@ impl MediaMgr {
             .fold(None, |mut base, next| {
                 if let Some(overlay) = match next {
                     "" => Some(self.texture("no_texture.png")),
+                    texmod if texmod.starts_with("[inventorycube") => {
+                        Some(self.inventorycube(texmod))
+                    }
                     texmod if matches!(texmod.chars().next(), Some('[')) => {
                         eprintln!("unknown texture modifier: {texmod}");
                         None
@@ -87,4 +90,64 @@ impl MediaMgr {
             })
             .unwrap_or_else(Self::rand_img)
     }
+
+    /// [inventorycube{top{left{right, textures use & instead of ^
+    fn inventorycube(&self, texmod: &str) -> image::RgbaImage {
+        let mut faces = texmod.split('{').skip(1).map(|texture| {
+            image::imageops::flip_vertical(&self.texture_string(&texture.replace('&', "^")))
+        });
+
+        let top = faces.next().unwrap_or_else(Self::rand_img);
+        let left = faces.next().unwrap_or_else(|| top.clone());
+        let right = faces.next().unwrap_or_else(|| left.clone());
+
+        image::imageops::flip_vertical(&inventorycube(&top, &left, &right))
+    }
+}
+
+/// renders an isometric view of a cube, all images are top down
+pub fn inventorycube(
+    top: &image::RgbaImage,
+    left: &image::RgbaImage,
+    right: &image::RgbaImage,
+) -> image::RgbaImage {
+    let size = top.width().max(left.width()).max(right.width()).max(16) * 2;
+
+    let sample = |img: &image::RgbaImage, u: f32, v: f32, light: f32| {
+        let x = ((u * img.width() as f32) as u32).min(img.width() - 1);
+        let y = ((v * img.height() as f32) as u32).min(img.height() - 1);
+
+        let mut pixel = *img.get_pixel(x, y);
+        for c in &mut pixel.0[..3] {
+            *c = (*c as f32 * light) as u8;
+        }
+        pixel
+    };
+    let unit = |x: f32| (0.0..1.0).contains(&x);
+
+    image::RgbaImage::from_fn(size, size, |x, y| {
+        let x = (x as f32 + 0.5) / size as f32;
+        let y = (y as f32 + 0.5) / size as f32;
+
+        // the top face is a rhombus spanned from (0.5, 0) to the left and right corners
+        let (s, t) = (2.0 * y + (x - 0.5), 2.0 * y - (x - 0.5));
+        if unit(s) && unit(t) {
+            return sample(top, s, t, 1.0);
+        }
+
+        // the side faces go down from the left and right corners to the center
+        let u = 2.0 * x;
+        let v = (y - 0.25 - 0.25 * u) * 2.0;
+        if unit(u) && unit(v) {
+            return sample(left, u, v, 0.8);
+        }
+
+        let u = 2.0 * x - 1.0;
+        let v = (y - 0.5 + 0.25 * u) * 2.0;
+        if unit(u) && unit(v) {
+            return sample(right, u, v, 0.65);
+        }
+
+        image::Rgba([0, 0, 0, 0])
+    })
 }
@@ -0,0 +1,233 @@
+use super::{
+    font::Font,
+    input::{Action, Input},
+    settings::Settings,
+};
+use wgpu_glyph::{Section, Text};
+use winit::event::VirtualKeyCode as Key;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MenuResult {
+    None,
+    Resume,
+    SettingsChanged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Item {
+    Fov,
+    Sensitivity,
+    InvertY,
+    DpiScaling,
+    Smoothing,
+    CinematicSmoothing,
+    Crosshair,
+    InfoText,
+    Binding(Action),
+}
+
+impl Item {
+    fn all() -> impl Iterator<Item = Item> {
+        [
+            Item::Fov,
+            Item::Sensitivity,
+            Item::InvertY,
+            Item::DpiScaling,
+            Item::Smoothing,
+            Item::CinematicSmoothing,
+            Item::Crosshair,
+            Item::InfoText,
+        ]
+        .into_iter()
+        .chain(Action::ALL.into_iter().map(Item::Binding))
+    }
+
+    fn count() -> usize {
+        Self::all().count()
+    }
+
+    fn label(self, settings: &Settings) -> String {
+        let mouse = &settings.mouse;
+        let on_off = |b| if b { "on" } else { "off" };
+
+        match self {
+            Item::Fov => format!("Field of view: {:.0}", settings.fov),
+            Item::Sensitivity => format!("Mouse sensitivity: {:.3}", mouse.sensitivity),
+            Item::InvertY => format!("Invert mouse: {}", on_off(mouse.invert_y)),
+            Item::DpiScaling => format!("DPI scaling: {}", on_off(mouse.dpi_scaling)),
+            Item::Smoothing => format!("Camera smoothing: {:.1}", mouse.smoothing),
+            Item::CinematicSmoothing => {
+                format!("Cinematic smoothing: {:.1}", mouse.cinematic_smoothing)
+            }
+            Item::Crosshair => format!("Crosshair: {}", on_off(settings.crosshair.enabled)),
+            Item::InfoText => format!(
+                "Pointed node info: {}",
+                on_off(settings.crosshair.info_text)
+            ),
+            Item::Binding(action) => format!(
+                "{}: {}",
+                action.name(),
+                settings
+                    .keys
+                    .get(action)
+                    .map_or("(none)".into(), |input| input.to_string())
+            ),
+        }
+    }
+
+    /// changes the setting by steps, returns whether anything changed
+    fn adjust(self, settings: &mut Settings, steps: i32) -> bool {
+        let mouse = &mut settings.mouse;
+        let step = |value: &mut f32, size: f32, min: f32, max: f32| {
+            *value = (*value + size * steps as f32).clamp(min, max);
+        };
+
+        match self {
+            Item::Fov => step(&mut settings.fov, 5.0, 30.0, 160.0),
+            Item::Sensitivity => step(&mut mouse.sensitivity, 0.005, 0.005, 1.0),
+            Item::InvertY => mouse.invert_y = !mouse.invert_y,
+            Item::DpiScaling => mouse.dpi_scaling = !mouse.dpi_scaling,
+            Item::Smoothing => step(&mut mouse.smoothing, 0.1, 0.0, 0.9),
+            Item::CinematicSmoothing => step(&mut mouse.cinematic_smoothing, 0.1, 0.0, 0.9),
+            Item::Crosshair => settings.crosshair.enabled = !settings.crosshair.enabled,
+            Item::InfoText => settings.crosshair.info_text = !settings.crosshair.info_text,
+            Item::Binding(_) => return false,
+        }
+
+        true
+    }
+}
+
+/// how a captured key press is bound
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Capture {
+    /// the key of the current keyboard layout
+    Key,
+    /// the physical key position, independent of the layout
+    Scancode,
+}
+
+/// pause menu for mouse settings and key bindings, navigated with the arrow keys
+#[derive(Default)]
+pub struct PauseMenu {
+    selected: usize,
+    /// waiting for an input to bind to the selected action
+    capturing: Option<Capture>,
+    pub shift: bool,
+}
+
+impl PauseMenu {
+    fn selected(&self) -> Item {
+        Item::all().nth(self.selected).unwrap()
+    }
+
+    /// whether the next input is bound to an action
+    pub fn capturing(&self) -> bool {
+        self.capturing.is_some()
+    }
+
+    /// handles a pressed input while the game is paused,
+    /// scancode is the physical key of keyboard inputs
+    pub fn input(
+        &mut self,
+        input: Input,
+        scancode: Option<u32>,
+        settings: &mut Settings,
+    ) -> MenuResult {
+        let escape = input == Input::Key(Key::Escape);
+        let item = self.selected();
+
+        if let Some(capture) = self.capturing.take() {
+            if let (false, Item::Binding(action)) = (escape, item) {
+                let input = match (capture, scancode) {
+                    (Capture::Scancode, Some(scancode)) => Input::Scancode(scancode),
+                    _ => input,
+                };
+
+                settings.keys.set(action, input);
+                return MenuResult::SettingsChanged;
+            }
+
+            return MenuResult::None;
+        }
+
+        let count = Item::count();
+        let changed = match input {
+            _ if escape => return MenuResult::Resume,
+            Input::Key(Key::Up) | Input::ScrollUp => {
+                self.selected = (self.selected + count - 1) % count;
+                false
+            }
+            Input::Key(Key::Down) | Input::ScrollDown => {
+                self.selected = (self.selected + 1) % count;
+                false
+            }
+            Input::Key(Key::Left) => item.adjust(settings, -1),
+            Input::Key(Key::Right) => item.adjust(settings, 1),
+            Input::Key(Key::Return) => {
+                if let Item::Binding(_) = item {
+                    self.capturing = Some(if self.shift {
+                        Capture::Scancode
+                    } else {
+                        Capture::Key
+                    });
+                }
+                item.adjust(settings, 1)
+            }
+            _ => false,
+        };
+
+        if changed {
+            MenuResult::SettingsChanged
+        } else {
+            MenuResult::None
+        }
+    }
+
+    pub fn render(&self, bounds: (f32, f32), settings: &Settings, font: &mut Font) {
+        let mut offset = bounds.1 / 2.0 - (Item::count() + 2) as f32 * 11.0;
+
+        let mut add_text = |txt: &str, color: [f32; 4]| {
+            font.add(Section {
+                screen_position: (bounds.0 / 2.0 - 150.0, offset),
+                bounds,
+                text: vec![Text::new(txt).with_color(color).with_scale(20.0)],
+                ..Section::default()
+            });
+
+            offset += 22.0;
+        };
+
+        add_text(
+            "Paused - Esc: resume, Up/Down: select, Left/Right: adjust, Enter: change, \
+             Shift+Enter: change by key position",
+            [1.0, 1.0, 1.0, 1.0],
+        );
+        add_text("", [1.0; 4]);
+
+        for (i, item) in Item::all().enumerate() {
+            let selected = i == self.selected;
+
+            let label = match (item, self.capturing) {
+                (Item::Binding(action), Some(capture)) if selected => format!(
+                    "{}: press a {} or button, Esc to cancel",
+                    action.name(),
+                    match capture {
+                        Capture::Key => "key",
+                        Capture::Scancode => "key (by position)",
+                    }
+                ),
+                _ => item.label(settings),
+            };
+
+            add_text(
+                &label,
+                if selected {
+                    [1.0, 1.0, 0.0, 1.0]
+                } else {
+                    [0.8, 0.8, 0.8, 1.0]
+                },
+            );
+        }
+    }
+}
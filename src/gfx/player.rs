@@ -0,0 +1,911 @@
+use super::camera::{Camera, CameraInput};
+use cgmath::{prelude::*, Point3, Vector3};
+use collision::Aabb3;
+use mt_net::NodeDef;
+use std::collections::HashSet;
+
+pub const EYE_HEIGHT: f32 = 1.625;
+const STEP_HEIGHT: f32 = 0.6;
+// maximum length of a single physics step, larger frame times are split up
+const MAX_STEP: f32 = 0.02;
+const EPSILON: f32 = 0.001;
+// liquid parameters are tuned in upstream's node size units
+const BS: f32 = 10.0;
+// how much liquid_viscosity contributes to liquid resistance
+const VISCOSITY_FACTOR: f32 = 0.3;
+// server corrections further away than this are treated as teleports
+const MAX_CORRECTION: f32 = 2.0;
+// rate at which the visual offset of a server correction decays, per second
+const CORRECTION_RATE: f32 = 10.0;
+
+/// movement parameters sent by the server in the Movement packet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MovementParams {
+    pub default_accel: f32,
+    pub air_accel: f32,
+    pub fast_accel: f32,
+    pub walk_speed: f32,
+    pub crouch_speed: f32,
+    pub fast_speed: f32,
+    pub climb_speed: f32,
+    pub jump_speed: f32,
+    pub gravity: f32,
+    pub fluidity: f32,
+    pub smoothing: f32,
+    pub sink: f32,
+}
+
+impl Default for MovementParams {
+    fn default() -> Self {
+        Self {
+            default_accel: 3.0,
+            air_accel: 2.0,
+            fast_accel: 10.0,
+            walk_speed: 4.0,
+            crouch_speed: 1.35,
+            fast_speed: 20.0,
+            climb_speed: 2.0,
+            jump_speed: 6.5,
+            gravity: 9.81,
+            fluidity: 1.0,
+            smoothing: 0.5,
+            sink: 10.0,
+        }
+    }
+}
+
+/// physics overrides set by the server on the player's active object
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsOverride {
+    pub speed: f32,
+    pub jump: f32,
+    pub gravity: f32,
+    pub sneak: bool,
+    pub sneak_glitch: bool,
+    pub new_move: bool,
+}
+
+impl Default for PhysicsOverride {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            jump: 1.0,
+            gravity: 1.0,
+            sneak: true,
+            sneak_glitch: false,
+            new_move: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PlayerPhysics {
+    pub movement: MovementParams,
+    pub overrides: PhysicsOverride,
+}
+
+/// read access to the loaded map, implemented by the map renderer
+pub trait NodeMap {
+    /// definition of the node at the given position,
+    /// None if the containing block is not loaded or the node is undefined
+    fn node(&self, pos: Point3<i32>) -> Option<&NodeDef>;
+}
+
+/// synthetic maps for testing code that reads nodes
+#[cfg(test)]
+pub mod test_map {
+    use super::NodeMap;
+    use cgmath::Point3;
+    use mt_net::NodeDef;
+    use std::{collections::HashMap, ops::RangeInclusive};
+
+    pub fn air() -> NodeDef {
+        NodeDef {
+            walkable: false,
+            pointable: false,
+            climbable: false,
+            liquid_type: mt_net::LiquidType::None,
+            ..Default::default()
+        }
+    }
+
+    /// a node colliding with and selected by the given box
+    pub fn solid(min: [f32; 3], max: [f32; 3]) -> NodeDef {
+        let nodebox = mt_net::NodeBox::Fixed {
+            fixed: vec![min..max],
+        };
+
+        NodeDef {
+            walkable: true,
+            pointable: true,
+            collision_box: nodebox.clone(),
+            selection_box: nodebox,
+            ..air()
+        }
+    }
+
+    pub fn cube() -> NodeDef {
+        solid([-0.5; 3], [0.5; 3])
+    }
+
+    /// map of air with the given nodes, loaded everywhere
+    pub struct TestMap {
+        nodes: HashMap<[i32; 3], NodeDef>,
+        air: NodeDef,
+    }
+
+    impl Default for TestMap {
+        fn default() -> Self {
+            Self {
+                nodes: HashMap::new(),
+                air: air(),
+            }
+        }
+    }
+
+    impl TestMap {
+        pub fn set(&mut self, x: i32, y: i32, z: i32, def: &NodeDef) {
+            self.nodes.insert([x, y, z], def.clone());
+        }
+
+        /// fills x_range × y_range × -10..=10 with def
+        pub fn fill(
+            &mut self,
+            x_range: RangeInclusive<i32>,
+            y_range: RangeInclusive<i32>,
+            def: &NodeDef,
+        ) {
+            for x in x_range {
+                for y in y_range.clone() {
+                    for z in -10..=10 {
+                        self.set(x, y, z, def);
+                    }
+                }
+            }
+        }
+
+        /// a floor of cubes with their top at y = 0.5
+        pub fn floor() -> Self {
+            let mut map = Self::default();
+            map.fill(-20..=20, 0..=0, &cube());
+            map
+        }
+    }
+
+    impl NodeMap for TestMap {
+        fn node(&self, pos: Point3<i32>) -> Option<&NodeDef> {
+            Some(self.nodes.get(&[pos.x, pos.y, pos.z]).unwrap_or(&self.air))
+        }
+    }
+}
+
+fn unit_cube() -> Aabb3<f32> {
+    Aabb3::new(Point3::new(-0.5, -0.5, -0.5), Point3::new(0.5, 0.5, 0.5))
+}
+
+/// boxes of a node box relative to the node's center,
+/// regular and unsupported types are full cubes
+pub fn nodebox_boxes(nodebox: &mt_net::NodeBox) -> Vec<Aabb3<f32>> {
+    match nodebox {
+        mt_net::NodeBox::Fixed { fixed }
+        | mt_net::NodeBox::Leveled { fixed }
+        | mt_net::NodeBox::Connected { fixed, .. } => fixed
+            .iter()
+            .map(|b| Aabb3::new(Point3::from(b.start), Point3::from(b.end)))
+            .collect(),
+        _ => vec![unit_cube()],
+    }
+}
+
+/// collision boxes of a node, relative to its center
+fn node_boxes(def: Option<&NodeDef>) -> Vec<Aabb3<f32>> {
+    match def {
+        // unloaded and unknown nodes are solid, like upstream's ignore
+        None => vec![unit_cube()],
+        Some(def) if !def.walkable => Vec::new(),
+        Some(def) => nodebox_boxes(&def.collision_box),
+    }
+}
+
+/// definition of the node containing pos
+pub fn node_at(map: &impl NodeMap, pos: Point3<f32>) -> Option<&NodeDef> {
+    map.node(pos.map(|x| x.round() as i32))
+}
+
+fn is_liquid(def: &NodeDef) -> bool {
+    def.liquid_type != mt_net::LiquidType::None
+}
+
+// nodes are centered on integer coordinates
+fn node_range(min: f32, max: f32) -> std::ops::RangeInclusive<i32> {
+    min.round() as i32..=max.round() as i32
+}
+
+/// all collision boxes of walkable nodes that may intersect the given area
+pub fn collect_boxes(map: &impl NodeMap, area: Aabb3<f32>, out: &mut Vec<Aabb3<f32>>) {
+    out.clear();
+
+    for z in node_range(area.min.z, area.max.z) {
+        for y in node_range(area.min.y, area.max.y) {
+            for x in node_range(area.min.x, area.max.x) {
+                let pos = Point3::new(x, y, z);
+                let offset = pos.cast::<f32>().unwrap().to_vec();
+
+                out.extend(
+                    node_boxes(map.node(pos))
+                        .into_iter()
+                        .map(|b| Aabb3::new(b.min + offset, b.max + offset)),
+                );
+            }
+        }
+    }
+}
+
+fn overlaps(a: &Aabb3<f32>, b: &Aabb3<f32>, axis: usize) -> bool {
+    (0..3)
+        .filter(|&i| i != axis)
+        .all(|i| a.min[i] < b.max[i] - EPSILON && a.max[i] > b.min[i] + EPSILON)
+}
+
+/// clips movement of `aabb` along `axis` against `boxes`, returns the allowed distance
+pub fn sweep(aabb: &Aabb3<f32>, boxes: &[Aabb3<f32>], axis: usize, mut dist: f32) -> f32 {
+    for b in boxes.iter().filter(|b| overlaps(aabb, b, axis)) {
+        if dist > 0.0 && b.min[axis] >= aabb.max[axis] - EPSILON {
+            dist = dist.min(b.min[axis] - aabb.max[axis]);
+        } else if dist < 0.0 && b.max[axis] <= aabb.min[axis] + EPSILON {
+            dist = dist.max(b.max[axis] - aabb.min[axis]);
+        }
+    }
+
+    dist
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveMode {
+    Fly,
+    Fast,
+    Noclip,
+    PitchMove,
+}
+
+impl MoveMode {
+    fn name(self) -> &'static str {
+        match self {
+            MoveMode::Fly => "Fly",
+            MoveMode::Fast => "Fast",
+            MoveMode::Noclip => "Noclip",
+            MoveMode::PitchMove => "Pitch move",
+        }
+    }
+
+    fn privilege(self) -> Option<&'static str> {
+        match self {
+            MoveMode::Fly => Some("fly"),
+            MoveMode::Fast => Some("fast"),
+            MoveMode::Noclip => Some("noclip"),
+            MoveMode::PitchMove => None,
+        }
+    }
+}
+
+fn accelerate(vel: f32, target: f32, max_increase: f32) -> f32 {
+    let d = target - vel;
+    vel + d.clamp(-max_increase, max_increase)
+}
+
+pub struct LocalPlayer {
+    /// position of the feet
+    pub pos: Point3<f32>,
+    pub vel: Vector3<f32>,
+    pub physics: PlayerPhysics,
+    /// visual offset left over from server corrections, decays over time
+    correction: Vector3<f32>,
+    pub touching_ground: bool,
+    pub collision_box: Aabb3<f32>,
+    pub fly: bool,
+    pub fast: bool,
+    pub noclip: bool,
+    pub pitch_move: bool,
+    pub in_liquid: bool,
+    /// submerged deep enough to not be at the surface
+    pub in_liquid_stable: bool,
+    pub climbing: bool,
+    /// downward speed at the last landing, taken by the view bobbing
+    landing: Option<f32>,
+    liquid_viscosity: f32,
+    privs: HashSet<String>,
+    boxes: Vec<Aabb3<f32>>,
+}
+
+impl LocalPlayer {
+    pub fn new() -> Self {
+        Self {
+            pos: Point3::origin(),
+            vel: Vector3::zero(),
+            physics: Default::default(),
+            correction: Vector3::zero(),
+            touching_ground: false,
+            collision_box: Aabb3::new(Point3::new(-0.3, 0.0, -0.3), Point3::new(0.3, 1.77, 0.3)),
+            fly: false,
+            fast: false,
+            noclip: false,
+            pitch_move: false,
+            in_liquid: false,
+            in_liquid_stable: false,
+            climbing: false,
+            landing: None,
+            liquid_viscosity: 0.0,
+            privs: HashSet::new(),
+            boxes: Vec::new(),
+        }
+    }
+
+    fn mode_mut(&mut self, mode: MoveMode) -> &mut bool {
+        match mode {
+            MoveMode::Fly => &mut self.fly,
+            MoveMode::Fast => &mut self.fast,
+            MoveMode::Noclip => &mut self.noclip,
+            MoveMode::PitchMove => &mut self.pitch_move,
+        }
+    }
+
+    /// toggles a movement mode if the player has the privilege for it,
+    /// returns a message describing the outcome
+    pub fn toggle(&mut self, mode: MoveMode) -> String {
+        if let Some(privilege) = mode.privilege() {
+            if !self.privs.contains(privilege) {
+                return format!(
+                    "{} mode unavailable: missing '{privilege}' privilege",
+                    mode.name()
+                );
+            }
+        }
+
+        let enabled = self.mode_mut(mode);
+        *enabled = !*enabled;
+
+        format!(
+            "{} mode {}",
+            mode.name(),
+            if *enabled { "enabled" } else { "disabled" }
+        )
+    }
+
+    /// updates privileges, disabling modes that are no longer granted
+    pub fn set_privs(&mut self, privs: HashSet<String>) {
+        self.privs = privs;
+
+        for mode in [MoveMode::Fly, MoveMode::Fast, MoveMode::Noclip] {
+            if !self.privs.contains(mode.privilege().unwrap()) {
+                *self.mode_mut(mode) = false;
+            }
+        }
+    }
+
+    /// downward speed at the last landing since the previous call
+    pub fn take_landing(&mut self) -> Option<f32> {
+        self.landing.take()
+    }
+
+    /// position of the feet including the smoothing of server corrections
+    pub fn visual_pos(&self) -> Point3<f32> {
+        self.pos + self.correction
+    }
+
+    /// applies a position sent by the server, smoothing over small differences
+    /// to the predicted position and teleporting on large ones
+    pub fn set_server_pos(&mut self, pos: Point3<f32>) {
+        let diff = self.pos - pos;
+        self.pos = pos;
+
+        if diff.magnitude() < MAX_CORRECTION {
+            self.correction += diff;
+        } else {
+            self.correction = Vector3::zero();
+            self.vel = Vector3::zero();
+        }
+    }
+
+    /// the player's collision box in world coordinates
+    pub fn aabb(&self) -> Aabb3<f32> {
+        self.aabb_at(self.pos)
+    }
+
+    fn aabb_at(&self, pos: Point3<f32>) -> Aabb3<f32> {
+        Aabb3::new(
+            pos + self.collision_box.min.to_vec(),
+            pos + self.collision_box.max.to_vec(),
+        )
+    }
+
+    /// whether a walkable surface is directly below the player when standing at pos
+    fn supported_at(&self, pos: Point3<f32>) -> bool {
+        let aabb = self.aabb_at(pos);
+        sweep(&aabb, &self.boxes, 1, -0.05) > -0.05
+    }
+
+    /// moves the player by `delta`, resolving collisions one axis at a time
+    pub fn move_by(&mut self, map: &impl NodeMap, delta: Vector3<f32>, sneak: bool) {
+        if self.fly && self.noclip {
+            self.pos += delta;
+            self.touching_ground = false;
+            return;
+        }
+
+        let start = self.aabb_at(self.pos);
+        let reach = Vector3::new(1.0, 1.0 + STEP_HEIGHT, 1.0) + delta.map(f32::abs);
+        collect_boxes(
+            map,
+            Aabb3::new(start.min - reach, start.max + reach),
+            &mut self.boxes,
+        );
+
+        let was_on_ground = self.touching_ground;
+        let mut pos = self.pos;
+
+        // vertical movement first so horizontal steps use the current ground state
+        let dy = sweep(&self.aabb_at(pos), &self.boxes, 1, delta.y);
+        pos.y += dy;
+
+        self.touching_ground = delta.y <= 0.0 && dy > delta.y + EPSILON;
+        if self.touching_ground && !was_on_ground {
+            self.landing = Some(-self.vel.y);
+        }
+        if dy != delta.y {
+            self.vel.y = 0.0;
+        }
+
+        let on_ground = self.touching_ground || (was_on_ground && delta.y <= 0.0);
+
+        for axis in [0, 2] {
+            if delta[axis] == 0.0 {
+                continue;
+            }
+
+            let mut moved = pos;
+            moved[axis] += sweep(&self.aabb_at(pos), &self.boxes, axis, delta[axis]);
+
+            // step up onto obstacles lower than STEP_HEIGHT
+            if on_ground && (moved[axis] - pos[axis]).abs() + EPSILON < delta[axis].abs() {
+                let up = sweep(&self.aabb_at(pos), &self.boxes, 1, STEP_HEIGHT);
+
+                let mut raised = pos;
+                raised.y += up;
+                raised[axis] += sweep(&self.aabb_at(raised), &self.boxes, axis, delta[axis]);
+                raised.y += sweep(&self.aabb_at(raised), &self.boxes, 1, -up);
+
+                if (raised[axis] - pos[axis]).abs() > (moved[axis] - pos[axis]).abs() + EPSILON {
+                    moved = raised;
+                }
+            }
+
+            // don't fall off edges while sneaking
+            if sneak && on_ground && !self.supported_at(moved) {
+                self.vel[axis] = 0.0;
+                continue;
+            }
+
+            if (moved[axis] - pos[axis] - delta[axis]).abs() > EPSILON {
+                self.vel[axis] = 0.0;
+            }
+
+            pos = moved;
+        }
+
+        self.pos = pos;
+    }
+
+    // same sample points as upstream's LocalPlayer::move
+    fn update_environment(&mut self, map: &impl NodeMap) {
+        let at = |offset: f32| node_at(map, self.pos + Vector3::unit_y() * offset);
+
+        match at(0.1).filter(|def| is_liquid(def)) {
+            Some(def) => {
+                self.in_liquid = true;
+                self.liquid_viscosity = def.liquid_viscosity as f32;
+            }
+            None => self.in_liquid = false,
+        }
+
+        self.in_liquid_stable = at(0.5).map_or(false, is_liquid);
+        self.climbing = !self.fly
+            && [0.5, -0.2]
+                .into_iter()
+                .any(|offset| at(offset).map_or(false, |def| def.climbable));
+    }
+
+    pub fn update(&mut self, map: &impl NodeMap, camera: &Camera, dt: f32) {
+        let dirs = camera.move_dirs(self.fly && self.pitch_move);
+        self.simulate(map, &camera.input, dirs, dt);
+    }
+
+    /// advances the physics by dt, moving along the given forward and right directions
+    fn simulate(
+        &mut self,
+        map: &impl NodeMap,
+        input: &CameraInput,
+        dirs: (Vector3<f32>, Vector3<f32>),
+        dt: f32,
+    ) {
+        let steps = (dt / MAX_STEP).ceil().max(1.0);
+        let dt = dt / steps;
+
+        for _ in 0..steps as u32 {
+            self.step(map, input, dirs, dt);
+        }
+
+        self.correction *= (-CORRECTION_RATE * dt * steps).exp();
+    }
+
+    fn step(
+        &mut self,
+        map: &impl NodeMap,
+        input: &CameraInput,
+        (forward, right): (Vector3<f32>, Vector3<f32>),
+        dt: f32,
+    ) {
+        self.update_environment(map);
+
+        let PlayerPhysics {
+            movement: mv,
+            overrides: ov,
+        } = self.physics;
+        let swimming = self.in_liquid || self.in_liquid_stable;
+        let sneak = input.sneak && ov.sneak;
+
+        // like upstream's always_fly_fast, fast mode always applies while flying
+        let superspeed = self.fast && (input.aux1 || self.fly);
+
+        let mut dir = Vector3::zero();
+
+        if input.forward {
+            dir += forward;
+        }
+        if input.backward {
+            dir -= forward;
+        }
+        if input.right {
+            dir += right;
+        }
+        if input.left {
+            dir -= right;
+        }
+        dir += forward * input.move_y + right * input.move_x;
+
+        // analog input below full deflection moves slower
+        if dir.magnitude2() > 1.0 {
+            dir = dir.normalize();
+        }
+
+        let speed = ov.speed
+            * if superspeed {
+                mv.fast_speed
+            } else if sneak && !self.fly && !swimming {
+                mv.crouch_speed
+            } else {
+                mv.walk_speed
+            };
+
+        let accel = ov.speed
+            * if superspeed {
+                mv.fast_accel
+            } else if self.touching_ground || self.fly || self.climbing || swimming {
+                mv.default_accel
+            } else {
+                mv.air_accel
+            };
+
+        let target = dir * speed;
+        self.vel.x = accelerate(self.vel.x, target.x, accel * dt);
+        self.vel.z = accelerate(self.vel.z, target.z, accel * dt);
+
+        if self.fly {
+            let mut target_y = target.y;
+
+            if input.jump {
+                target_y += speed;
+            }
+            if sneak {
+                target_y -= speed;
+            }
+
+            self.vel.y = accelerate(self.vel.y, target_y, accel * dt);
+        } else if self.climbing {
+            // holding neither jump nor sneak stops right away like upstream
+            self.vel.y = if input.jump {
+                accelerate(self.vel.y, mv.climb_speed, accel * dt)
+            } else if sneak {
+                accelerate(self.vel.y, -mv.climb_speed, accel * dt)
+            } else {
+                0.0
+            };
+        } else {
+            let swimming_vertical = self.in_liquid && (input.jump || sneak);
+
+            if swimming_vertical {
+                let target_y = if input.jump {
+                    mv.walk_speed
+                } else {
+                    -mv.walk_speed
+                };
+
+                self.vel.y = accelerate(self.vel.y, target_y, accel * dt);
+            } else if input.jump && self.can_jump(sneak) {
+                self.vel.y = mv.jump_speed * ov.jump;
+                self.touching_ground = false;
+            }
+
+            if !self.in_liquid {
+                self.vel.y -= mv.gravity * ov.gravity * dt * 2.0;
+            } else if !swimming_vertical {
+                self.vel.y -= mv.sink * dt * 2.0;
+            }
+
+            if swimming {
+                self.apply_liquid_resistance(dt);
+            }
+        }
+
+        let sneak_edge = sneak && !self.fly && !self.climbing && !swimming;
+        self.move_by(map, self.vel * dt, sneak_edge);
+    }
+
+    fn can_jump(&self, sneak: bool) -> bool {
+        let ov = self.physics.overrides;
+
+        // old move's sneak glitch allows jumping again while sneaking
+        // as soon as a node edge is below, even mid-jump
+        self.touching_ground
+            || (sneak && ov.sneak_glitch && !ov.new_move && self.supported_at(self.pos))
+    }
+
+    // see upstream's ClientEnvironment::step
+    fn apply_liquid_resistance(&mut self, dt: f32) {
+        let mv = self.physics.movement;
+        let speed = self.vel.magnitude();
+
+        if speed < EPSILON {
+            return;
+        }
+
+        let resistance = (speed / mv.fluidity).min(mv.smoothing * BS)
+            * (self.liquid_viscosity * VISCOSITY_FACTOR + 1.0 - VISCOSITY_FACTOR);
+
+        // don't overshoot into the opposite direction
+        let change = (resistance * dt * 100.0 / BS).min(speed);
+        self.vel -= self.vel / speed * change;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{test_map::*, *};
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn player_at(x: f32, y: f32, z: f32) -> LocalPlayer {
+        let mut player = LocalPlayer::new();
+        player.pos = Point3::new(x, y, z);
+        player
+    }
+
+    /// runs the physics at 60 fps, forward is +x and right is +z
+    fn run(player: &mut LocalPlayer, map: &TestMap, input: &CameraInput, secs: f32) {
+        for _ in 0..(secs / DT).round() as u32 {
+            player.simulate(map, input, (Vector3::unit_x(), Vector3::unit_z()), DT);
+        }
+    }
+
+    fn forward() -> CameraInput {
+        CameraInput {
+            forward: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn gravity_accelerates_falling() {
+        let map = TestMap::floor();
+        let mut player = player_at(0.0, 50.0, 0.0);
+
+        run(&mut player, &map, &Default::default(), 0.5);
+
+        // upstream applies twice the gravity setting
+        assert!(
+            (player.vel.y + 19.62 * 0.5).abs() < 0.05,
+            "{:?}",
+            player.vel
+        );
+        assert!(
+            (player.pos.y - (50.0 - 9.81 * 0.25)).abs() < 0.1,
+            "{:?}",
+            player.pos
+        );
+        assert!(!player.touching_ground);
+    }
+
+    #[test]
+    fn lands_on_floor() {
+        let map = TestMap::floor();
+        let mut player = player_at(0.0, 3.0, 0.0);
+
+        run(&mut player, &map, &Default::default(), 2.0);
+
+        assert!((player.pos.y - 0.5).abs() < 0.01, "{:?}", player.pos);
+        assert!(player.touching_ground);
+        assert_eq!(player.vel.y, 0.0);
+        assert!(player.take_landing().map_or(false, |speed| speed > 5.0));
+        assert_eq!(player.take_landing(), None);
+    }
+
+    #[test]
+    fn steps_up_onto_slab() {
+        let mut map = TestMap::floor();
+        map.fill(2..=20, 1..=1, &solid([-0.5; 3], [0.5, 0.0, 0.5]));
+        let mut player = player_at(0.0, 0.5, 0.0);
+
+        run(&mut player, &map, &Default::default(), 0.1);
+        run(&mut player, &map, &forward(), 2.0);
+
+        assert!(player.pos.x > 2.0, "{:?}", player.pos);
+        assert!((player.pos.y - 1.0).abs() < 0.01, "{:?}", player.pos);
+        assert!(player.touching_ground);
+    }
+
+    #[test]
+    fn does_not_step_up_full_node() {
+        let mut map = TestMap::floor();
+        map.fill(2..=2, 1..=1, &cube());
+        let mut player = player_at(0.0, 0.5, 0.0);
+
+        run(&mut player, &map, &Default::default(), 0.1);
+        run(&mut player, &map, &forward(), 2.0);
+
+        assert!((player.pos.x - 1.2).abs() < 0.01, "{:?}", player.pos);
+        assert!((player.pos.y - 0.5).abs() < 0.01, "{:?}", player.pos);
+        assert_eq!(player.vel.x, 0.0);
+    }
+
+    #[test]
+    fn sneaking_stops_at_edge() {
+        let mut map = TestMap::default();
+        map.fill(-20..=0, 0..=0, &cube());
+        let mut player = player_at(0.0, 0.5, 0.0);
+        let input = CameraInput {
+            sneak: true,
+            ..forward()
+        };
+
+        run(&mut player, &map, &Default::default(), 0.1);
+        run(&mut player, &map, &input, 3.0);
+
+        // the collision box is 0.6 wide, the edge is at x = 0.5
+        assert!(player.pos.x > 0.7 && player.pos.x < 0.8, "{:?}", player.pos);
+        assert!((player.pos.y - 0.5).abs() < 0.01, "{:?}", player.pos);
+        assert!(player.touching_ground);
+    }
+
+    #[test]
+    fn walks_off_edge_without_sneaking() {
+        let mut map = TestMap::default();
+        map.fill(-20..=0, 0..=0, &cube());
+        let mut player = player_at(0.0, 0.5, 0.0);
+
+        run(&mut player, &map, &Default::default(), 0.1);
+        run(&mut player, &map, &forward(), 2.0);
+
+        assert!(player.pos.x > 0.8 && player.pos.y < 0.0, "{:?}", player.pos);
+    }
+
+    #[test]
+    fn sweep_does_not_tunnel_through_walls() {
+        let mut map = TestMap::floor();
+        map.fill(2..=2, 1..=2, &cube());
+
+        let mut player = player_at(0.0, 0.5, 0.0);
+        player.move_by(&map, Vector3::new(20.0, 0.0, 0.0), false);
+        assert!((player.pos.x - 1.2).abs() < 0.01, "{:?}", player.pos);
+
+        let mut player = player_at(0.0, 0.5, 0.0);
+        player.vel.x = 500.0;
+        player.simulate(
+            &map,
+            &Default::default(),
+            (Vector3::unit_x(), Vector3::unit_z()),
+            1.0,
+        );
+        assert!((player.pos.x - 1.2).abs() < 0.01, "{:?}", player.pos);
+        assert_eq!(player.vel.x, 0.0);
+    }
+
+    #[test]
+    fn fast_fall_does_not_tunnel_through_floor() {
+        let map = TestMap::floor();
+        let mut player = player_at(0.0, 100.0, 0.0);
+        player.vel.y = -1000.0;
+
+        player.simulate(
+            &map,
+            &Default::default(),
+            (Vector3::unit_x(), Vector3::unit_z()),
+            0.5,
+        );
+
+        assert!((player.pos.y - 0.5).abs() < 0.01, "{:?}", player.pos);
+        assert!(player.touching_ground);
+    }
+
+    #[test]
+    fn unloaded_nodes_are_solid() {
+        struct Unloaded;
+
+        impl NodeMap for Unloaded {
+            fn node(&self, _pos: Point3<i32>) -> Option<&NodeDef> {
+                None
+            }
+        }
+
+        let mut player = player_at(0.0, 0.5, 0.0);
+        player.move_by(&Unloaded, Vector3::new(0.0, -1.0, 0.0), false);
+
+        assert_eq!(player.pos.y, 0.5);
+        assert!(player.touching_ground);
+    }
+
+    #[test]
+    fn climbs_ladders() {
+        let mut map = TestMap::floor();
+        let ladder = NodeDef {
+            climbable: true,
+            ..air()
+        };
+        for y in 1..=10 {
+            map.set(0, y, 0, &ladder);
+        }
+        let mut player = player_at(0.0, 0.5, 0.0);
+        let input = CameraInput {
+            jump: true,
+            ..Default::default()
+        };
+
+        run(&mut player, &map, &input, 1.0);
+
+        assert!(player.climbing);
+        assert!(player.pos.y > 1.5, "{:?}", player.pos);
+
+        // holding still on a ladder neither falls nor keeps climbing
+        let y = player.pos.y;
+        run(&mut player, &map, &Default::default(), 1.0);
+        assert!(player.climbing);
+        assert!((player.pos.y - y).abs() < EPSILON, "{:?}", player.pos);
+    }
+
+    #[test]
+    fn sinks_slowly_in_liquids() {
+        let mut map = TestMap::floor();
+        let water = NodeDef {
+            liquid_type: mt_net::LiquidType::Source,
+            liquid_viscosity: 1,
+            ..air()
+        };
+        map.fill(-20..=20, 1..=10, &water);
+        let mut player = player_at(0.0, 8.0, 0.0);
+
+        run(&mut player, &map, &Default::default(), 1.0);
+
+        assert!(player.in_liquid);
+        assert!(player.in_liquid_stable);
+        assert!(!player.climbing);
+        assert!(
+            player.vel.y < 0.0 && player.vel.y > -5.0,
+            "{:?}",
+            player.vel
+        );
+
+        // the flags are cleared when leaving the liquid
+        player.pos.y = 20.0;
+        run(&mut player, &map, &Default::default(), DT);
+        assert!(!player.in_liquid);
+        assert!(!player.in_liquid_stable);
+    }
+}
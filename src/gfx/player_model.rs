@@ -0,0 +1,263 @@
+use super::{
+    camera::Camera,
+    gpu::Gpu,
+    media::MediaMgr,
+    util::{create_texture, nearest_sampler, MatrixUniform, TextureBinding},
+};
+use cgmath::{prelude::*, Matrix3, Matrix4, Point3, Vector3};
+use wgpu::util::DeviceExt;
+
+// the model is built in skin pixels, 32 of them make up the player's height
+const PIXEL: f32 = 1.75 / 32.0;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    tex_coords: [f32; 2],
+    light: f32,
+}
+
+impl Vertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// adds a cuboid textured using the classic 64x32 skin layout.
+/// the model faces +z, pos is the minimum corner, uv the top left corner of the
+/// cuboid's area on the skin, all in skin pixels
+fn skin_box(vertices: &mut Vec<Vertex>, pos: [f32; 3], size: [f32; 3], uv: [f32; 2]) {
+    let [x, y, z] = pos;
+    let [w, h, d] = size;
+    let [u, v] = uv;
+
+    // corners are given clockwise from the top left of the face's area on the skin
+    let mut face = |corners: [[f32; 3]; 4], rect: [f32; 4], light: f32| {
+        let [ru, rv, rw, rh] = rect;
+        let tex = [[ru, rv], [ru + rw, rv], [ru + rw, rv + rh], [ru, rv + rh]];
+
+        for i in [0, 1, 2, 2, 3, 0] {
+            vertices.push(Vertex {
+                pos: corners[i].map(|c| c * PIXEL),
+                tex_coords: tex[i],
+                light,
+            });
+        }
+    };
+
+    let (x1, y1, z1) = (x + w, y + h, z + d);
+
+    // front
+    face(
+        [[x, y1, z1], [x1, y1, z1], [x1, y, z1], [x, y, z1]],
+        [u + d, v + d, w, h],
+        0.9,
+    );
+    // left
+    face(
+        [[x1, y1, z1], [x1, y1, z], [x1, y, z], [x1, y, z1]],
+        [u + d + w, v + d, d, h],
+        0.8,
+    );
+    // back
+    face(
+        [[x1, y1, z], [x, y1, z], [x, y, z], [x1, y, z]],
+        [u + 2.0 * d + w, v + d, w, h],
+        0.9,
+    );
+    // right
+    face(
+        [[x, y1, z], [x, y1, z1], [x, y, z1], [x, y, z]],
+        [u, v + d, d, h],
+        0.8,
+    );
+    // top
+    face(
+        [[x, y1, z], [x1, y1, z], [x1, y1, z1], [x, y1, z1]],
+        [u + d, v, w, d],
+        1.0,
+    );
+    // bottom
+    face(
+        [[x, y, z], [x1, y, z], [x1, y, z1], [x, y, z1]],
+        [u + d + w, v, w, d],
+        0.6,
+    );
+}
+
+/// the local player's body, shown when the camera is not in first person
+pub struct PlayerModel {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    num_vertices: u32,
+    model: MatrixUniform,
+    texture: TextureBinding,
+}
+
+impl PlayerModel {
+    pub fn new(gpu: &Gpu, camera: &Camera, media: &MediaMgr) -> Self {
+        // upstream's player_api uses character.png, which servers usually send
+        let skin = if media.get("character.png").is_some() {
+            media.texture("character.png")
+        } else {
+            image::RgbaImage::from_pixel(64, 32, image::Rgba([120, 140, 180, 255]))
+        };
+
+        let mut vertices = Vec::new();
+        skin_box(
+            &mut vertices,
+            [-4.0, 24.0, -4.0],
+            [8.0, 8.0, 8.0],
+            [0.0, 0.0],
+        );
+        skin_box(
+            &mut vertices,
+            [-4.0, 12.0, -2.0],
+            [8.0, 12.0, 4.0],
+            [16.0, 16.0],
+        );
+        skin_box(
+            &mut vertices,
+            [-8.0, 12.0, -2.0],
+            [4.0, 12.0, 4.0],
+            [40.0, 16.0],
+        );
+        skin_box(
+            &mut vertices,
+            [4.0, 12.0, -2.0],
+            [4.0, 12.0, 4.0],
+            [40.0, 16.0],
+        );
+        skin_box(
+            &mut vertices,
+            [-4.0, 0.0, -2.0],
+            [4.0, 12.0, 4.0],
+            [0.0, 16.0],
+        );
+        skin_box(
+            &mut vertices,
+            [0.0, 0.0, -2.0],
+            [4.0, 12.0, 4.0],
+            [0.0, 16.0],
+        );
+
+        // skins may be 64x64, the layout only uses the upper 64x32 pixels.
+        // MediaMgr flips textures vertically
+        let scale = skin.width() as f32 / 64.0;
+        for vertex in &mut vertices {
+            let [u, v] = vertex.tex_coords;
+            vertex.tex_coords = [u / 64.0, 1.0 - v * scale / skin.height() as f32];
+        }
+
+        let vertex_buffer = gpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("player_model.vertex_buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+        let model_layout = MatrixUniform::layout(&gpu.device, "player_model");
+        let model = MatrixUniform::new(
+            &gpu.device,
+            &model_layout,
+            Matrix4::identity(),
+            "player_model",
+            true,
+        );
+
+        let texture_layout = TextureBinding::layout(&gpu.device, "player_model");
+        let texture = TextureBinding::new(
+            &gpu.device,
+            &texture_layout,
+            &create_texture(gpu, &skin, "player_model"),
+            &nearest_sampler(&gpu.device),
+            "player_model",
+        );
+
+        let shader = gpu
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../../assets/shaders/model.wgsl"));
+
+        let pipeline_layout = gpu
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&camera.layout, &model_layout, &texture_layout],
+                push_constant_ranges: &[],
+            });
+
+        let pipeline = gpu
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("player_model"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: gpu.scene_format(),
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: gpu.multisample(),
+                multiview: None,
+            });
+
+        Self {
+            pipeline,
+            vertex_buffer,
+            num_vertices: vertices.len() as u32,
+            model,
+            texture,
+        }
+    }
+
+    /// places the model with its feet at pos, facing the horizontal forward direction
+    pub fn update(&self, gpu: &Gpu, pos: Point3<f32>, forward: Vector3<f32>) {
+        let up = Vector3::unit_y();
+        // the model's +x is its left side
+        let left = up.cross(forward);
+
+        self.model.set(
+            &gpu.queue,
+            Matrix4::from_translation(pos.to_vec())
+                * Matrix4::from(Matrix3::from_cols(left, up, forward)),
+        );
+    }
+
+    pub fn render<'a>(&'a self, camera: &'a Camera, pass: &mut wgpu::RenderPass<'a>) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &camera.uniform.bind_group, &[]);
+        pass.set_bind_group(1, &self.model.bind_group, &[]);
+        pass.set_bind_group(2, &self.texture.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.draw(0..self.num_vertices, 0..1);
+    }
+}
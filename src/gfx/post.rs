@@ -0,0 +1,365 @@
+use super::util::TextureBinding;
+use serde::{Deserialize, Serialize};
+use wgpu::util::DeviceExt;
+
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PostProcessSettings {
+    /// render straight to the screen, bypassing everything below
+    pub enabled: bool,
+    pub tone_mapping: bool,
+    pub exposure: f32,
+    pub saturation: f32,
+    pub gamma: f32,
+    pub bloom: bool,
+    pub bloom_intensity: f32,
+    pub bloom_threshold: f32,
+    pub fxaa: bool,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            // opt-in so the picture only changes when asked to
+            enabled: false,
+            tone_mapping: false,
+            exposure: 1.0,
+            saturation: 1.0,
+            gamma: 1.0,
+            bloom: false,
+            bloom_intensity: 0.05,
+            bloom_threshold: 0.8,
+            fxaa: false,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PostUniform {
+    exposure: f32,
+    saturation: f32,
+    gamma: f32,
+    bloom_intensity: f32,
+    bloom_threshold: f32,
+    tone_mapping: f32,
+    texel_size: [f32; 2],
+}
+
+struct Target {
+    view: wgpu::TextureView,
+    binding: TextureBinding,
+}
+
+impl Target {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        (width, height): (u32, u32),
+        format: wgpu::TextureFormat,
+        name: &str,
+    ) -> Self {
+        let view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some(name),
+                size: wgpu::Extent3d {
+                    width: width.max(1),
+                    height: height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let binding = TextureBinding::new(device, layout, &view, sampler, name);
+
+        Self { view, binding }
+    }
+}
+
+struct Targets {
+    hdr: Target,
+    bloom: [Target; 2],
+    ldr: Target,
+}
+
+pub struct PostProcess {
+    settings: PostProcessSettings,
+    server_exposure: f32,
+    server_saturation: f32,
+    size: (u32, u32),
+    uniform: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    black: TextureBinding,
+    targets: Targets,
+    bright_pipeline: wgpu::RenderPipeline,
+    blur_h_pipeline: wgpu::RenderPipeline,
+    blur_v_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+    fxaa_pipeline: wgpu::RenderPipeline,
+}
+
+impl PostProcess {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        settings: &PostProcessSettings,
+    ) -> Option<Self> {
+        if !settings.enabled {
+            return None;
+        }
+
+        let uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("post.buffer"),
+            contents: bytemuck::cast_slice(&[<PostUniform as bytemuck::Zeroable>::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("post.bind_group_layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.as_entire_binding(),
+            }],
+            label: Some("post.bind_group"),
+        });
+
+        let texture_layout = TextureBinding::layout(device, "post_texture");
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        // bound in place of the bloom texture when bloom is disabled
+        let black = Target::new(
+            device,
+            &texture_layout,
+            &sampler,
+            (1, 1),
+            HDR_FORMAT,
+            "post.black",
+        );
+
+        let shader =
+            device.create_shader_module(wgpu::include_wgsl!("../../assets/shaders/post.wgsl"));
+
+        let pipeline = |entry: &str, format, textures: usize| {
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&layout, &texture_layout, &texture_layout][..textures + 1],
+                push_constant_ranges: &[],
+            });
+
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(&format!("post.{entry}")),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: &format!("fs_{entry}"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        Some(Self {
+            bright_pipeline: pipeline("bright", HDR_FORMAT, 1),
+            blur_h_pipeline: pipeline("blur_h", HDR_FORMAT, 1),
+            blur_v_pipeline: pipeline("blur_v", HDR_FORMAT, 1),
+            composite_pipeline: pipeline("composite", config.format, 2),
+            fxaa_pipeline: pipeline("fxaa", config.format, 1),
+            targets: Self::create_targets(device, &texture_layout, &sampler, config),
+            settings: settings.clone(),
+            server_exposure: 0.0,
+            server_saturation: 1.0,
+            size: (config.width, config.height),
+            uniform,
+            bind_group,
+            texture_layout,
+            sampler,
+            black: black.binding,
+        })
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        config: &wgpu::SurfaceConfiguration,
+    ) -> Targets {
+        let size = (config.width, config.height);
+        let half = (config.width / 2, config.height / 2);
+
+        Targets {
+            hdr: Target::new(device, layout, sampler, size, HDR_FORMAT, "post.hdr"),
+            bloom: [
+                Target::new(device, layout, sampler, half, HDR_FORMAT, "post.bloom"),
+                Target::new(device, layout, sampler, half, HDR_FORMAT, "post.bloom"),
+            ],
+            ldr: Target::new(device, layout, sampler, size, config.format, "post.ldr"),
+        }
+    }
+
+    /// the view the scene is rendered to instead of the swapchain
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        &self.targets.hdr.view
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.size = (config.width, config.height);
+        self.targets = Self::create_targets(device, &self.texture_layout, &self.sampler, config);
+    }
+
+    /// exposure correction and saturation sent by the server
+    pub fn set_lighting(&mut self, exposure: f32, saturation: f32) {
+        self.server_exposure = exposure;
+        self.server_saturation = saturation;
+    }
+
+    fn pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        target: &wgpu::TextureView,
+        inputs: &[&TextureBinding],
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("post"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        for (i, input) in inputs.iter().enumerate() {
+            pass.set_bind_group(i as u32 + 1, &input.bind_group, &[]);
+        }
+        pass.draw(0..3, 0..1);
+    }
+
+    pub fn run(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        output: &wgpu::TextureView,
+    ) {
+        let settings = &self.settings;
+        let targets = &self.targets;
+
+        // the blur passes run at half resolution and scale the texel size accordingly
+        let (width, height) = self.size;
+        queue.write_buffer(
+            &self.uniform,
+            0,
+            bytemuck::cast_slice(&[PostUniform {
+                exposure: settings.exposure * self.server_exposure.exp2(),
+                saturation: settings.saturation * self.server_saturation,
+                gamma: settings.gamma,
+                bloom_intensity: if settings.bloom {
+                    settings.bloom_intensity
+                } else {
+                    0.0
+                },
+                bloom_threshold: settings.bloom_threshold,
+                tone_mapping: if settings.tone_mapping { 1.0 } else { 0.0 },
+                texel_size: [1.0 / width.max(1) as f32, 1.0 / height.max(1) as f32],
+            }]),
+        );
+
+        let bloom = if settings.bloom {
+            self.pass(
+                encoder,
+                &self.bright_pipeline,
+                &targets.bloom[0].view,
+                &[&targets.hdr.binding],
+            );
+            self.pass(
+                encoder,
+                &self.blur_h_pipeline,
+                &targets.bloom[1].view,
+                &[&targets.bloom[0].binding],
+            );
+            self.pass(
+                encoder,
+                &self.blur_v_pipeline,
+                &targets.bloom[0].view,
+                &[&targets.bloom[1].binding],
+            );
+
+            &targets.bloom[0].binding
+        } else {
+            &self.black
+        };
+
+        if settings.fxaa {
+            self.pass(
+                encoder,
+                &self.composite_pipeline,
+                &targets.ldr.view,
+                &[&targets.hdr.binding, bloom],
+            );
+            self.pass(
+                encoder,
+                &self.fxaa_pipeline,
+                output,
+                &[&targets.ldr.binding],
+            );
+        } else {
+            self.pass(
+                encoder,
+                &self.composite_pipeline,
+                output,
+                &[&targets.hdr.binding, bloom],
+            );
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use super::player::{nodebox_boxes, NodeMap};
+use cgmath::{prelude::*, Point3, Vector3};
+use collision::Aabb3;
+
+/// reach of the empty hand in nodes, like upstream's default
+pub const HAND_RANGE: f32 = 4.0;
+
+/// the node the player is looking at
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointedNode {
+    pub under: Point3<i32>,
+    /// the node in front of the face that was hit, where nodes get placed
+    pub above: Point3<i32>,
+    /// outward normal of the face that was hit
+    pub face: Vector3<i32>,
+    pub intersection: Point3<f32>,
+    /// selection boxes of the pointed node, in world coordinates
+    pub boxes: Vec<Aabb3<f32>>,
+}
+
+impl PointedNode {
+    pub fn pointed_thing(&self) -> mt_net::PointedThing {
+        mt_net::PointedThing::Node {
+            under: self.under.map(|x| x as i16),
+            above: self.above.map(|x| x as i16),
+        }
+    }
+}
+
+/// distance along the ray at which it enters aabb and the axis it enters through,
+/// None if it misses or starts inside
+fn ray_aabb(origin: Point3<f32>, dir: Vector3<f32>, aabb: &Aabb3<f32>) -> Option<(f32, usize)> {
+    let mut enter = (f32::NEG_INFINITY, 0);
+    let mut exit = f32::INFINITY;
+
+    for axis in 0..3 {
+        if dir[axis] == 0.0 {
+            if origin[axis] < aabb.min[axis] || origin[axis] > aabb.max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let t1 = (aabb.min[axis] - origin[axis]) / dir[axis];
+        let t2 = (aabb.max[axis] - origin[axis]) / dir[axis];
+        let (near, far) = (t1.min(t2), t1.max(t2));
+
+        if near > enter.0 {
+            enter = (near, axis);
+        }
+        exit = exit.min(far);
+    }
+
+    (enter.0 >= 0.0 && enter.0 <= exit).then_some(enter)
+}
+
+/// walks the nodes along the ray (amanatides & woo) and returns the first
+/// pointable node whose selection boxes are hit within range
+pub fn raycast(
+    map: &impl NodeMap,
+    origin: Point3<f32>,
+    dir: Vector3<f32>,
+    range: f32,
+) -> Option<PointedNode> {
+    let dir = dir.normalize();
+
+    // nodes are centered on integer coordinates
+    let mut node = origin.map(|x| x.round() as i32);
+    let step = dir.map(|d| d.signum() as i32);
+
+    let mut t_max = Vector3::new(0.0, 0.0, 0.0);
+    let mut t_delta = Vector3::new(0.0, 0.0, 0.0);
+    for axis in 0..3 {
+        if dir[axis] == 0.0 {
+            t_max[axis] = f32::INFINITY;
+            t_delta[axis] = f32::INFINITY;
+        } else {
+            let boundary = node[axis] as f32 + 0.5 * step[axis] as f32;
+            t_max[axis] = (boundary - origin[axis]) / dir[axis];
+            t_delta[axis] = 1.0 / dir[axis].abs();
+        }
+    }
+
+    let mut best: Option<(f32, PointedNode)> = None;
+
+    loop {
+        if let Some(def) = map.node(node).filter(|def| def.pointable) {
+            let offset = node.cast::<f32>().unwrap().to_vec();
+            let boxes: Vec<_> = nodebox_boxes(&def.selection_box)
+                .into_iter()
+                .map(|b| Aabb3::new(b.min + offset, b.max + offset))
+                .collect();
+
+            let hit = boxes
+                .iter()
+                .filter_map(|b| ray_aabb(origin, dir, b))
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+
+            if let Some((t, axis)) = hit {
+                if t <= range && best.as_ref().map_or(true, |(best_t, _)| t < *best_t) {
+                    let mut face = Vector3::zero();
+                    face[axis] = -step[axis];
+
+                    best = Some((
+                        t,
+                        PointedNode {
+                            under: node,
+                            above: node + face,
+                            face,
+                            intersection: origin + dir * t,
+                            boxes,
+                        },
+                    ));
+                }
+            }
+        }
+
+        let axis = if t_max.x < t_max.y && t_max.x < t_max.z {
+            0
+        } else if t_max.y < t_max.z {
+            1
+        } else {
+            2
+        };
+
+        // selection boxes may reach into neighboring nodes, so a hit only
+        // counts once the ray has left the node it was found in
+        if best.as_ref().map_or(false, |(t, _)| *t <= t_max[axis]) || t_max[axis] > range {
+            break;
+        }
+
+        node[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+    }
+
+    best.map(|(_, pointed)| pointed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::player::test_map::{cube, solid, TestMap};
+    use mt_net::NodeDef;
+
+    fn cast(map: &TestMap, origin: [f32; 3], dir: [f32; 3], range: f32) -> Option<PointedNode> {
+        raycast(map, Point3::from(origin), Vector3::from(dir), range)
+    }
+
+    fn assert_near(p: Point3<f32>, x: f32, y: f32, z: f32) {
+        assert!(
+            (p - Point3::new(x, y, z)).magnitude() < 1e-4,
+            "{p:?} != ({x}, {y}, {z})"
+        );
+    }
+
+    #[test]
+    fn axis_aligned() {
+        let mut map = TestMap::default();
+        map.set(3, 0, 0, &cube());
+
+        let pointed = cast(&map, [0.0; 3], [1.0, 0.0, 0.0], 10.0).unwrap();
+        assert_eq!(pointed.under, Point3::new(3, 0, 0));
+        assert_eq!(pointed.above, Point3::new(2, 0, 0));
+        assert_eq!(pointed.face, Vector3::new(-1, 0, 0));
+        assert_near(pointed.intersection, 2.5, 0.0, 0.0);
+
+        assert_eq!(cast(&map, [0.0; 3], [-1.0, 0.0, 0.0], 10.0), None);
+    }
+
+    #[test]
+    fn looking_down() {
+        let mut map = TestMap::default();
+        map.set(0, 0, 0, &cube());
+
+        let pointed = cast(&map, [0.2, 3.3, -0.1], [0.0, -1.0, 0.0], 10.0).unwrap();
+        assert_eq!(pointed.under, Point3::new(0, 0, 0));
+        assert_eq!(pointed.above, Point3::new(0, 1, 0));
+        assert_eq!(pointed.face, Vector3::new(0, 1, 0));
+        assert_near(pointed.intersection, 0.2, 0.5, -0.1);
+    }
+
+    #[test]
+    fn diagonal() {
+        let mut map = TestMap::default();
+        map.set(2, 2, 0, &cube());
+
+        // y = x + 0.2 reaches x = 1.5 within the node's y range
+        let pointed = cast(&map, [0.1, 0.3, 0.0], [1.0, 1.0, 0.0], 10.0).unwrap();
+        assert_eq!(pointed.under, Point3::new(2, 2, 0));
+        assert_eq!(pointed.above, Point3::new(1, 2, 0));
+        assert_near(pointed.intersection, 1.5, 1.7, 0.0);
+
+        // y = x - 0.2 enters through the bottom face instead
+        let pointed = cast(&map, [0.3, 0.1, 0.0], [1.0, 1.0, 0.0], 10.0).unwrap();
+        assert_eq!(pointed.under, Point3::new(2, 2, 0));
+        assert_eq!(pointed.above, Point3::new(2, 1, 0));
+        assert_eq!(pointed.face, Vector3::new(0, -1, 0));
+        assert_near(pointed.intersection, 1.7, 1.5, 0.0);
+    }
+
+    #[test]
+    fn nearest_hit_wins() {
+        let mut map = TestMap::default();
+        map.set(2, 0, 0, &cube());
+        map.set(4, 0, 0, &cube());
+        map.set(-1, 0, 0, &cube());
+
+        let pointed = cast(&map, [0.0; 3], [1.0, 0.0, 0.0], 10.0).unwrap();
+        assert_eq!(pointed.under, Point3::new(2, 0, 0));
+    }
+
+    #[test]
+    fn starting_inside_a_node() {
+        let mut map = TestMap::default();
+        map.set(0, 0, 0, &cube());
+        map.set(2, 0, 0, &cube());
+
+        // the node the ray starts in can't be hit from the inside
+        let pointed = cast(&map, [0.1, 0.0, 0.0], [1.0, 0.0, 0.0], 10.0).unwrap();
+        assert_eq!(pointed.under, Point3::new(2, 0, 0));
+        assert_eq!(pointed.above, Point3::new(1, 0, 0));
+    }
+
+    #[test]
+    fn selection_boxes() {
+        let slab = solid([-0.5; 3], [0.5, 0.0, 0.5]);
+        let mut map = TestMap::default();
+        map.set(2, 0, 0, &slab);
+
+        assert_eq!(cast(&map, [0.0, 0.25, 0.0], [1.0, 0.0, 0.0], 10.0), None);
+
+        let pointed = cast(&map, [0.0, -0.25, 0.0], [1.0, 0.0, 0.0], 10.0).unwrap();
+        assert_eq!(pointed.under, Point3::new(2, 0, 0));
+        assert_near(pointed.intersection, 1.5, -0.25, 0.0);
+        assert_eq!(pointed.boxes.len(), 1);
+        assert_near(pointed.boxes[0].max, 2.5, 0.0, 0.5);
+
+        // from above, the top of the slab is hit
+        let pointed = cast(&map, [2.0, 3.0, 0.0], [0.0, -1.0, 0.0], 10.0).unwrap();
+        assert_eq!(pointed.above, Point3::new(2, 1, 0));
+        assert_near(pointed.intersection, 2.0, 0.0, 0.0);
+    }
+
+    #[test]
+    fn selection_box_reaching_into_neighbor() {
+        let mut map = TestMap::default();
+        map.set(2, 0, 0, &solid([-1.0, -0.5, -0.5], [0.5; 3]));
+
+        let pointed = cast(&map, [0.0; 3], [1.0, 0.0, 0.0], 10.0).unwrap();
+        assert_eq!(pointed.under, Point3::new(2, 0, 0));
+        assert_eq!(pointed.above, Point3::new(1, 0, 0));
+        assert_near(pointed.intersection, 1.0, 0.0, 0.0);
+    }
+
+    #[test]
+    fn skips_unpointable_nodes() {
+        let air = NodeDef {
+            pointable: false,
+            ..cube()
+        };
+        let mut map = TestMap::default();
+        map.set(1, 0, 0, &air);
+        map.set(3, 0, 0, &cube());
+
+        let pointed = cast(&map, [0.0; 3], [1.0, 0.0, 0.0], 10.0).unwrap();
+        assert_eq!(pointed.under, Point3::new(3, 0, 0));
+    }
+
+    #[test]
+    fn range_cutoff() {
+        let mut map = TestMap::default();
+        map.set(5, 0, 0, &cube());
+
+        assert_eq!(cast(&map, [0.0; 3], [1.0, 0.0, 0.0], 4.0), None);
+        assert_eq!(cast(&map, [0.0; 3], [1.0, 0.0, 0.0], 4.4), None);
+
+        let pointed = cast(&map, [0.0; 3], [1.0, 0.0, 0.0], 4.5).unwrap();
+        assert_eq!(pointed.under, Point3::new(5, 0, 0));
+    }
+
+    #[test]
+    fn pointed_thing() {
+        let mut map = TestMap::default();
+        map.set(0, -2, 0, &cube());
+
+        let pointed = cast(&map, [0.0; 3], [0.0, -1.0, 0.0], 10.0).unwrap();
+        assert!(matches!(
+            pointed.pointed_thing(),
+            mt_net::PointedThing::Node { under, above }
+                if under == Point3::new(0, -2, 0) && above == Point3::new(0, -1, 0)
+        ));
+    }
+}
@@ -0,0 +1,329 @@
+use super::{
+    camera::Camera,
+    gpu::Gpu,
+    media::MediaMgr,
+    raycast::PointedNode,
+    util::{create_texture, nearest_sampler, TextureBinding},
+};
+use collision::Aabb3;
+use serde::{Deserialize, Serialize};
+
+// boxes are drawn slightly larger than the node to avoid z-fighting
+const GROW: f32 = 0.002;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Highlighting {
+    #[default]
+    Box,
+    Halo,
+    None,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SelectionSettings {
+    pub highlighting: Highlighting,
+    /// color of the selection box outline
+    pub color: [u8; 3],
+}
+
+impl Default for SelectionSettings {
+    fn default() -> Self {
+        Self {
+            highlighting: Highlighting::Box,
+            color: [0, 0, 0],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    tex_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+impl Vertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+fn corners(b: &Aabb3<f32>) -> [[f32; 3]; 8] {
+    let (min, max) = (b.min.map(|x| x - GROW), b.max.map(|x| x + GROW));
+
+    [0, 1, 2, 3, 4, 5, 6, 7].map(|i| {
+        [
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        ]
+    })
+}
+
+/// the 12 edges of each box as a line list
+fn outline(boxes: &[Aabb3<f32>], color: [f32; 4]) -> Vec<Vertex> {
+    const EDGES: [(usize, usize); 12] = [
+        (0, 1),
+        (2, 3),
+        (4, 5),
+        (6, 7),
+        (0, 2),
+        (1, 3),
+        (4, 6),
+        (5, 7),
+        (0, 4),
+        (1, 5),
+        (2, 6),
+        (3, 7),
+    ];
+
+    boxes
+        .iter()
+        .flat_map(|b| {
+            let c = corners(b);
+            EDGES.into_iter().flat_map(move |(i, j)| {
+                [c[i], c[j]].map(|pos| Vertex {
+                    pos,
+                    tex_coords: [0.0, 0.0],
+                    color,
+                })
+            })
+        })
+        .collect()
+}
+
+/// the 6 faces of each box as a triangle list, tex maps face to texture coordinates
+fn faces(boxes: &[Aabb3<f32>], tex: impl Fn([f32; 2]) -> [f32; 2] + Copy) -> Vec<Vertex> {
+    // corner indices of each face, counterclockwise
+    const FACES: [[usize; 4]; 6] = [
+        [0, 2, 3, 1],
+        [4, 5, 7, 6],
+        [0, 4, 6, 2],
+        [1, 3, 7, 5],
+        [0, 1, 5, 4],
+        [2, 6, 7, 3],
+    ];
+    const TEX: [[f32; 2]; 4] = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+
+    boxes
+        .iter()
+        .flat_map(|b| {
+            let c = corners(b);
+            FACES.into_iter().flat_map(move |face| {
+                [0, 1, 2, 2, 3, 0].map(|i| Vertex {
+                    pos: c[face[i]],
+                    tex_coords: tex(TEX[i]),
+                    color: [1.0; 4],
+                })
+            })
+        })
+        .collect()
+}
+
+/// highlights the pointed node and draws the crack while digging it
+pub struct Selection {
+    line_pipeline: wgpu::RenderPipeline,
+    face_pipeline: wgpu::RenderPipeline,
+    halo: TextureBinding,
+    crack: TextureBinding,
+    crack_frames: u32,
+    vertex_buffer: wgpu::Buffer,
+    capacity: usize,
+    highlight_vertices: u32,
+    crack_vertices: u32,
+    pointed: Option<PointedNode>,
+    crack_frame: Option<u32>,
+    settings: SelectionSettings,
+}
+
+impl Selection {
+    pub fn new(gpu: &Gpu, camera: &Camera, media: &MediaMgr, settings: &SelectionSettings) -> Self {
+        let texture_layout = TextureBinding::layout(&gpu.device, "selection");
+        let halo = TextureBinding::new(
+            &gpu.device,
+            &texture_layout,
+            &create_texture(gpu, &media.texture("halo.png"), "selection.halo"),
+            &nearest_sampler(&gpu.device),
+            "selection.halo",
+        );
+
+        // the crack animation's frames are stacked vertically
+        let crack_img = media.texture("crack_anylength.png");
+        let crack_frames = (crack_img.height() / crack_img.width().max(1)).max(1);
+        let crack = TextureBinding::new(
+            &gpu.device,
+            &texture_layout,
+            &create_texture(gpu, &crack_img, "selection.crack"),
+            &nearest_sampler(&gpu.device),
+            "selection.crack",
+        );
+
+        let shader = gpu
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../../assets/shaders/selection.wgsl"));
+
+        let pipeline_layout = gpu
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&camera.layout, &texture_layout],
+                push_constant_ranges: &[],
+            });
+
+        let create_pipeline = |label, fs_entry, topology| {
+            gpu.device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[Vertex::desc()],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: fs_entry,
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: gpu.scene_format(),
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology,
+                        cull_mode: None,
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: wgpu::TextureFormat::Depth32Float,
+                        depth_write_enabled: false,
+                        depth_compare: wgpu::CompareFunction::LessEqual,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: gpu.multisample(),
+                    multiview: None,
+                })
+        };
+
+        let line_pipeline = create_pipeline(
+            "selection.line",
+            "fs_line",
+            wgpu::PrimitiveTopology::LineList,
+        );
+        let face_pipeline = create_pipeline(
+            "selection.face",
+            "fs_face",
+            wgpu::PrimitiveTopology::TriangleList,
+        );
+
+        Self {
+            line_pipeline,
+            face_pipeline,
+            halo,
+            crack,
+            crack_frames,
+            vertex_buffer: Self::create_buffer(gpu, 0),
+            capacity: 0,
+            highlight_vertices: 0,
+            crack_vertices: 0,
+            pointed: None,
+            crack_frame: None,
+            settings: settings.clone(),
+        }
+    }
+
+    fn create_buffer(gpu: &Gpu, capacity: usize) -> wgpu::Buffer {
+        gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("selection.vertex_buffer"),
+            size: (capacity.max(1) * std::mem::size_of::<Vertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn apply_settings(&mut self, settings: &SelectionSettings) {
+        if *settings != self.settings {
+            self.settings = settings.clone();
+            // rebuild the vertices on the next update
+            self.pointed = None;
+            self.highlight_vertices = 0;
+            self.crack_vertices = 0;
+        }
+    }
+
+    /// crack is the digging progress from 0 to 1
+    pub fn update(&mut self, gpu: &Gpu, pointed: Option<&PointedNode>, crack: Option<f32>) {
+        let crack_frame = crack.map(|progress| {
+            ((progress * self.crack_frames as f32) as u32).min(self.crack_frames - 1)
+        });
+
+        if pointed == self.pointed.as_ref() && crack_frame == self.crack_frame {
+            return;
+        }
+        self.pointed = pointed.cloned();
+        self.crack_frame = crack_frame;
+
+        let boxes = pointed.map(|p| p.boxes.as_slice()).unwrap_or_default();
+        let [r, g, b] = self.settings.color.map(|c| c as f32 / 255.0);
+
+        let mut vertices = match self.settings.highlighting {
+            Highlighting::Box => outline(boxes, [r, g, b, 1.0]),
+            Highlighting::Halo => faces(boxes, |uv| uv),
+            Highlighting::None => Vec::new(),
+        };
+        let highlight_vertices = vertices.len();
+
+        if let Some(frame) = crack_frame {
+            // MediaMgr flips textures, so the first frame is at the top
+            let frames = self.crack_frames as f32;
+            let first = (self.crack_frames - 1 - frame) as f32;
+            vertices.extend(faces(boxes, |[u, v]| [u, (first + v) / frames]));
+        }
+
+        if vertices.len() > self.capacity {
+            self.capacity = vertices.len().next_power_of_two();
+            self.vertex_buffer = Self::create_buffer(gpu, self.capacity);
+        }
+
+        gpu.queue
+            .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.highlight_vertices = highlight_vertices as u32;
+        self.crack_vertices = (vertices.len() - highlight_vertices) as u32;
+    }
+
+    pub fn render<'a>(&'a self, camera: &'a Camera, pass: &mut wgpu::RenderPass<'a>) {
+        if self.highlight_vertices + self.crack_vertices == 0 {
+            return;
+        }
+
+        pass.set_bind_group(0, &camera.uniform.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+
+        if self.highlight_vertices > 0 {
+            pass.set_pipeline(match self.settings.highlighting {
+                Highlighting::Halo => &self.face_pipeline,
+                _ => &self.line_pipeline,
+            });
+            pass.set_bind_group(1, &self.halo.bind_group, &[]);
+            pass.draw(0..self.highlight_vertices, 0..1);
+        }
+
+        if self.crack_vertices > 0 {
+            let start = self.highlight_vertices;
+            pass.set_pipeline(&self.face_pipeline);
+            pass.set_bind_group(1, &self.crack.bind_group, &[]);
+            pass.draw(start..start + self.crack_vertices, 0..1);
+        }
+    }
+}
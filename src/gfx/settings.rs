@@ -0,0 +1,70 @@
+use super::{
+    crosshair::CrosshairSettings, gamepad::GamepadSettings, input::Bindings,
+    map::MapRenderSettings, post::PostProcessSettings, selection::SelectionSettings,
+};
+use serde::{Deserialize, Serialize};
+
+const PATH: &str = "mt_client.yml";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub map: MapRenderSettings,
+    pub post: PostProcessSettings,
+    /// number of MSAA samples, 1 disables multisampling
+    pub msaa: u32,
+    /// field of view in degrees
+    pub fov: f32,
+    /// size of HUD elements relative to upstream's default
+    pub hud_scale: f32,
+    pub keys: Bindings,
+    pub mouse: MouseSettings,
+    pub bobbing: BobbingSettings,
+    pub gamepad: GamepadSettings,
+    pub selection: SelectionSettings,
+    pub crosshair: CrosshairSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            map: Default::default(),
+            post: Default::default(),
+            msaa: 1,
+            fov: 90.0,
+            hud_scale: 1.0,
+            keys: Default::default(),
+            mouse: Default::default(),
+            bobbing: Default::default(),
+            gamepad: Default::default(),
+            selection: Default::default(),
+            crosshair: Default::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        match std::fs::read_to_string(PATH) {
+            Ok(config) => serde_yaml::from_str(&config).unwrap_or_else(|e| {
+                eprintln!("while loading {PATH}: {e}");
+                Default::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Default::default(),
+            Err(e) => {
+                eprintln!("while loading {PATH}: {e}");
+                Default::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let res = serde_yaml::to_string(self)
+            .map_err(|e| e.to_string())
+            .and_then(|config| std::fs::write(PATH, config).map_err(|e| e.to_string()));
+
+        if let Err(e) = res {
+            eprintln!("while saving {PATH}: {e}");
+        }
+    }
+}
@@ -0,0 +1,875 @@
+use super::{
+    camera::Camera,
+    gpu::Gpu,
+    media::MediaMgr,
+    util::{color_to_rgba, create_texture, nearest_sampler, TextureBinding},
+};
+use cgmath::{prelude::*, Matrix3, Matrix4, Rad, Vector2, Vector3, Vector4};
+use rand::{Rng, SeedableRng};
+use std::time::Duration;
+use wgpu::util::DeviceExt;
+
+// skybox texture order sent by the server is Y+, Y-, X-, X+, Z+, Z-
+const SKYBOX_FACES: [usize; 6] = [0, 1, 3, 2, 4, 5];
+
+const CLOUD_SIZE: f32 = 16.0;
+const CLOUD_RADIUS: i32 = 12;
+const CLOUD_VERTICES: usize = ((2 * CLOUD_RADIUS + 1) * (2 * CLOUD_RADIUS + 1)) as usize * 36;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SkyUniform {
+    view_proj: [[f32; 4]; 4],
+    inv_view_proj: [[f32; 4]; 4],
+    sky_color: [f32; 4],
+    horizon_color: [f32; 4],
+    cloud_color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct BodyVertex {
+    pos: [f32; 3],
+    tex_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+impl BodyVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CloudVertex {
+    pos: [f32; 3],
+    shade: f32,
+}
+
+impl CloudVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+enum SkyType {
+    Regular,
+    Skybox,
+    Plain,
+}
+
+struct SkyColors {
+    day_sky: Vector3<f32>,
+    day_horizon: Vector3<f32>,
+    dawn_sky: Vector3<f32>,
+    dawn_horizon: Vector3<f32>,
+    night_sky: Vector3<f32>,
+    night_horizon: Vector3<f32>,
+}
+
+fn rgb(r: u8, g: u8, b: u8) -> Vector3<f32> {
+    Vector3::new(r as f32, g as f32, b as f32) / 255.0
+}
+
+fn color_rgb(color: mt_net::Color) -> Vector3<f32> {
+    let [r, g, b, _] = color_to_rgba(color);
+    Vector3::new(r, g, b)
+}
+
+impl Default for SkyColors {
+    fn default() -> Self {
+        Self {
+            day_sky: rgb(0x61, 0xB5, 0xF5),
+            day_horizon: rgb(0x90, 0xD3, 0xF6),
+            dawn_sky: rgb(0xB4, 0xBA, 0xFA),
+            dawn_horizon: rgb(0xBA, 0xC1, 0xF0),
+            night_sky: rgb(0x00, 0x6B, 0xFF),
+            night_horizon: rgb(0x40, 0x90, 0xFF),
+        }
+    }
+}
+
+struct Body {
+    visible: bool,
+    size: f32,
+    texture: TextureBinding,
+}
+
+struct Clouds {
+    enabled: bool,
+    density: f32,
+    diffuse_color: [f32; 4],
+    ambient_color: [f32; 4],
+    height: f32,
+    thickness: f32,
+    speed: Vector2<f32>,
+    offset: Vector2<f32>,
+}
+
+impl Default for Clouds {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            density: 0.4,
+            diffuse_color: [1.0, 0.941, 0.941, 0.898],
+            ambient_color: [0.0, 0.0, 0.0, 1.0],
+            height: 120.0,
+            thickness: 16.0,
+            speed: Vector2::new(0.0, -2.0),
+            offset: Vector2::zero(),
+        }
+    }
+}
+
+/// the latest sky parameters from the server, which may arrive before the sky is created
+#[derive(Default)]
+pub struct SkyState {
+    pub sky: Option<Box<mt_net::SkyParams>>,
+    pub sun: Option<mt_net::SunParams>,
+    pub moon: Option<mt_net::MoonParams>,
+    pub stars: Option<mt_net::StarParams>,
+    pub clouds: Option<mt_net::CloudParams>,
+    /// time of day and speed
+    pub time: Option<(u16, f32)>,
+}
+
+pub struct Sky {
+    pub time_of_day: f32,
+    pub time_speed: f32,
+    sky_type: SkyType,
+    bg_color: Vector3<f32>,
+    colors: SkyColors,
+    skybox: Vec<TextureBinding>,
+    sun: Body,
+    moon: Body,
+    sunrise: Body,
+    sunrise_visible: bool,
+    stars: Body,
+    star_dirs: Vec<Vector3<f32>>,
+    star_color: [f32; 4],
+    clouds: Clouds,
+    uniform: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    dome_pipeline: wgpu::RenderPipeline,
+    body_pipeline: wgpu::RenderPipeline,
+    body_vertices: Vec<BodyVertex>,
+    body_buffer: wgpu::Buffer,
+    body_buffer_cap: usize,
+    cloud_pipeline: wgpu::RenderPipeline,
+    cloud_vertices: Vec<CloudVertex>,
+    cloud_buffer: wgpu::Buffer,
+}
+
+fn disc_img(color: [u8; 3]) -> image::RgbaImage {
+    image::RgbaImage::from_fn(32, 32, |x, y| {
+        let d = ((x as f32 - 15.5).powi(2) + (y as f32 - 15.5).powi(2)).sqrt();
+        let a = (16.0 - d).clamp(0.0, 1.0);
+        image::Rgba([color[0], color[1], color[2], (a * 255.0) as u8])
+    })
+}
+
+fn body_img(media: &MediaMgr, texture: &str, fallback: [u8; 3]) -> image::RgbaImage {
+    let base = texture.split('^').next().unwrap_or_default();
+
+    if media.get(base).is_some() {
+        media.texture_string(texture)
+    } else {
+        disc_img(fallback)
+    }
+}
+
+// smooth value noise with three octaves, used to lay out cloud cells
+fn cloud_noise(x: i32, z: i32) -> f32 {
+    fn hash(x: i32, z: i32) -> f32 {
+        let mut h = (x as u32).wrapping_mul(0x8da6b343) ^ (z as u32).wrapping_mul(0xd8163841);
+        h = (h ^ (h >> 13)).wrapping_mul(0x5bd1e995);
+        (h ^ (h >> 15)) as f32 / u32::MAX as f32
+    }
+
+    fn smooth(x: i32, z: i32, scale: i32) -> f32 {
+        let (cx, cz) = (x.div_euclid(scale), z.div_euclid(scale));
+        let fx = x.rem_euclid(scale) as f32 / scale as f32;
+        let fz = z.rem_euclid(scale) as f32 / scale as f32;
+
+        use lerp::Lerp;
+        let top = hash(cx, cz).lerp(hash(cx + 1, cz), fx);
+        let bottom = hash(cx, cz + 1).lerp(hash(cx + 1, cz + 1), fx);
+        top.lerp(bottom, fz)
+    }
+
+    smooth(x, z, 8) * 0.57 + smooth(x, z, 4) * 0.29 + smooth(x, z, 2) * 0.14
+}
+
+fn body_quad(
+    vertices: &mut Vec<BodyVertex>,
+    dir: Vector3<f32>,
+    size: f32,
+    color: [f32; 4],
+    tex_coords: [[f32; 2]; 2],
+) {
+    let helper = if dir.y.abs() > 0.99 {
+        Vector3::unit_x()
+    } else {
+        Vector3::unit_y()
+    };
+
+    let right = dir.cross(helper).normalize() * size;
+    let up = right.cross(dir).normalize() * size;
+
+    let corner = |u: usize, v: usize| BodyVertex {
+        pos: (dir + right * (u as f32 * 2.0 - 1.0) + up * (v as f32 * 2.0 - 1.0)).into(),
+        tex_coords: [tex_coords[u][0], tex_coords[v][1]],
+        color,
+    };
+
+    vertices.extend([
+        corner(0, 0),
+        corner(1, 0),
+        corner(1, 1),
+        corner(1, 1),
+        corner(0, 1),
+        corner(0, 0),
+    ]);
+}
+
+fn create_pipeline(
+    gpu: &Gpu,
+    layouts: &[&wgpu::BindGroupLayout],
+    shader: &wgpu::ShaderModule,
+    entry: &str,
+    buffers: &[wgpu::VertexBufferLayout],
+    depth_write_enabled: bool,
+    depth_compare: wgpu::CompareFunction,
+) -> wgpu::RenderPipeline {
+    let layout = gpu
+        .device
+        .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: layouts,
+            push_constant_ranges: &[],
+        });
+
+    gpu.device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&format!("sky.{entry}")),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: &format!("vs_{entry}"),
+                buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: &format!("fs_{entry}"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: gpu.scene_format(),
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled,
+                depth_compare,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: gpu.multisample(),
+            multiview: None,
+        })
+}
+
+impl Sky {
+    pub fn new(gpu: &Gpu, camera: &Camera, media: &MediaMgr) -> Self {
+        let uniform = gpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("sky.buffer"),
+                contents: bytemuck::cast_slice(&[<SkyUniform as bytemuck::Zeroable>::zeroed()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        let layout = gpu
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("sky.bind_group_layout"),
+            });
+
+        let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.as_entire_binding(),
+            }],
+            label: Some("sky.bind_group"),
+        });
+
+        let texture_layout = TextureBinding::layout(&gpu.device, "sky_texture");
+        let sampler = nearest_sampler(&gpu.device);
+
+        let shader = gpu
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../../assets/shaders/sky.wgsl"));
+
+        use wgpu::CompareFunction::*;
+
+        let dome_pipeline = create_pipeline(gpu, &[&layout], &shader, "dome", &[], false, Always);
+        let body_pipeline = create_pipeline(
+            gpu,
+            &[&layout, &texture_layout],
+            &shader,
+            "body",
+            &[BodyVertex::desc()],
+            false,
+            Always,
+        );
+        let cloud_pipeline = create_pipeline(
+            gpu,
+            &[&layout, &camera.layout],
+            &shader,
+            "cloud",
+            &[CloudVertex::desc()],
+            true,
+            Less,
+        );
+
+        let cloud_buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("clouds.vertex_buffer"),
+            size: (CLOUD_VERTICES * std::mem::size_of::<CloudVertex>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let body = |img: image::RgbaImage, name: &str, size: f32| Body {
+            visible: true,
+            size,
+            texture: TextureBinding::new(
+                &gpu.device,
+                &texture_layout,
+                &create_texture(gpu, &img, name),
+                &sampler,
+                name,
+            ),
+        };
+
+        let sun = body(body_img(media, "sun.png", [255, 240, 200]), "sun", 1.0);
+        let moon = body(body_img(media, "moon.png", [220, 220, 240]), "moon", 1.0);
+        let sunrise = body(media.texture("sunrisebg.png"), "sunrise", 1.0);
+        let stars = body(disc_img([255, 255, 255]), "stars", 1.0);
+
+        let mut sky = Self {
+            time_of_day: 0.5,
+            time_speed: 72.0,
+            sky_type: SkyType::Regular,
+            bg_color: Vector3::zero(),
+            colors: Default::default(),
+            skybox: Vec::new(),
+            sun,
+            moon,
+            sunrise,
+            sunrise_visible: true,
+            stars,
+            star_dirs: Vec::new(),
+            star_color: [0.92, 0.92, 1.0, 0.41],
+            clouds: Default::default(),
+            uniform,
+            bind_group,
+            texture_layout,
+            sampler,
+            dome_pipeline,
+            body_pipeline,
+            body_vertices: Vec::new(),
+            body_buffer: gpu.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("sky.vertex_buffer"),
+                size: 0,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            body_buffer_cap: 0,
+            cloud_pipeline,
+            cloud_vertices: Vec::with_capacity(CLOUD_VERTICES),
+            cloud_buffer,
+        };
+
+        sky.set_star_count(1000);
+        sky
+    }
+
+    fn texture(&self, gpu: &Gpu, img: &image::RgbaImage, name: &str) -> TextureBinding {
+        TextureBinding::new(
+            &gpu.device,
+            &self.texture_layout,
+            &create_texture(gpu, img, name),
+            &self.sampler,
+            name,
+        )
+    }
+
+    fn set_star_count(&mut self, count: usize) {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+
+        self.star_dirs = (0..count)
+            .map(|_| {
+                loop {
+                    let v = Vector3::new(
+                        rng.gen_range(-1.0..1.0),
+                        rng.gen_range(-1.0..1.0),
+                        rng.gen_range(-1.0..1.0),
+                    );
+
+                    // rejection sampling for uniform distribution on the sphere
+                    let len = v.magnitude2();
+                    if len > 0.01 && len <= 1.0 {
+                        break v.normalize();
+                    }
+                }
+            })
+            .collect();
+    }
+
+    /// applies everything the server has set so far
+    pub fn apply(&mut self, gpu: &Gpu, media: &MediaMgr, state: &SkyState) {
+        if let Some(params) = &state.sky {
+            self.set_sky(gpu, media, params);
+        }
+        if let Some(params) = &state.sun {
+            self.set_sun(gpu, media, params);
+        }
+        if let Some(params) = &state.moon {
+            self.set_moon(gpu, media, params);
+        }
+        if let Some(params) = &state.stars {
+            self.set_stars(params);
+        }
+        if let Some(params) = &state.clouds {
+            self.set_clouds(params);
+        }
+        if let Some((time, speed)) = state.time {
+            self.set_time(time, speed);
+        }
+    }
+
+    pub fn set_sky(&mut self, gpu: &Gpu, media: &MediaMgr, params: &mt_net::SkyParams) {
+        self.bg_color = color_rgb(params.bg_color);
+        self.clouds.enabled = params.clouds;
+        self.skybox.clear();
+
+        self.sky_type = match params.sky_type.as_str() {
+            "skybox" if params.textures.len() == 6 => {
+                self.skybox = params
+                    .textures
+                    .iter()
+                    .map(|tex| self.texture(gpu, &media.texture_string(tex), "skybox"))
+                    .collect();
+
+                SkyType::Skybox
+            }
+            "plain" | "skybox" => SkyType::Plain,
+            _ => SkyType::Regular,
+        };
+
+        self.colors = SkyColors {
+            day_sky: color_rgb(params.day_sky),
+            day_horizon: color_rgb(params.day_horizon),
+            dawn_sky: color_rgb(params.dawn_sky),
+            dawn_horizon: color_rgb(params.dawn_horizon),
+            night_sky: color_rgb(params.night_sky),
+            night_horizon: color_rgb(params.night_horizon),
+        };
+    }
+
+    pub fn set_sun(&mut self, gpu: &Gpu, media: &MediaMgr, params: &mt_net::SunParams) {
+        self.sun.visible = params.visible;
+        self.sun.size = params.size;
+        self.sun.texture = self.texture(
+            gpu,
+            &body_img(media, &params.texture, [255, 240, 200]),
+            "sun",
+        );
+        self.sunrise_visible = params.rising;
+        self.sunrise.texture = self.texture(
+            gpu,
+            &media.texture_string(match params.rise.as_str() {
+                "" => "sunrisebg.png",
+                rise => rise,
+            }),
+            "sunrise",
+        );
+    }
+
+    pub fn set_moon(&mut self, gpu: &Gpu, media: &MediaMgr, params: &mt_net::MoonParams) {
+        self.moon.visible = params.visible;
+        self.moon.size = params.size;
+        self.moon.texture = self.texture(
+            gpu,
+            &body_img(media, &params.texture, [220, 220, 240]),
+            "moon",
+        );
+    }
+
+    pub fn set_stars(&mut self, params: &mt_net::StarParams) {
+        self.stars.visible = params.visible;
+        self.stars.size = params.size;
+        self.star_color = color_to_rgba(params.color);
+
+        if params.count as usize != self.star_dirs.len() {
+            self.set_star_count(params.count as usize);
+        }
+    }
+
+    pub fn set_clouds(&mut self, params: &mt_net::CloudParams) {
+        self.clouds.density = params.density;
+        self.clouds.diffuse_color = color_to_rgba(params.diffuse_color);
+        self.clouds.ambient_color = color_to_rgba(params.ambient_color);
+        self.clouds.height = params.height;
+        self.clouds.thickness = params.thickness;
+        self.clouds.speed = params.speed;
+    }
+
+    pub fn set_time(&mut self, time: u16, speed: f32) {
+        self.time_of_day = time as f32 / 24000.0;
+        self.time_speed = speed;
+    }
+
+    fn sun_angle(&self) -> Rad<f32> {
+        Rad((self.time_of_day - 0.25) * std::f32::consts::TAU)
+    }
+
+    /// direction towards the sun during the day and towards the moon at night
+    pub fn light_dir(&self) -> Vector3<f32> {
+        let dir = Matrix3::from_angle_z(self.sun_angle()) * Vector3::unit_x();
+
+        if dir.y >= 0.0 {
+            dir
+        } else {
+            -dir
+        }
+    }
+
+    pub fn update(&mut self, gpu: &Gpu, camera: &Camera, dt: Duration) {
+        let dt = dt.as_secs_f32();
+
+        // one in-game day has 86400 seconds, time_speed is the ratio to real time
+        self.time_of_day = (self.time_of_day + dt * self.time_speed / 86400.0).rem_euclid(1.0);
+        self.clouds.offset += self.clouds.speed * dt;
+
+        let angle = self.sun_angle();
+        let sun_dir = Vector3::new(angle.0.cos(), angle.0.sin(), 0.0);
+        let day = ((sun_dir.y + 0.1) / 0.3).clamp(0.0, 1.0);
+        let dawn = (1.0 - sun_dir.y.abs() / 0.25).clamp(0.0, 1.0);
+
+        let night = 0.1;
+        let mix = |night_color: Vector3<f32>, day_color: Vector3<f32>, dawn_color: Vector3<f32>| {
+            let base = night_color * night * (1.0 - day) + day_color * day;
+            base * (1.0 - dawn * 0.6) + dawn_color * dawn * 0.6
+        };
+
+        let (sky_color, horizon_color) = match self.sky_type {
+            SkyType::Regular => (
+                mix(
+                    self.colors.night_sky,
+                    self.colors.day_sky,
+                    self.colors.dawn_sky,
+                ),
+                mix(
+                    self.colors.night_horizon,
+                    self.colors.day_horizon,
+                    self.colors.dawn_horizon,
+                ),
+            ),
+            _ => (self.bg_color, self.bg_color),
+        };
+
+        let brightness = night + (1.0 - night) * day;
+        let [dr, dg, db, da] = self.clouds.diffuse_color;
+        let [ar, ag, ab, _] = self.clouds.ambient_color;
+        let cloud_color = [
+            (dr * brightness + ar).min(1.0),
+            (dg * brightness + ag).min(1.0),
+            (db * brightness + ab).min(1.0),
+            da,
+        ];
+
+        let mut view = camera.view;
+        view.w = Vector4::unit_w();
+        let view_proj = camera.proj * view;
+
+        gpu.queue.write_buffer(
+            &self.uniform,
+            0,
+            bytemuck::cast_slice(&[SkyUniform {
+                view_proj: view_proj.into(),
+                inv_view_proj: view_proj.invert().unwrap_or_else(Matrix4::identity).into(),
+                sky_color: sky_color.extend(1.0).into(),
+                horizon_color: horizon_color.extend(1.0).into(),
+                cloud_color,
+            }]),
+        );
+
+        self.update_bodies(gpu, angle, day, dawn);
+        self.update_clouds(gpu, camera);
+    }
+
+    fn update_bodies(&mut self, gpu: &Gpu, angle: Rad<f32>, day: f32, dawn: f32) {
+        let full = [[0.0, 0.0], [1.0, 1.0]];
+        let white = [1.0, 1.0, 1.0, 1.0];
+        let rot = Matrix3::from_angle_z(angle);
+
+        self.body_vertices.clear();
+
+        if self.sky_type == SkyType::Skybox {
+            for &f in SKYBOX_FACES.iter() {
+                for (pos, tex_coords) in super::map::CUBE[f] {
+                    self.body_vertices.push(BodyVertex {
+                        pos,
+                        tex_coords,
+                        color: white,
+                    });
+                }
+            }
+        }
+
+        let sunrise_dir = if self.time_of_day < 0.5 { 1.0 } else { -1.0 };
+        let sunrise_alpha = if self.sunrise_visible { dawn } else { 0.0 };
+        body_quad(
+            &mut self.body_vertices,
+            Vector3::new(sunrise_dir, 0.1, 0.0),
+            0.6,
+            [1.0, 1.0, 1.0, sunrise_alpha],
+            full,
+        );
+
+        let sun_alpha = if self.sun.visible { 1.0 } else { 0.0 };
+        body_quad(
+            &mut self.body_vertices,
+            rot * Vector3::unit_x(),
+            0.07 * self.sun.size,
+            [1.0, 1.0, 1.0, sun_alpha],
+            full,
+        );
+
+        let moon_alpha = if self.moon.visible { 1.0 } else { 0.0 };
+        body_quad(
+            &mut self.body_vertices,
+            rot * -Vector3::unit_x(),
+            0.05 * self.moon.size,
+            [1.0, 1.0, 1.0, moon_alpha],
+            full,
+        );
+
+        if self.stars.visible {
+            let mut color = self.star_color;
+            color[3] *= 1.0 - day;
+
+            if color[3] > 0.0 {
+                for &dir in self.star_dirs.iter() {
+                    body_quad(
+                        &mut self.body_vertices,
+                        rot * dir,
+                        0.002 * self.stars.size,
+                        color,
+                        full,
+                    );
+                }
+            }
+        }
+
+        if self.body_vertices.len() > self.body_buffer_cap {
+            self.body_buffer_cap = self.body_vertices.len().next_power_of_two();
+            self.body_buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("sky.vertex_buffer"),
+                size: (self.body_buffer_cap * std::mem::size_of::<BodyVertex>()) as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
+
+        gpu.queue.write_buffer(
+            &self.body_buffer,
+            0,
+            bytemuck::cast_slice(&self.body_vertices),
+        );
+    }
+
+    fn update_clouds(&mut self, gpu: &Gpu, camera: &Camera) {
+        self.cloud_vertices.clear();
+
+        if !self.clouds.enabled || self.clouds.density <= 0.0 {
+            return;
+        }
+
+        let offset = self.clouds.offset;
+        let center = Vector2::new(
+            ((camera.pos.x - offset.x) / CLOUD_SIZE).floor() as i32,
+            ((camera.pos.z - offset.y) / CLOUD_SIZE).floor() as i32,
+        );
+
+        let filled = |x: i32, z: i32| cloud_noise(x, z) < self.clouds.density;
+        let bottom = self.clouds.height;
+        let top = bottom + self.clouds.thickness;
+
+        for dz in -CLOUD_RADIUS..=CLOUD_RADIUS {
+            for dx in -CLOUD_RADIUS..=CLOUD_RADIUS {
+                let (cx, cz) = (center.x + dx, center.y + dz);
+
+                if !filled(cx, cz) {
+                    continue;
+                }
+
+                let x0 = cx as f32 * CLOUD_SIZE + offset.x;
+                let z0 = cz as f32 * CLOUD_SIZE + offset.y;
+                let (x1, z1) = (x0 + CLOUD_SIZE, z0 + CLOUD_SIZE);
+
+                let mut quad = |corners: [[f32; 3]; 4], shade: f32| {
+                    let [a, b, c, d] = corners.map(|pos| CloudVertex { pos, shade });
+                    self.cloud_vertices.extend([a, b, c, c, d, a]);
+                };
+
+                if self.clouds.thickness <= 0.0 {
+                    quad(
+                        [[x0, top, z0], [x1, top, z0], [x1, top, z1], [x0, top, z1]],
+                        1.0,
+                    );
+                    continue;
+                }
+
+                quad(
+                    [[x0, top, z0], [x1, top, z0], [x1, top, z1], [x0, top, z1]],
+                    1.0,
+                );
+                quad(
+                    [
+                        [x0, bottom, z0],
+                        [x1, bottom, z0],
+                        [x1, bottom, z1],
+                        [x0, bottom, z1],
+                    ],
+                    0.7,
+                );
+
+                if !filled(cx - 1, cz) {
+                    quad(
+                        [
+                            [x0, bottom, z0],
+                            [x0, top, z0],
+                            [x0, top, z1],
+                            [x0, bottom, z1],
+                        ],
+                        0.9,
+                    );
+                }
+                if !filled(cx + 1, cz) {
+                    quad(
+                        [
+                            [x1, bottom, z0],
+                            [x1, top, z0],
+                            [x1, top, z1],
+                            [x1, bottom, z1],
+                        ],
+                        0.9,
+                    );
+                }
+                if !filled(cx, cz - 1) {
+                    quad(
+                        [
+                            [x0, bottom, z0],
+                            [x1, bottom, z0],
+                            [x1, top, z0],
+                            [x0, top, z0],
+                        ],
+                        0.8,
+                    );
+                }
+                if !filled(cx, cz + 1) {
+                    quad(
+                        [
+                            [x0, bottom, z1],
+                            [x1, bottom, z1],
+                            [x1, top, z1],
+                            [x0, top, z1],
+                        ],
+                        0.8,
+                    );
+                }
+            }
+        }
+
+        gpu.queue.write_buffer(
+            &self.cloud_buffer,
+            0,
+            bytemuck::cast_slice(&self.cloud_vertices),
+        );
+    }
+
+    pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        pass.set_pipeline(&self.dome_pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+
+        pass.set_pipeline(&self.body_pipeline);
+        pass.set_vertex_buffer(0, self.body_buffer.slice(..));
+
+        let stars = self.body_vertices.len() as u32 - (self.skybox.len() as u32 + 3) * 6;
+        let bodies = self.skybox.iter().map(|texture| (texture, 6)).chain([
+            (&self.sunrise.texture, 6),
+            (&self.sun.texture, 6),
+            (&self.moon.texture, 6),
+            (&self.stars.texture, stars),
+        ]);
+
+        let mut start = 0;
+        for (texture, len) in bodies {
+            if len > 0 {
+                pass.set_bind_group(1, &texture.bind_group, &[]);
+                pass.draw(start..start + len, 0..1);
+            }
+
+            start += len;
+        }
+    }
+
+    pub fn render_clouds<'a>(&'a self, camera: &'a Camera, pass: &mut wgpu::RenderPass<'a>) {
+        if self.cloud_vertices.is_empty() {
+            return;
+        }
+
+        pass.set_pipeline(&self.cloud_pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_bind_group(1, &camera.uniform.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.cloud_buffer.slice(..));
+        pass.draw(0..self.cloud_vertices.len() as u32, 0..1);
+    }
+}
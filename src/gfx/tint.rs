@@ -0,0 +1,120 @@
+use super::{gpu::Gpu, util::srgb_to_linear};
+use wgpu::util::DeviceExt;
+
+/// colors the whole scene, used for the post_effect_color of the node the camera is in
+pub struct ScreenTint {
+    pipeline: wgpu::RenderPipeline,
+    uniform: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    color: [f32; 4],
+}
+
+impl ScreenTint {
+    pub fn new(gpu: &Gpu) -> Self {
+        let uniform = gpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("tint.buffer"),
+                contents: bytemuck::cast_slice(&[[0.0f32; 4]]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        let layout = gpu
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("tint.bind_group_layout"),
+            });
+
+        let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.as_entire_binding(),
+            }],
+            label: Some("tint.bind_group"),
+        });
+
+        let shader = gpu
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../../assets/shaders/tint.wgsl"));
+
+        let pipeline_layout = gpu
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            });
+
+        let pipeline = gpu
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("tint"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: gpu.scene_format(),
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: gpu.multisample(),
+                multiview: None,
+            });
+
+        Self {
+            pipeline,
+            uniform,
+            bind_group,
+            color: [0.0; 4],
+        }
+    }
+
+    /// sets the tint from an sRGB color like post_effect_color
+    pub fn set_color(&mut self, gpu: &Gpu, color: [f32; 4]) {
+        if color != self.color {
+            self.color = color;
+
+            // the scene is drawn in linear color, only the alpha stays as is
+            let [r, g, b, a] = color;
+            let linear = [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a];
+            gpu.queue
+                .write_buffer(&self.uniform, 0, bytemuck::cast_slice(&[linear]));
+        }
+    }
+
+    pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        if self.color[3] == 0.0 {
+            return;
+        }
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
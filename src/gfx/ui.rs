@@ -0,0 +1,570 @@
+use super::{
+    font::Font,
+    gpu::{Frame, Gpu},
+    media::MediaMgr,
+    util::{create_texture, nearest_sampler, TextureBinding},
+};
+use cgmath::{prelude::*, Rad};
+use std::collections::HashMap;
+use wgpu::util::DeviceExt;
+use wgpu_glyph::{OwnedSection, Section};
+
+/// a rectangle on screen in pixels, from the top left corner
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Rect {
+    /// texture coordinates covering a whole texture
+    pub const UNIT: Rect = Rect {
+        x: 0.0,
+        y: 0.0,
+        w: 1.0,
+        h: 1.0,
+    };
+
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.w && y < self.y + self.h
+    }
+
+    /// the overlap of both rectangles, None if they don't overlap
+    pub fn intersect(self, other: Rect) -> Option<Rect> {
+        let x0 = self.x.max(other.x);
+        let y0 = self.y.max(other.y);
+        let x1 = (self.x + self.w).min(other.x + other.w);
+        let y1 = (self.y + self.h).min(other.y + other.h);
+
+        (x0 < x1 && y0 < y1).then(|| Rect::new(x0, y0, x1 - x0, y1 - y0))
+    }
+
+    /// the part of self inside clip, along with the matching part of the texture coordinates uv
+    pub fn clip(self, uv: Rect, clip: Rect) -> Option<(Rect, Rect)> {
+        let part = self.intersect(clip)?;
+
+        let u = |x: f32| uv.x + (x - self.x) / self.w * uv.w;
+        let v = |y: f32| uv.y + (y - self.y) / self.h * uv.h;
+
+        Some((
+            part,
+            Rect::new(
+                u(part.x),
+                v(part.y),
+                u(part.x + part.w) - u(part.x),
+                v(part.y + part.h) - v(part.y),
+            ),
+        ))
+    }
+}
+
+/// the nine parts of a texture stretched over rect and their texture coordinates,
+/// the borders keep their size given in texture pixels as left, top, right and bottom
+pub fn nine_slice(rect: Rect, texture: (u32, u32), border: [f32; 4]) -> Vec<(Rect, Rect)> {
+    // shrink borders that don't fit into rect
+    let fit = |a: f32, b: f32, size: f32| {
+        let f = if a + b > size { size / (a + b) } else { 1.0 };
+        (a * f, b * f)
+    };
+
+    let (left, right) = fit(border[0], border[2], rect.w);
+    let (top, bottom) = fit(border[1], border[3], rect.h);
+    let (w, h) = (texture.0.max(1) as f32, texture.1.max(1) as f32);
+
+    let xs = [
+        rect.x,
+        rect.x + left,
+        rect.x + rect.w - right,
+        rect.x + rect.w,
+    ];
+    let ys = [
+        rect.y,
+        rect.y + top,
+        rect.y + rect.h - bottom,
+        rect.y + rect.h,
+    ];
+    let us = [0.0, border[0] / w, 1.0 - border[2] / w, 1.0];
+    let vs = [0.0, border[1] / h, 1.0 - border[3] / h, 1.0];
+
+    let mut parts = Vec::new();
+    for row in 0..3 {
+        for col in 0..3 {
+            let dst = Rect::new(
+                xs[col],
+                ys[row],
+                xs[col + 1] - xs[col],
+                ys[row + 1] - ys[row],
+            );
+            if dst.w > 0.0 && dst.h > 0.0 {
+                let uv = Rect::new(
+                    us[col],
+                    vs[row],
+                    us[col + 1] - us[col],
+                    vs[row + 1] - vs[row],
+                );
+                parts.push((dst, uv));
+            }
+        }
+    }
+
+    parts
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    pos: [f32; 2],
+    tex_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+impl Vertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// consecutive quads sharing a texture, layer and clip rectangle
+struct Batch {
+    texture: usize,
+    z: i32,
+    clip: Option<Rect>,
+    start: u32,
+    end: u32,
+}
+
+struct UiText {
+    z: i32,
+    clip: Option<Rect>,
+    section: OwnedSection,
+}
+
+struct UiTexture {
+    binding: TextureBinding,
+    size: (u32, u32),
+}
+
+/// draws textured rectangles and text on top of the finished frame,
+/// ordered by layer and then by the order they were added in
+pub struct Ui {
+    pipeline: wgpu::RenderPipeline,
+    screen: wgpu::Buffer,
+    screen_bind_group: wgpu::BindGroup,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// None until loaded on the next submit
+    textures: Vec<Option<UiTexture>>,
+    texture_ids: HashMap<String, usize>,
+    pending: Vec<(usize, String)>,
+    screen_size: (f32, f32),
+    /// layer of things added, higher ones are drawn on top
+    z: i32,
+    /// clip rectangles, each one already inside the previous one
+    clips: Vec<Rect>,
+    batches: Vec<Batch>,
+    texts: Vec<UiText>,
+    vertices: Vec<Vertex>,
+    vertex_buffer: wgpu::Buffer,
+    capacity: usize,
+}
+
+// id of the white texture used for plain colored rectangles
+const WHITE: usize = 0;
+
+impl Ui {
+    pub fn new(gpu: &Gpu) -> Self {
+        let screen = gpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("ui.screen"),
+                contents: bytemuck::cast_slice(&[[1.0f32; 2]]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        let screen_layout = gpu
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("ui.screen_bind_group_layout"),
+            });
+
+        let screen_bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &screen_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: screen.as_entire_binding(),
+            }],
+            label: Some("ui.screen_bind_group"),
+        });
+
+        let texture_layout = TextureBinding::layout(&gpu.device, "ui");
+
+        let shader = gpu
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../../assets/shaders/ui.wgsl"));
+
+        let pipeline_layout = gpu
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&screen_layout, &texture_layout],
+                push_constant_ranges: &[],
+            });
+
+        let pipeline = gpu
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("ui"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: gpu.config.format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            });
+
+        let mut ui = Self {
+            pipeline,
+            screen,
+            screen_bind_group,
+            texture_layout,
+            sampler: nearest_sampler(&gpu.device),
+            textures: Vec::new(),
+            texture_ids: HashMap::new(),
+            pending: Vec::new(),
+            screen_size: (gpu.config.width as f32, gpu.config.height as f32),
+            z: 0,
+            clips: Vec::new(),
+            batches: Vec::new(),
+            texts: Vec::new(),
+            vertices: Vec::new(),
+            vertex_buffer: Self::create_buffer(gpu, 0),
+            capacity: 0,
+        };
+
+        let white = ui.load_texture(
+            gpu,
+            &image::RgbaImage::from_pixel(1, 1, image::Rgba([255; 4])),
+        );
+        ui.textures.push(Some(white));
+        ui
+    }
+
+    fn create_buffer(gpu: &Gpu, capacity: usize) -> wgpu::Buffer {
+        gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ui.vertex_buffer"),
+            size: (capacity.max(1) * std::mem::size_of::<Vertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn load_texture(&self, gpu: &Gpu, img: &image::RgbaImage) -> UiTexture {
+        UiTexture {
+            binding: TextureBinding::new(
+                &gpu.device,
+                &self.texture_layout,
+                &create_texture(gpu, img, "ui"),
+                &self.sampler,
+                "ui",
+            ),
+            size: img.dimensions(),
+        }
+    }
+
+    /// id of a texture string, it's loaded on the next submit
+    fn texture(&mut self, texture: &str) -> usize {
+        if let Some(&id) = self.texture_ids.get(texture) {
+            return id;
+        }
+
+        let id = self.textures.len();
+        self.textures.push(None);
+        self.texture_ids.insert(texture.to_string(), id);
+        self.pending.push((id, texture.to_string()));
+        id
+    }
+
+    fn load_pending(&mut self, gpu: &Gpu, media: &MediaMgr) {
+        for (id, texture) in std::mem::take(&mut self.pending) {
+            // MediaMgr flips textures for the 3D renderer, screen space goes downwards
+            let img = image::imageops::flip_vertical(&media.texture_string(&texture));
+            self.textures[id] = Some(self.load_texture(gpu, &img));
+        }
+    }
+
+    /// size of a texture in pixels, None until it's loaded on the next submit
+    pub fn texture_size(&mut self, texture: &str) -> Option<(u32, u32)> {
+        let id = self.texture(texture);
+        self.textures[id].as_ref().map(|tex| tex.size)
+    }
+
+    pub fn screen_size(&self) -> (f32, f32) {
+        self.screen_size
+    }
+
+    pub fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        self.screen_size = (size.width as f32, size.height as f32);
+    }
+
+    pub fn z(&self) -> i32 {
+        self.z
+    }
+
+    /// sets the layer of everything added from now on
+    pub fn set_z(&mut self, z: i32) {
+        self.z = z;
+    }
+
+    /// limits everything added until the matching pop_clip to rect
+    pub fn push_clip(&mut self, rect: Rect) {
+        let clip = match self.clips.last() {
+            Some(clip) => clip
+                .intersect(rect)
+                .unwrap_or(Rect::new(rect.x, rect.y, 0.0, 0.0)),
+            None => rect,
+        };
+
+        self.clips.push(clip);
+    }
+
+    pub fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+
+    /// corners are clockwise from the top left one
+    fn quad(&mut self, texture: usize, corners: [[f32; 2]; 4], uv: Rect, color: [f32; 4]) {
+        let (z, clip) = (self.z, self.clips.last().copied());
+        let start = self.vertices.len() as u32;
+
+        match self.batches.last_mut() {
+            Some(batch)
+                if batch.texture == texture
+                    && batch.z == z
+                    && batch.clip == clip
+                    && batch.end == start =>
+            {
+                batch.end += 6;
+            }
+            _ => self.batches.push(Batch {
+                texture,
+                z,
+                clip,
+                start,
+                end: start + 6,
+            }),
+        }
+
+        let Rect { x, y, w, h } = uv;
+        let tex_coords = [[x, y], [x + w, y], [x + w, y + h], [x, y + h]];
+
+        for i in [0, 1, 2, 2, 3, 0] {
+            self.vertices.push(Vertex {
+                pos: corners[i],
+                tex_coords: tex_coords[i],
+                color,
+            });
+        }
+    }
+
+    fn corners(rect: Rect) -> [[f32; 2]; 4] {
+        let Rect { x, y, w, h } = rect;
+        [[x, y], [x + w, y], [x + w, y + h], [x, y + h]]
+    }
+
+    /// draws a texture stretched over rect, tinted with color
+    pub fn image(&mut self, texture: &str, rect: Rect, color: [f32; 4]) {
+        self.image_region(texture, rect, Rect::UNIT, color);
+    }
+
+    /// draws the part uv of a texture, in texture coordinates from 0 to 1
+    pub fn image_region(&mut self, texture: &str, rect: Rect, uv: Rect, color: [f32; 4]) {
+        let id = self.texture(texture);
+        self.quad(id, Self::corners(rect), uv, color);
+    }
+
+    /// draws a texture over rect turned counterclockwise around its center
+    pub fn image_rotated(&mut self, texture: &str, rect: Rect, angle: Rad<f32>, color: [f32; 4]) {
+        let (sin, cos) = angle.sin_cos();
+        let center = [rect.x + rect.w / 2.0, rect.y + rect.h / 2.0];
+
+        // the y axis points down, so this turns the other way than usual
+        let corners = Self::corners(rect).map(|[x, y]| {
+            let (x, y) = (x - center[0], y - center[1]);
+            [center[0] + x * cos + y * sin, center[1] - x * sin + y * cos]
+        });
+
+        let id = self.texture(texture);
+        self.quad(id, corners, Rect::UNIT, color);
+    }
+
+    /// draws a texture stretched over rect without stretching its borders,
+    /// which are given in texture pixels as left, top, right and bottom
+    pub fn image_nine_slice(
+        &mut self,
+        texture: &str,
+        rect: Rect,
+        border: [f32; 4],
+        color: [f32; 4],
+    ) {
+        // the borders are unknown until the texture is loaded
+        let size = match self.texture_size(texture) {
+            Some(size) => size,
+            None => return,
+        };
+
+        for (rect, uv) in nine_slice(rect, size, border) {
+            self.image_region(texture, rect, uv, color);
+        }
+    }
+
+    /// adds text, drawn above images of the same layer
+    pub fn text(&mut self, section: Section) {
+        self.texts.push(UiText {
+            z: self.z,
+            clip: self.clips.last().copied(),
+            section: section.to_owned(),
+        });
+    }
+
+    /// a clip rectangle in whole pixels inside the screen, None if nothing is visible
+    fn scissor(&self, clip: Option<Rect>) -> Option<[u32; 4]> {
+        let screen = Rect::new(0.0, 0.0, self.screen_size.0, self.screen_size.1);
+        let clip = screen.intersect(clip.unwrap_or(screen))?;
+
+        let (x, y) = (clip.x.round(), clip.y.round());
+        let (w, h) = ((clip.x + clip.w).round() - x, (clip.y + clip.h).round() - y);
+
+        (w > 0.0 && h > 0.0).then_some([x as u32, y as u32, w as u32, h as u32])
+    }
+
+    /// draws a plain colored rectangle
+    pub fn fill(&mut self, rect: Rect, color: [f32; 4]) {
+        self.quad(WHITE, Self::corners(rect), Rect::UNIT, color);
+    }
+
+    /// draws everything added since the last call onto the frame, text is drawn using font
+    pub fn submit(&mut self, frame: &mut Frame, media: &MediaMgr, font: &mut Font) {
+        // texture sizes may be requested before anything is drawn with them
+        self.load_pending(&*frame.gpu, media);
+
+        if self.vertices.is_empty() && self.texts.is_empty() {
+            return;
+        }
+
+        {
+            let gpu = &*frame.gpu;
+            let [w, h] = [self.screen_size.0, self.screen_size.1];
+            gpu.queue
+                .write_buffer(&self.screen, 0, bytemuck::cast_slice(&[[w, h]]));
+
+            if self.vertices.len() > self.capacity {
+                self.capacity = self.vertices.len().next_power_of_two();
+                self.vertex_buffer = Self::create_buffer(gpu, self.capacity);
+            }
+            gpu.queue
+                .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        }
+
+        let mut layers: Vec<i32> = self
+            .batches
+            .iter()
+            .map(|batch| batch.z)
+            .chain(self.texts.iter().map(|text| text.z))
+            .collect();
+        layers.sort_unstable();
+        layers.dedup();
+
+        for z in layers {
+            if self.batches.iter().any(|batch| batch.z == z) {
+                let mut pass = frame
+                    .encoder
+                    .begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("ui"),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view: &frame.view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: true,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                    });
+
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(0, &self.screen_bind_group, &[]);
+                pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+
+                for batch in self.batches.iter().filter(|batch| batch.z == z) {
+                    let texture = match &self.textures[batch.texture] {
+                        Some(texture) => texture,
+                        None => continue,
+                    };
+                    let [x, y, w, h] = match self.scissor(batch.clip) {
+                        Some(scissor) => scissor,
+                        None => continue,
+                    };
+
+                    pass.set_scissor_rect(x, y, w, h);
+                    pass.set_bind_group(1, &texture.binding.bind_group, &[]);
+                    pass.draw(batch.start..batch.end, 0..1);
+                }
+            }
+
+            // text with the same clip rectangle is drawn at once
+            let mut texts = self.texts.iter().filter(|text| text.z == z).peekable();
+            while let Some(text) = texts.next() {
+                let scissor = self.scissor(text.clip);
+                if scissor.is_some() {
+                    font.add(text.section.to_borrowed());
+                }
+
+                if texts.peek().map(|next| next.clip) != Some(text.clip) {
+                    if let Some(scissor) = scissor {
+                        font.draw(frame, Some(scissor));
+                    }
+                }
+            }
+        }
+
+        self.batches.clear();
+        self.texts.clear();
+        self.vertices.clear();
+    }
+}
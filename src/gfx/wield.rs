@@ -0,0 +1,345 @@
+use super::{
+    gpu::Gpu,
+    items::ItemRegistry,
+    media::MediaMgr,
+    util::{create_texture, nearest_sampler, MatrixUniform, TextureBinding},
+};
+use cgmath::{prelude::*, Deg, Matrix4, Vector3};
+use mt_net::NodeDef;
+use std::f32::consts::PI;
+use wgpu::util::DeviceExt;
+
+// duration of a single swing in seconds
+const SWING_TIME: f32 = 0.3;
+const WIELD_FOV: Deg<f32> = Deg(72.0);
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    tex_coords: [f32; 2],
+    light: f32,
+}
+
+impl Vertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    Hand,
+    Cube,
+    Sprite,
+}
+
+/// adds a quad spanned by u and v around center, counterclockwise seen from u x v.
+/// the texture is frame of a vertical strip of frames images
+fn quad(
+    vertices: &mut Vec<Vertex>,
+    center: Vector3<f32>,
+    u: Vector3<f32>,
+    v: Vector3<f32>,
+    frame: usize,
+    frames: usize,
+    light: f32,
+) {
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+
+    for i in [0, 1, 2, 2, 3, 0] {
+        let (cu, cv) = corners[i];
+        // MediaMgr flips textures, so v grows upwards
+        let (tu, tv) = ((cu + 1.0) / 2.0, (cv + 1.0) / 2.0);
+
+        vertices.push(Vertex {
+            pos: (center + u * cu + v * cv).into(),
+            tex_coords: [tu, (frame as f32 + tv) / frames as f32],
+            light,
+        });
+    }
+}
+
+/// a cuboid textured with a strip of 6 frames in upstream's tile order:
+/// top, bottom, right, left, back, front
+fn cuboid(size: [f32; 3]) -> Vec<Vertex> {
+    let [x, y, z] = size.map(|s| s / 2.0);
+    let (ux, uy, uz) = (
+        Vector3::unit_x() * x,
+        Vector3::unit_y() * y,
+        Vector3::unit_z() * z,
+    );
+    let (ex, ey, ez) = (Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z());
+
+    let mut vertices = Vec::new();
+    quad(&mut vertices, uy, ex * x, -ez * z, 0, 6, 1.0);
+    quad(&mut vertices, -uy, ex * x, ez * z, 1, 6, 0.6);
+    quad(&mut vertices, ux, -ez * z, ey * y, 2, 6, 0.8);
+    quad(&mut vertices, -ux, ez * z, ey * y, 3, 6, 0.8);
+    quad(&mut vertices, uz, ex * x, ey * y, 4, 6, 0.9);
+    quad(&mut vertices, -uz, -ex * x, ey * y, 5, 6, 0.9);
+    vertices
+}
+
+/// a flat, double sided image
+fn sprite(size: f32) -> Vec<Vertex> {
+    let (u, v) = (
+        Vector3::unit_x() * size / 2.0,
+        Vector3::unit_y() * size / 2.0,
+    );
+
+    let mut vertices = Vec::new();
+    quad(&mut vertices, Vector3::zero(), u, v, 0, 1, 1.0);
+    quad(&mut vertices, Vector3::zero(), -u, v, 0, 1, 0.8);
+    vertices
+}
+
+/// stacks images of possibly different sizes into a vertical strip
+fn strip(images: &[image::RgbaImage]) -> image::RgbaImage {
+    let size = images.iter().map(|img| img.width()).max().unwrap_or(1);
+    let mut strip = image::RgbaImage::new(size, size * images.len() as u32);
+
+    for (i, img) in images.iter().enumerate() {
+        let img = image::imageops::resize(img, size, size, image::imageops::FilterType::Nearest);
+        image::imageops::replace(&mut strip, &img, 0, (i as u32 * size) as i64);
+    }
+
+    strip
+}
+
+/// the item in the player's hand, shown in first person
+pub struct WieldItem {
+    pipeline: wgpu::RenderPipeline,
+    texture_layout: wgpu::BindGroupLayout,
+    projection: MatrixUniform,
+    model: MatrixUniform,
+    vertex_buffer: wgpu::Buffer,
+    num_vertices: u32,
+    texture: TextureBinding,
+    shape: Shape,
+    swing: Option<f32>,
+}
+
+impl WieldItem {
+    pub fn new(gpu: &Gpu, media: &MediaMgr) -> Self {
+        let matrix_layout = MatrixUniform::layout(&gpu.device, "wield");
+        let projection = MatrixUniform::new(
+            &gpu.device,
+            &matrix_layout,
+            Matrix4::identity(),
+            "wield.projection",
+            true,
+        );
+        let model = MatrixUniform::new(
+            &gpu.device,
+            &matrix_layout,
+            Matrix4::identity(),
+            "wield.model",
+            true,
+        );
+
+        let texture_layout = TextureBinding::layout(&gpu.device, "wield");
+
+        let shader = gpu
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../../assets/shaders/model.wgsl"));
+
+        let pipeline_layout = gpu
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&matrix_layout, &matrix_layout, &texture_layout],
+                push_constant_ranges: &[],
+            });
+
+        let pipeline = gpu
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("wield"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: gpu.scene_format(),
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                // the item is convex, culling back faces is enough to sort it
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                // always drawn on top of the world
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: gpu.multisample(),
+                multiview: None,
+            });
+
+        let (vertex_buffer, num_vertices, texture) = Self::create_mesh(
+            gpu,
+            &texture_layout,
+            Shape::Hand,
+            &[media.texture("wieldhand.png")],
+        );
+
+        Self {
+            pipeline,
+            texture_layout,
+            projection,
+            model,
+            vertex_buffer,
+            num_vertices,
+            texture,
+            shape: Shape::Hand,
+            swing: None,
+        }
+    }
+
+    fn create_mesh(
+        gpu: &Gpu,
+        texture_layout: &wgpu::BindGroupLayout,
+        shape: Shape,
+        faces: &[image::RgbaImage],
+    ) -> (wgpu::Buffer, u32, TextureBinding) {
+        let vertices = match shape {
+            Shape::Hand => cuboid([0.15, 0.15, 0.6]),
+            Shape::Cube => cuboid([0.4, 0.4, 0.4]),
+            Shape::Sprite => sprite(0.6),
+        };
+
+        // cuboids use the last face for the missing ones, like node tiles
+        let img = match shape {
+            Shape::Sprite => strip(&faces[..1]),
+            _ => strip(&std::array::from_fn::<_, 6, _>(|i| {
+                faces.get(i).or(faces.last()).unwrap().clone()
+            })),
+        };
+
+        let vertex_buffer = gpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("wield.vertex_buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+        let texture = TextureBinding::new(
+            &gpu.device,
+            texture_layout,
+            &create_texture(gpu, &img, "wield"),
+            &nearest_sampler(&gpu.device),
+            "wield",
+        );
+
+        (vertex_buffer, vertices.len() as u32, texture)
+    }
+
+    fn set_mesh(&mut self, gpu: &Gpu, shape: Shape, faces: &[image::RgbaImage]) {
+        (self.vertex_buffer, self.num_vertices, self.texture) =
+            Self::create_mesh(gpu, &self.texture_layout, shape, faces);
+        self.shape = shape;
+    }
+
+    /// switches to the item called name, node is its node definition if it has one
+    pub fn set_item(
+        &mut self,
+        gpu: &Gpu,
+        media: &MediaMgr,
+        items: &ItemRegistry,
+        name: &str,
+        node: Option<&NodeDef>,
+    ) {
+        if name.is_empty() {
+            let hand = items
+                .wield_texture("")
+                .unwrap_or_else(|| "wieldhand.png".into());
+            self.set_mesh(gpu, Shape::Hand, &[media.texture_string(&hand)]);
+            return;
+        }
+
+        match (items.wield_texture(name), node) {
+            (None, Some(node)) if !node.tiles.is_empty() => {
+                let faces: Vec<_> = node
+                    .tiles
+                    .iter()
+                    .map(|tile| media.texture_string(&tile.texture.name))
+                    .collect();
+                self.set_mesh(gpu, Shape::Cube, &faces);
+            }
+            (texture, _) => {
+                let texture = texture.unwrap_or_else(|| "unknown_item.png".into());
+                self.set_mesh(gpu, Shape::Sprite, &[media.texture_string(&texture)]);
+            }
+        }
+    }
+
+    /// starts a swing unless one is in progress
+    pub fn swing(&mut self) {
+        self.swing.get_or_insert(0.0);
+    }
+
+    pub fn update(&mut self, gpu: &Gpu, dt: f32) {
+        let aspect = gpu.config.width as f32 / gpu.config.height.max(1) as f32;
+        self.projection.set(
+            &gpu.queue,
+            cgmath::perspective(WIELD_FOV, aspect, 0.05, 10.0),
+        );
+
+        let phase = match &mut self.swing {
+            Some(time) => {
+                *time += dt;
+                if *time >= SWING_TIME {
+                    self.swing = None;
+                    0.0
+                } else {
+                    (*time / SWING_TIME * PI).sin()
+                }
+            }
+            None => 0.0,
+        };
+
+        let pose = match self.shape {
+            Shape::Hand => Matrix4::from_angle_y(Deg(-10.0)) * Matrix4::from_angle_x(Deg(10.0)),
+            Shape::Cube => Matrix4::from_angle_x(Deg(20.0)) * Matrix4::from_angle_y(Deg(45.0)),
+            Shape::Sprite => Matrix4::from_angle_y(Deg(-20.0)),
+        };
+
+        // the view looks along -z
+        self.model.set(
+            &gpu.queue,
+            Matrix4::from_translation(Vector3::new(0.55 - 0.15 * phase, -0.5, -1.1 - 0.1 * phase))
+                * Matrix4::from_angle_x(Deg(-40.0 * phase))
+                * pose,
+        );
+    }
+
+    pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.projection.bind_group, &[]);
+        pass.set_bind_group(1, &self.model.bind_group, &[]);
+        pass.set_bind_group(2, &self.texture.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.draw(0..self.num_vertices, 0..1);
+    }
+}
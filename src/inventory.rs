@@ -0,0 +1,694 @@
+use cgmath::Point3;
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// reads a JSON string if s starts with a quote, a single word otherwise.
+/// returns the string and the rest of s
+fn read_string(s: &str) -> Result<(String, &str), String> {
+    let s = s.trim_start();
+
+    if !s.starts_with('"') {
+        let end = s.find(char::is_whitespace).unwrap_or(s.len());
+        return Ok((s[..end].to_string(), &s[end..]));
+    }
+
+    // upstream escapes every non-ASCII byte on its own, so collect bytes
+    let mut out = Vec::new();
+    let mut chars = s[1..].char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((String::from_utf8_lossy(&out).into(), &s[i + 2..])),
+            '\\' => {
+                let escaped = match chars.next().map(|(_, c)| c) {
+                    Some('b') => '\x08',
+                    Some('f') => '\x0c',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('u') => {
+                        let hex: String = (0..4)
+                            .filter_map(|_| chars.next())
+                            .map(|(_, c)| c)
+                            .collect();
+                        let code = u32::from_str_radix(&hex, 16)
+                            .map_err(|_| format!("invalid escape \\u{hex}"))?;
+
+                        if code < 0x100 {
+                            out.push(code as u8);
+                            continue;
+                        }
+
+                        char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                    }
+                    Some(c) => c,
+                    None => break,
+                };
+
+                out.extend_from_slice(escaped.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            c => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+
+    Err("unterminated string".into())
+}
+
+/// quotes s as a JSON string if it can't be written as a single word.
+/// like upstream, every byte outside of printable ASCII is escaped on its own
+fn write_string(s: &str) -> String {
+    let plain = !s.is_empty() && s.bytes().all(|b| b > b' ' && b < 0x7f && b != b'"');
+
+    if plain {
+        return s.to_string();
+    }
+
+    let mut out = String::from("\"");
+    for b in s.bytes() {
+        match b {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b'\x08' => out.push_str("\\b"),
+            b'\x0c' => out.push_str("\\f"),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            b' '..=b'~' => out.push(b as char),
+            b => out.push_str(&format!("\\u{b:04x}")),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// item metadata is a list of key/value pairs separated by these
+const META_START: char = '\x01';
+const META_KV: char = '\x02';
+const META_PAIR: char = '\x03';
+
+/// an item and how many of it there are, empty if the name is empty
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItemStack {
+    pub name: String,
+    pub count: u16,
+    pub wear: u16,
+    pub meta: BTreeMap<String, String>,
+}
+
+impl ItemStack {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_empty() || self.count == 0
+    }
+
+    /// the text shown for the item, its description metadata if it has one
+    pub fn description(&self) -> Option<&str> {
+        self.meta.get("description").map(String::as_str)
+    }
+}
+
+impl FromStr for ItemStack {
+    type Err = String;
+
+    /// parses upstream's item strings: name [count [wear [metadata]]]
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (name, rest) = read_string(s)?;
+        let mut stack = ItemStack {
+            name,
+            count: 1,
+            ..Default::default()
+        };
+
+        let mut words = rest.trim_start().splitn(3, ' ');
+
+        if let Some(count) = words.next().filter(|w| !w.is_empty()) {
+            stack.count = count
+                .parse()
+                .map_err(|_| format!("invalid count: {count}"))?;
+        }
+        if let Some(wear) = words.next().filter(|w| !w.is_empty()) {
+            stack.wear = wear.parse().map_err(|_| format!("invalid wear: {wear}"))?;
+        }
+        if let Some(meta) = words.next().filter(|w| !w.trim().is_empty()) {
+            let (meta, _) = read_string(meta)?;
+
+            if let Some(pairs) = meta.strip_prefix(META_START) {
+                stack.meta = pairs
+                    .split(META_PAIR)
+                    .filter(|pair| !pair.is_empty())
+                    .filter_map(|pair| pair.split_once(META_KV))
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect();
+            }
+        }
+
+        Ok(stack)
+    }
+}
+
+impl fmt::Display for ItemStack {
+    /// omits trailing default values like upstream
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", write_string(&self.name))?;
+
+        let meta = !self.meta.is_empty();
+        let wear = meta || self.wear != 0;
+
+        if wear || self.count != 1 {
+            write!(f, " {}", self.count)?;
+        }
+        if wear {
+            write!(f, " {}", self.wear)?;
+        }
+        if meta {
+            let mut s = META_START.to_string();
+            for (k, v) in &self.meta {
+                s += &format!("{k}{META_KV}{v}{META_PAIR}");
+            }
+            write!(f, " {}", write_string(&s))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InventoryList {
+    pub name: String,
+    /// number of columns shown in formspecs, 0 if unset
+    pub width: u32,
+    pub stacks: Vec<ItemStack>,
+}
+
+/// named lists of item stacks, like a player's main and craft lists
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Inventory {
+    pub lists: Vec<InventoryList>,
+}
+
+impl Inventory {
+    pub fn list(&self, name: &str) -> Option<&InventoryList> {
+        self.lists.iter().find(|list| list.name == name)
+    }
+
+    pub fn list_mut(&mut self, name: &str) -> Option<&mut InventoryList> {
+        self.lists.iter_mut().find(|list| list.name == name)
+    }
+
+    pub fn stack(&self, list: &str, index: usize) -> Option<&ItemStack> {
+        self.list(list)?.stacks.get(index)
+    }
+
+    /// applies a full or incremental update in upstream's text format.
+    /// lists and stacks marked as kept stay as they are, unmentioned lists are removed
+    pub fn deserialize(&mut self, s: &str) -> Result<(), String> {
+        // the inventory stays untouched if the update is invalid
+        let mut old = self.lists.clone();
+        let mut lists = Vec::new();
+        let mut lines = s.lines().map(str::trim);
+
+        loop {
+            let line = lines.next().ok_or("missing EndInventory")?;
+            let (keyword, args) = line.split_once(' ').unwrap_or((line, ""));
+
+            match keyword {
+                "" => {}
+                "EndInventory" | "end" => {
+                    self.lists = lists;
+                    return Ok(());
+                }
+                "KeepList" => {
+                    if let Some(i) = old.iter().position(|list| list.name == args) {
+                        lists.push(old.remove(i));
+                    }
+                }
+                "List" => {
+                    let (name, size) = args.split_once(' ').ok_or("invalid List line")?;
+                    let size: usize = size
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("invalid list size: {size}"))?;
+
+                    let prev = old
+                        .iter()
+                        .position(|list| list.name == name)
+                        .map(|i| old.remove(i));
+
+                    let mut list = InventoryList {
+                        name: name.to_string(),
+                        width: 0,
+                        stacks: Vec::with_capacity(size),
+                    };
+
+                    loop {
+                        let line = lines.next().ok_or("missing EndInventoryList")?;
+                        let (keyword, args) = line.split_once(' ').unwrap_or((line, ""));
+
+                        match keyword {
+                            "" => {}
+                            "EndInventoryList" | "end" => break,
+                            "Width" => {
+                                list.width =
+                                    args.parse().map_err(|_| format!("invalid width: {args}"))?;
+                            }
+                            "Item" => list.stacks.push(args.parse()?),
+                            "Empty" => list.stacks.push(ItemStack::default()),
+                            "Keep" => {
+                                let i = list.stacks.len();
+                                list.stacks.push(
+                                    prev.as_ref()
+                                        .and_then(|prev| prev.stacks.get(i))
+                                        .cloned()
+                                        .unwrap_or_default(),
+                                );
+                            }
+                            other => return Err(format!("unknown list keyword: {other}")),
+                        }
+                    }
+
+                    list.stacks.resize_with(size, Default::default);
+                    lists.push(list);
+                }
+                other => return Err(format!("unknown inventory keyword: {other}")),
+            }
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+
+        for list in &self.lists {
+            out += &format!("List {} {}\n", list.name, list.stacks.len());
+            out += &format!("Width {}\n", list.width);
+
+            for stack in &list.stacks {
+                if stack.is_empty() {
+                    out += "Empty\n";
+                } else {
+                    out += &format!("Item {stack}\n");
+                }
+            }
+
+            out += "EndInventoryList\n";
+        }
+
+        out += "EndInventory\n";
+        out
+    }
+}
+
+/// which inventory an action refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvLocation {
+    CurrentPlayer,
+    Player(String),
+    NodeMeta(Point3<i16>),
+    Detached(String),
+}
+
+impl fmt::Display for InvLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvLocation::CurrentPlayer => write!(f, "current_player"),
+            InvLocation::Player(name) => write!(f, "player:{name}"),
+            InvLocation::NodeMeta(pos) => write!(f, "nodemeta:{},{},{}", pos.x, pos.y, pos.z),
+            InvLocation::Detached(name) => write!(f, "detached:{name}"),
+        }
+    }
+}
+
+impl FromStr for InvLocation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (kind, arg) = s.split_once(':').unwrap_or((s, ""));
+
+        Ok(match kind {
+            "current_player" => InvLocation::CurrentPlayer,
+            "player" => InvLocation::Player(arg.to_string()),
+            "detached" => InvLocation::Detached(arg.to_string()),
+            "nodemeta" => {
+                let coords = arg
+                    .split(',')
+                    .map(|c| c.trim().parse::<i16>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| format!("invalid node position: {arg}"))?;
+
+                match coords[..] {
+                    [x, y, z] => InvLocation::NodeMeta(Point3::new(x, y, z)),
+                    _ => return Err(format!("invalid node position: {arg}")),
+                }
+            }
+            _ => return Err(format!("unknown inventory location: {s}")),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvSlot {
+    pub inv: InvLocation,
+    pub list: String,
+    pub index: u16,
+}
+
+/// an inventory change requested from the server, a count of 0 means the whole stack
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvAction {
+    Move {
+        count: u16,
+        from: InvSlot,
+        to: InvSlot,
+    },
+    Drop {
+        count: u16,
+        from: InvSlot,
+    },
+    Craft {
+        count: u16,
+        inv: InvLocation,
+    },
+}
+
+impl fmt::Display for InvAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvAction::Move { count, from, to } => write!(
+                f,
+                "Move {count} {} {} {} {} {} {}",
+                from.inv, from.list, from.index, to.inv, to.list, to.index
+            ),
+            InvAction::Drop { count, from } => {
+                write!(f, "Drop {count} {} {} {}", from.inv, from.list, from.index)
+            }
+            InvAction::Craft { count, inv } => write!(f, "Craft {count} {inv}"),
+        }
+    }
+}
+
+impl FromStr for InvAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let words: Vec<_> = s.split_whitespace().collect();
+
+        let count = |w: &str| w.parse().map_err(|_| format!("invalid count: {w}"));
+        let slot = |w: &[&str]| -> Result<InvSlot, String> {
+            Ok(InvSlot {
+                inv: w[0].parse()?,
+                list: w[1].to_string(),
+                index: w[2]
+                    .parse()
+                    .map_err(|_| format!("invalid index: {}", w[2]))?,
+            })
+        };
+
+        match words[..] {
+            ["Move", n, ref rest @ ..] if rest.len() == 6 => Ok(InvAction::Move {
+                count: count(n)?,
+                from: slot(&rest[..3])?,
+                to: slot(&rest[3..])?,
+            }),
+            ["Drop", n, ref rest @ ..] if rest.len() == 3 => Ok(InvAction::Drop {
+                count: count(n)?,
+                from: slot(rest)?,
+            }),
+            ["Craft", n, inv] => Ok(InvAction::Craft {
+                count: count(n)?,
+                inv: inv.parse()?,
+            }),
+            _ => Err(format!("invalid inventory action: {s}")),
+        }
+    }
+}
+
+impl InvAction {
+    /// predicts the outcome of a move within the player's own inventory,
+    /// stack_max gives the maximum stack size of an item
+    pub fn apply(&self, inv: &mut Inventory, stack_max: impl Fn(&str) -> u16) {
+        let (count, from, to) = match self {
+            InvAction::Move { count, from, to }
+                if from.inv == InvLocation::CurrentPlayer
+                    && to.inv == InvLocation::CurrentPlayer =>
+            {
+                (*count, from, to)
+            }
+            _ => return,
+        };
+
+        let src = match inv.stack(&from.list, from.index as usize) {
+            Some(stack) if !stack.is_empty() => stack.clone(),
+            _ => return,
+        };
+        let dst = match inv.stack(&to.list, to.index as usize) {
+            Some(stack) => stack.clone(),
+            None => return,
+        };
+
+        let count = if count == 0 {
+            src.count
+        } else {
+            count.min(src.count)
+        };
+
+        let (src, dst) = if dst.is_empty()
+            || (dst.name == src.name && dst.wear == src.wear && dst.meta == src.meta)
+        {
+            // merge as much as fits
+            let have = if dst.is_empty() { 0 } else { dst.count };
+            let moved = count.min(stack_max(&src.name).saturating_sub(have));
+            let remaining = ItemStack {
+                count: src.count - moved,
+                ..src.clone()
+            };
+            let merged = ItemStack {
+                count: have + moved,
+                ..src
+            };
+            (remaining, merged)
+        } else if count == src.count {
+            // different items swap places
+            (dst, src)
+        } else {
+            return;
+        };
+
+        let mut set = |slot: &InvSlot, stack: ItemStack| {
+            if let Some(list) = inv.list_mut(&slot.list) {
+                list.stacks[slot.index as usize] = if stack.count == 0 {
+                    ItemStack::default()
+                } else {
+                    stack
+                };
+            }
+        };
+
+        set(from, src);
+        set(to, dst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // as sent by upstream for a player with a few items
+    const INVENTORY: &str = "List main 4
+Width 0
+Item default:stone 99
+Empty
+Item default:pick_steel 1 6554
+Item default:chest_locked 1 0 \"\\u0001description\\u0002Locked Chest\\u0003owner\\u0002sam\\u0003\"
+EndInventoryList
+List craft 3
+Width 3
+Empty
+Item default:stick 2
+Empty
+EndInventoryList
+EndInventory
+";
+
+    fn stack(s: &str) -> ItemStack {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn deserialize_full() {
+        let mut inv = Inventory::default();
+        inv.deserialize(INVENTORY).unwrap();
+
+        let main = inv.list("main").unwrap();
+        assert_eq!(main.width, 0);
+        assert_eq!(main.stacks.len(), 4);
+        assert_eq!(main.stacks[0].name, "default:stone");
+        assert_eq!(main.stacks[0].count, 99);
+        assert!(main.stacks[1].is_empty());
+        assert_eq!(main.stacks[2].wear, 6554);
+
+        let chest = &main.stacks[3];
+        assert_eq!(chest.count, 1);
+        assert_eq!(chest.description(), Some("Locked Chest"));
+        assert_eq!(chest.meta["owner"], "sam");
+
+        let craft = inv.list("craft").unwrap();
+        assert_eq!(craft.width, 3);
+        assert_eq!(inv.stack("craft", 1), Some(&stack("default:stick 2")));
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let mut inv = Inventory::default();
+        inv.deserialize(INVENTORY).unwrap();
+
+        assert_eq!(inv.serialize(), INVENTORY);
+    }
+
+    #[test]
+    fn deserialize_incremental() {
+        let mut inv = Inventory::default();
+        inv.deserialize(INVENTORY).unwrap();
+
+        inv.deserialize(
+            "List main 4
+Width 0
+Keep
+Item default:dirt 5
+Keep
+Empty
+EndInventoryList
+KeepList craft
+EndInventory
+",
+        )
+        .unwrap();
+
+        let main = inv.list("main").unwrap();
+        assert_eq!(main.stacks[0], stack("default:stone 99"));
+        assert_eq!(main.stacks[1], stack("default:dirt 5"));
+        assert_eq!(main.stacks[2], stack("default:pick_steel 1 6554"));
+        assert!(main.stacks[3].is_empty());
+        assert_eq!(inv.stack("craft", 1), Some(&stack("default:stick 2")));
+
+        // unmentioned lists are removed, resized lists are padded
+        inv.deserialize("List main 6\nWidth 0\nKeep\nEndInventoryList\nEndInventory\n")
+            .unwrap();
+        assert_eq!(inv.lists.len(), 1);
+        assert_eq!(inv.list("main").unwrap().stacks.len(), 6);
+        assert_eq!(inv.stack("main", 0), Some(&stack("default:stone 99")));
+        assert!(inv.stack("main", 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid_update_keeps_inventory() {
+        let mut inv = Inventory::default();
+        inv.deserialize(INVENTORY).unwrap();
+        let before = inv.clone();
+
+        assert!(inv
+            .deserialize("List main 4\nWidth 0\nItem default:dirt 5\n")
+            .is_err());
+        assert!(inv
+            .deserialize("List main 4\nWidth 0\nBogus\nEndInventoryList\nEndInventory\n")
+            .is_err());
+        assert!(inv
+            .deserialize("List main x\nEndInventoryList\nEndInventory\n")
+            .is_err());
+        assert_eq!(inv, before);
+    }
+
+    #[test]
+    fn item_strings() {
+        let stone = stack("default:stone");
+        assert_eq!((stone.count, stone.wear), (1, 0));
+
+        let pick = stack("default:pick_steel 1 6554");
+        assert_eq!((pick.count, pick.wear), (1, 6554));
+
+        let named = stack("\"mod:odd name\" 3");
+        assert_eq!((named.name.as_str(), named.count), ("mod:odd name", 3));
+
+        let quoted = stack("default:sign 1 0 \"\\u0001text\\u0002say \\\"hi\\\"\\nbye\\u0003\"");
+        assert_eq!(quoted.meta["text"], "say \"hi\"\nbye");
+
+        // upstream escapes non-ASCII text byte by byte
+        let utf8 = stack("default:paper 1 0 \"\\u0001description\\u0002Caf\\u00c3\\u00a9\\u0003\"");
+        assert_eq!(utf8.description(), Some("Café"));
+
+        assert!("default:stone many".parse::<ItemStack>().is_err());
+        assert!("default:stone 1 -5".parse::<ItemStack>().is_err());
+        assert!("\"default:stone".parse::<ItemStack>().is_err());
+    }
+
+    #[test]
+    fn item_string_round_trip() {
+        for s in [
+            "default:stone",
+            "default:stone 99",
+            "default:pick_steel 1 6554",
+            "default:stone 5 0 \"\\u0001description\\u0002Shiny stone\\u0003\"",
+            "default:sign 1 0 \"\\u0001text\\u0002say \\\"hi\\\"\\nbye\\u0003\"",
+            "default:paper 1 0 \"\\u0001description\\u0002Caf\\u00c3\\u00a9\\u0003\"",
+            "\"mod:odd name\" 3",
+        ] {
+            assert_eq!(stack(s).to_string(), s);
+        }
+
+        // trailing defaults are omitted
+        assert_eq!(stack("default:stone 1 0").to_string(), "default:stone");
+    }
+
+    fn slot(inv: InvLocation, list: &str, index: u16) -> InvSlot {
+        InvSlot {
+            inv,
+            list: list.to_string(),
+            index,
+        }
+    }
+
+    #[test]
+    fn action_strings() {
+        let actions = [
+            (
+                "Move 0 current_player main 0 current_player craft 4",
+                InvAction::Move {
+                    count: 0,
+                    from: slot(InvLocation::CurrentPlayer, "main", 0),
+                    to: slot(InvLocation::CurrentPlayer, "craft", 4),
+                },
+            ),
+            (
+                "Move 5 nodemeta:10,-2,300 main 31 detached:creative_singleplayer main 1",
+                InvAction::Move {
+                    count: 5,
+                    from: slot(InvLocation::NodeMeta(Point3::new(10, -2, 300)), "main", 31),
+                    to: slot(
+                        InvLocation::Detached("creative_singleplayer".into()),
+                        "main",
+                        1,
+                    ),
+                },
+            ),
+            (
+                "Drop 1 player:singleplayer main 3",
+                InvAction::Drop {
+                    count: 1,
+                    from: slot(InvLocation::Player("singleplayer".into()), "main", 3),
+                },
+            ),
+            (
+                "Craft 1 current_player",
+                InvAction::Craft {
+                    count: 1,
+                    inv: InvLocation::CurrentPlayer,
+                },
+            ),
+        ];
+
+        for (s, action) in actions {
+            assert_eq!(action.to_string(), s);
+            assert_eq!(s.parse::<InvAction>(), Ok(action));
+        }
+
+        assert!("Move 1 current_player main 0".parse::<InvAction>().is_err());
+        assert!("Craft 1 nowhere".parse::<InvAction>().is_err());
+        assert!("Drop 1 nodemeta:1,2 main 0".parse::<InvAction>().is_err());
+    }
+}
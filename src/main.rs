@@ -1,10 +1,11 @@
 #![feature(hash_drain_filter)]
 
 mod gfx;
+mod inventory;
 mod net;
 
-use cgmath::{Deg, Point3};
-use std::collections::HashMap;
+use cgmath::{Deg, Point3, Vector3};
+use std::collections::{HashMap, HashSet};
 use tokio::sync::mpsc;
 
 #[derive(Debug, Clone)]
@@ -12,14 +13,63 @@ pub enum GfxEvent {
     Close,
     Media(HashMap<String, Vec<u8>>, bool),
     NodeDefs(HashMap<u16, mt_net::NodeDef>),
+    ItemDefs(Vec<mt_net::ItemDef>, HashMap<String, String>),
+    Inventory(inventory::Inventory),
+    HudAdd(u32, Box<mt_net::HudElement>),
+    HudChange(u32, mt_net::HudChange),
+    HudRemove(u32),
+    /// new flags and the mask of flags to change
+    HudFlags(
+        mt_net::enumset::EnumSet<mt_net::HudFlag>,
+        mt_net::enumset::EnumSet<mt_net::HudFlag>,
+    ),
+    HudParam(mt_net::HudParam),
+    Hp(u16),
+    Breath(u16),
+    /// name and formspec of a form to show, an empty formspec closes it
+    ShowFormspec(String, String),
+    InventoryFormspec(String),
+    FormspecPrepend(String),
+    /// None if the detached inventory was removed
+    DetachedInventory(String, Option<inventory::Inventory>),
     MapBlock(Point3<i16>, Box<mt_net::MapBlock>),
+    /// position of a block and all node metas inside it
+    BlockMetas(Point3<i16>, HashMap<Point3<i16>, net::NodeMeta>),
+    NodeMetas(HashMap<Point3<i16>, net::NodeMeta>),
     PlayerPos(Point3<f32>, Deg<f32>, Deg<f32>),
-    MovementSpeed(f32),
+    Movement(gfx::MovementParams),
+    Physics(gfx::PhysicsOverride),
+    Privs(HashSet<String>),
+    EyeOffset(Vector3<f32>, Vector3<f32>),
+    Fov {
+        fov: f32,
+        multiplier: bool,
+        transition_time: f32,
+    },
+    ZoomFov(f32),
+    SkyParams(Box<mt_net::SkyParams>),
+    SunParams(mt_net::SunParams),
+    MoonParams(mt_net::MoonParams),
+    StarParams(mt_net::StarParams),
+    CloudParams(mt_net::CloudParams),
+    TimeOfDay(u16, f32),
+    Lighting {
+        shadow_intensity: f32,
+        exposure: f32,
+        saturation: f32,
+    },
 }
 
 #[derive(Debug, Clone)]
 pub enum NetEvent {
-    PlayerPos(Point3<f32>, Deg<f32>, Deg<f32>),
+    PlayerPos(net::PlayerState),
+    Interact(mt_net::Interaction, mt_net::PointedThing),
+    InvAction(inventory::InvAction),
+    PlayerItem(u16),
+    /// name of a form and the values of its fields
+    InvFields(String, HashMap<String, String>),
+    /// like InvFields, for forms shown from the meta of the node at the position
+    NodeMetaFields(Point3<i16>, String, HashMap<String, String>),
     Ready,
 }
 
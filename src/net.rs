@@ -1,21 +1,70 @@
-use crate::{GfxEvent, NetEvent};
-use cgmath::{Deg, Point3, Vector3};
+use crate::{inventory, GfxEvent, NetEvent};
+use cgmath::{Deg, Point3, Rad, Vector3};
 use futures::future::OptionFuture;
-use mt_net::{CltSender, ReceiverExt, SenderExt, ToCltPkt, ToSrvPkt};
-use std::{future::Future, time::Duration};
+use mt_net::{enumset::EnumSet, CltSender, Key, ReceiverExt, SenderExt, ToCltPkt, ToSrvPkt};
+use std::{collections::HashMap, future::Future, time::Duration};
 use tokio::{
     sync::mpsc,
     time::{interval, Instant, Interval},
 };
 use winit::event_loop::EventLoopProxy;
 
+// the position is resent at this interval even if it didn't change
+const POS_HEARTBEAT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerState {
+    pub pos: Point3<f32>,
+    pub vel: Vector3<f32>,
+    pub yaw: Deg<f32>,
+    pub pitch: Deg<f32>,
+    pub keys: EnumSet<Key>,
+    pub fov: Rad<f32>,
+}
+
+/// fields and inventory the server stores for a node
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeMeta {
+    pub fields: HashMap<String, String>,
+    pub inv: inventory::Inventory,
+}
+
+impl NodeMeta {
+    /// the form shown when the node is rightclicked, empty if there is none
+    pub fn formspec(&self) -> &str {
+        self.fields.get("formspec").map_or("", String::as_str)
+    }
+}
+
+fn node_meta(pos: Point3<i16>, meta: mt_net::NodeMeta) -> NodeMeta {
+    let mut inv = inventory::Inventory::default();
+    if let Err(e) = inv.deserialize(&meta.inv) {
+        eprintln!("invalid inventory of node at {pos:?}: {e}");
+    }
+
+    NodeMeta {
+        fields: meta
+            .fields
+            .into_iter()
+            .map(|(name, field)| (name, field.value))
+            .collect(),
+        inv,
+    }
+}
+
 struct Conn {
     tx: CltSender,
     auth: mt_auth::Auth,
+    name: String,
+    /// id of the active object representing the local player
+    player_ao: Option<u16>,
     send_pos_iv: Option<Interval>,
-    pos: Point3<f32>,
-    pitch: Deg<f32>,
-    yaw: Deg<f32>,
+    player: PlayerState,
+    last_sent: Option<(PlayerState, Instant)>,
+    /// hotbar slot of the wielded item
+    item_slot: u16,
+    inv: inventory::Inventory,
+    detached_invs: HashMap<String, inventory::Inventory>,
     events: EventLoopProxy<GfxEvent>,
 }
 
@@ -29,13 +78,26 @@ pub(crate) async fn run(
 ) {
     let (tx, mut rx, worker) = mt_net::connect("localhost:30000").await.unwrap();
 
+    let name = "shrek";
+
     let mut conn = Conn {
-        auth: mt_auth::Auth::new(tx.clone(), "shrek", "boobies", "en_US"),
+        auth: mt_auth::Auth::new(tx.clone(), name, "boobies", "en_US"),
         tx,
+        name: name.into(),
+        player_ao: None,
         send_pos_iv: None,
-        pos: Point3::new(0.0, 0.0, 0.0),
-        pitch: Deg(0.0),
-        yaw: Deg(0.0),
+        player: PlayerState {
+            pos: Point3::new(0.0, 0.0, 0.0),
+            vel: Vector3::new(0.0, 0.0, 0.0),
+            yaw: Deg(0.0),
+            pitch: Deg(0.0),
+            keys: EnumSet::empty(),
+            fov: Deg(90.0).into(),
+        },
+        last_sent: None,
+        item_slot: 0,
+        inv: Default::default(),
+        detached_invs: HashMap::new(),
         events: evt_out,
     };
 
@@ -49,27 +111,48 @@ pub(crate) async fn run(
                 Some(Ok(v)) => conn.handle_pkt(v).await,
             },
             _ = conn.auth.poll() => {}
-            Some(_) = maybe_tick(conn.send_pos_iv.as_mut()) => {
-                conn.tx
-                    .send(&ToSrvPkt::PlayerPos(mt_net::PlayerPos {
-                        pos: conn.pos,
-                        vel: Vector3::new(0.0, 0.0, 0.0),
-                        pitch: conn.pitch,
-                        yaw: conn.yaw,
-                        keys: mt_net::enumset::EnumSet::empty(),
-                        fov: Deg(90.0).into(),
-                        wanted_range: 12,
-                    }))
-                    .await
-                    .unwrap();
+            Some(now) = maybe_tick(conn.send_pos_iv.as_mut()) => {
+                conn.send_pos(now).await;
             }
             evt = evt_in.recv() => {
                 match evt {
-                    Some(NetEvent::PlayerPos(pos, yaw, pitch)) => {
-                        conn.pos = pos;
-                        conn.yaw = yaw;
-                        conn.pitch = pitch;
+                    Some(NetEvent::PlayerPos(player)) => {
+                        conn.player = player;
                     },
+                    Some(NetEvent::Interact(action, pointed)) => {
+                        conn.interact(action, pointed).await;
+                    }
+                    Some(NetEvent::PlayerItem(item)) => {
+                        conn.item_slot = item;
+                        conn.tx
+                            .send(&ToSrvPkt::PlayerItem { item })
+                            .await
+                            .unwrap();
+                    }
+                    Some(NetEvent::InvAction(action)) => {
+                        conn.tx
+                            .send(&ToSrvPkt::InvAction {
+                                action: action.to_string(),
+                            })
+                            .await
+                            .unwrap();
+                    }
+                    Some(NetEvent::InvFields(formname, fields)) => {
+                        conn.tx
+                            .send(&ToSrvPkt::InvFields { formname, fields })
+                            .await
+                            .unwrap();
+                    }
+                    Some(NetEvent::NodeMetaFields(pos, formname, fields)) => {
+                        conn.tx
+                            .send(&ToSrvPkt::NodeMetaFields {
+                                pos,
+                                formname,
+                                fields,
+                            })
+                            .await
+                            .unwrap();
+                    }
                     Some(NetEvent::Ready) => {
                         conn.tx
                             .send(&ToSrvPkt::CltReady {
@@ -78,7 +161,7 @@ pub(crate) async fn run(
                                 patch: 0,
                                 reserved: 0,
                                 version: format!("Minetest Rust {}", env!("CARGO_PKG_VERSION")),
-                                formspec: 4,
+                                formspec: 6,
                             })
                             .await
                             .unwrap();
@@ -96,6 +179,76 @@ pub(crate) async fn run(
 }
 
 impl Conn {
+    fn player_pos(&self) -> mt_net::PlayerPos {
+        let player = self.player;
+
+        mt_net::PlayerPos {
+            pos: player.pos,
+            vel: player.vel,
+            pitch: player.pitch,
+            yaw: player.yaw,
+            keys: player.keys,
+            fov: player.fov,
+            wanted_range: 12,
+        }
+    }
+
+    async fn send_pos(&mut self, now: Instant) {
+        let player = self.player;
+
+        if let Some((last, time)) = self.last_sent {
+            if last == player && now - time < POS_HEARTBEAT {
+                return;
+            }
+        }
+
+        self.tx
+            .send(&ToSrvPkt::PlayerPos(self.player_pos()))
+            .await
+            .unwrap();
+
+        self.last_sent = Some((player, now));
+    }
+
+    async fn interact(&mut self, action: mt_net::Interaction, pointed: mt_net::PointedThing) {
+        self.tx
+            .send(&ToSrvPkt::Interact {
+                action,
+                item_slot: self.item_slot,
+                pointed,
+                pos: self.player_pos(),
+            })
+            .await
+            .unwrap();
+    }
+
+    fn handle_ao_msg(&self, id: u16, msg: mt_net::AoMsg) {
+        if Some(id) != self.player_ao {
+            return;
+        }
+
+        match msg {
+            mt_net::AoMsg::PhysicsOverride(phys) => {
+                self.events
+                    .send_event(GfxEvent::Physics(crate::gfx::PhysicsOverride {
+                        speed: phys.walk,
+                        jump: phys.jump,
+                        gravity: phys.gravity,
+                        sneak: !phys.no_sneak,
+                        sneak_glitch: !phys.no_sneak_glitch,
+                        new_move: !phys.old_sneak,
+                    }))
+                    .ok();
+            }
+            mt_net::AoMsg::Props(props) => {
+                self.events
+                    .send_event(GfxEvent::ZoomFov(props.zoom_fov))
+                    .ok();
+            }
+            _ => {}
+        }
+    }
+
     async fn handle_pkt(&mut self, pkt: ToCltPkt) {
         use ToCltPkt::*;
 
@@ -105,23 +258,129 @@ impl Conn {
             NodeDefs(defs) => {
                 self.events.send_event(GfxEvent::NodeDefs(defs.0)).ok();
             }
+            Inventory { inv } => match self.inv.deserialize(&inv) {
+                Ok(()) => {
+                    self.events
+                        .send_event(GfxEvent::Inventory(self.inv.clone()))
+                        .ok();
+                }
+                Err(e) => eprintln!("invalid inventory: {e}"),
+            },
+            DetachedInventory {
+                name, keep, inv, ..
+            } => {
+                if !keep {
+                    self.detached_invs.remove(&name);
+                    self.events
+                        .send_event(GfxEvent::DetachedInventory(name, None))
+                        .ok();
+                } else {
+                    let detached = self.detached_invs.entry(name.clone()).or_default();
+
+                    match detached.deserialize(&inv) {
+                        Ok(()) => {
+                            let detached = detached.clone();
+                            self.events
+                                .send_event(GfxEvent::DetachedInventory(name, Some(detached)))
+                                .ok();
+                        }
+                        Err(e) => eprintln!("invalid detached inventory {name}: {e}"),
+                    }
+                }
+            }
+            HudAdd { id, hud } => {
+                self.events
+                    .send_event(GfxEvent::HudAdd(id, Box::new(hud)))
+                    .ok();
+            }
+            HudChange { id, change } => {
+                self.events.send_event(GfxEvent::HudChange(id, change)).ok();
+            }
+            HudRm { id } => {
+                self.events.send_event(GfxEvent::HudRemove(id)).ok();
+            }
+            HudFlags { flags, mask } => {
+                self.events.send_event(GfxEvent::HudFlags(flags, mask)).ok();
+            }
+            HudSetParam(param) => {
+                self.events.send_event(GfxEvent::HudParam(param)).ok();
+            }
+            Hp { hp, .. } => {
+                self.events.send_event(GfxEvent::Hp(hp)).ok();
+            }
+            Breath { breath } => {
+                self.events.send_event(GfxEvent::Breath(breath)).ok();
+            }
+            ShowFormspec { formspec, formname } => {
+                self.events
+                    .send_event(GfxEvent::ShowFormspec(formname, formspec))
+                    .ok();
+            }
+            InventoryFormspec { formspec } => {
+                self.events
+                    .send_event(GfxEvent::InventoryFormspec(formspec))
+                    .ok();
+            }
+            FormspecPrepend { prepend } => {
+                self.events
+                    .send_event(GfxEvent::FormspecPrepend(prepend))
+                    .ok();
+            }
+            ItemDefs { defs, aliases } => {
+                self.events
+                    .send_event(GfxEvent::ItemDefs(defs, aliases))
+                    .ok();
+            }
             Kick(reason) => {
                 println!("kicked: {reason}");
             }
             AcceptAuth { player_pos, .. } => {
-                self.pos = player_pos;
+                self.player.pos = player_pos;
                 self.send_pos_iv = Some(interval(Duration::from_millis(100)));
             }
             MovePlayer { pos, pitch, yaw } => {
-                self.pos = pos;
-                self.pitch = pitch;
-                self.yaw = yaw;
+                self.player.pos = pos;
+                self.player.pitch = pitch;
+                self.player.yaw = yaw;
 
                 self.events
-                    .send_event(GfxEvent::PlayerPos(self.pos, self.pitch, self.yaw))
+                    .send_event(GfxEvent::PlayerPos(pos, pitch, yaw))
                     .ok();
             }
-            BlockData { pos, block } => {
+            AoRmAdd { remove, add } => {
+                if self.player_ao.map_or(false, |id| remove.contains(&id)) {
+                    self.player_ao = None;
+                }
+
+                for obj in add {
+                    if obj.init_data.is_player && obj.init_data.name == self.name {
+                        self.player_ao = Some(obj.id);
+                    }
+
+                    for msg in obj.init_data.msgs {
+                        self.handle_ao_msg(obj.id, msg);
+                    }
+                }
+            }
+            AoMsgs { msgs } => {
+                for mt_net::IdAoMsg { id, msg } in msgs {
+                    self.handle_ao_msg(id, msg);
+                }
+            }
+            BlockData { pos, mut block } => {
+                // metadata is keyed by the node's index inside the block
+                let metas = std::mem::take(&mut block.metadata)
+                    .into_iter()
+                    .map(|(i, meta)| {
+                        let i = i as i16;
+                        let pos = pos * 16 + Vector3::new(i & 0xf, (i >> 4) & 0xf, (i >> 8) & 0xf);
+                        (pos, node_meta(pos, meta))
+                    })
+                    .collect();
+
+                self.events
+                    .send_event(GfxEvent::BlockMetas(pos, metas))
+                    .ok();
                 self.events.send_event(GfxEvent::MapBlock(pos, block)).ok();
                 self.tx
                     .send(&ToSrvPkt::GotBlocks {
@@ -130,6 +389,14 @@ impl Conn {
                     .await
                     .unwrap();
             }
+            NodeMetasChanged { changed } => {
+                let metas = changed
+                    .into_iter()
+                    .map(|(pos, meta)| (pos, node_meta(pos, meta)))
+                    .collect();
+
+                self.events.send_event(GfxEvent::NodeMetas(metas)).ok();
+            }
             AnnounceMedia { files, .. } => {
                 self.tx
                     .send(&ToSrvPkt::RequestMedia {
@@ -146,9 +413,91 @@ impl Conn {
             ChatMsg { text, .. } => {
                 println!("{text}");
             }
-            Movement { walk_speed, .. } => {
+            Movement {
+                default_accel,
+                air_accel,
+                fast_accel,
+                walk_speed,
+                crouch_speed,
+                fast_speed,
+                climb_speed,
+                jump_speed,
+                gravity,
+                fluidity,
+                smoothing,
+                sink,
+            } => {
+                self.events
+                    .send_event(GfxEvent::Movement(crate::gfx::MovementParams {
+                        default_accel,
+                        air_accel,
+                        fast_accel,
+                        walk_speed,
+                        crouch_speed,
+                        fast_speed,
+                        climb_speed,
+                        jump_speed,
+                        gravity,
+                        fluidity,
+                        smoothing,
+                        sink,
+                    }))
+                    .ok();
+            }
+            Fov {
+                fov,
+                multiplier,
+                transition_time,
+            } => {
+                self.events
+                    .send_event(GfxEvent::Fov {
+                        fov,
+                        multiplier,
+                        transition_time,
+                    })
+                    .ok();
+            }
+            EyeOffset { first, third } => {
                 self.events
-                    .send_event(GfxEvent::MovementSpeed(walk_speed))
+                    .send_event(GfxEvent::EyeOffset(first, third))
+                    .ok();
+            }
+            Privs { privs } => {
+                self.events
+                    .send_event(GfxEvent::Privs(privs.into_iter().collect()))
+                    .ok();
+            }
+            SkyParams(params) => {
+                self.events.send_event(GfxEvent::SkyParams(params)).ok();
+            }
+            SunParams(params) => {
+                self.events.send_event(GfxEvent::SunParams(params)).ok();
+            }
+            MoonParams(params) => {
+                self.events.send_event(GfxEvent::MoonParams(params)).ok();
+            }
+            StarParams(params) => {
+                self.events.send_event(GfxEvent::StarParams(params)).ok();
+            }
+            CloudParams(params) => {
+                self.events.send_event(GfxEvent::CloudParams(params)).ok();
+            }
+            TimeOfDay { time, speed } => {
+                self.events
+                    .send_event(GfxEvent::TimeOfDay(time, speed))
+                    .ok();
+            }
+            SetLighting {
+                shadow_intensity,
+                saturation,
+                exposure,
+            } => {
+                self.events
+                    .send_event(GfxEvent::Lighting {
+                        shadow_intensity,
+                        exposure: exposure.exposure_correction,
+                        saturation,
+                    })
                     .ok();
             }
             _ => {}